@@ -0,0 +1,48 @@
+//! Compares looking up routes in a group to matching them one by one.
+//!
+//! Run with `cargo bench --bench routing`.
+
+use std::{
+    hint::black_box,
+    time::{Duration, Instant},
+};
+
+use genuine::{Group, Path};
+use http::Method;
+
+const ROUTES: usize = 500;
+const LOOKUPS: u32 = 10_000;
+
+fn pattern(i: usize) -> String {
+    format!("/resource{i}/{{id}}/items")
+}
+
+fn measure(name: &str, mut lookup: impl FnMut(&str) -> bool) {
+    let paths: Vec<_> = (0..ROUTES)
+        .step_by(7)
+        .map(|i| format!("/resource{i}/42/items"))
+        .collect();
+
+    let start = Instant::now();
+    for n in 0..LOOKUPS {
+        let path = &paths[n as usize % paths.len()];
+        assert!(lookup(black_box(path)));
+    }
+    let elapsed: Duration = start.elapsed() / LOOKUPS;
+
+    println!("{name:<12} {elapsed:>10?} per lookup");
+}
+
+fn main() {
+    let mut group = Group::new("");
+    let mut paths = Vec::new();
+    for i in 0..ROUTES {
+        group.get(&pattern(i), (|| "item") as fn() -> _).unwrap();
+        paths.push(Path::new(pattern(i)).unwrap());
+    }
+
+    measure("linear scan", |path| {
+        paths.iter().any(|route| route.matches(path).is_some())
+    });
+    measure("trie", |path| group.find(&Method::GET, path).is_some());
+}
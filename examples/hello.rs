@@ -12,7 +12,8 @@ fn main() {
             .unwrap()
     };
 
-    app.get("/", greet as fn() -> _);
+    app.get("/", greet as fn() -> _)
+        .expect("invalid route path");
 
     app.run(([127, 0, 0, 1], 3000)).unwrap();
 }
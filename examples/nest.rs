@@ -0,0 +1,23 @@
+use genuine::App;
+
+/// The first version of the API, set up on its own.
+fn api_v1() -> App {
+    let mut api = App::new();
+
+    api.get("/users", (|| r#"["alice","bob"]"#) as fn() -> _)
+        .expect("invalid route path");
+    api.get("/users/{id}", (|| r#"{"name":"alice"}"#) as fn() -> _)
+        .expect("invalid route path");
+
+    api
+}
+
+fn main() {
+    let mut app = App::new();
+
+    app.get("/health", (|| "ok") as fn() -> _)
+        .expect("invalid route path");
+    app.nest("/v1", api_v1());
+
+    app.run(([127, 0, 0, 1], 3000)).unwrap();
+}
@@ -0,0 +1,18 @@
+use genuine::App;
+
+fn main() {
+    let mut app = App::new();
+
+    app.get("/", (|| "Press Ctrl+C to stop the server") as fn() -> _)
+        .expect("invalid route path");
+
+    let ctrl_c = async {
+        tokio::signal::ctrl_c()
+            .await
+            .expect("failed to listen for Ctrl+C");
+        println!("shutting down");
+    };
+
+    app.run_with_shutdown(([127, 0, 0, 1], 3000), ctrl_c)
+        .unwrap();
+}
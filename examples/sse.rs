@@ -0,0 +1,32 @@
+use std::time::Duration;
+
+use futures::stream::{self, Stream};
+use genuine::{
+    sse::{Event, Sse},
+    App,
+};
+
+/// Counts down from ten, one event per second.
+fn countdown() -> Sse<impl Stream<Item = Event>> {
+    let events = stream::unfold(10, |n| async move {
+        if n < 0 {
+            return None;
+        }
+        tokio::time::sleep(Duration::from_secs(1)).await;
+        let event = Event::new(n.to_string())
+            .event("countdown")
+            .id(n.to_string());
+        Some((event, n - 1))
+    });
+
+    Sse::new(events).keep_alive(Duration::from_secs(15))
+}
+
+fn main() {
+    let mut app = App::new();
+
+    app.get("/countdown", countdown as fn() -> _)
+        .expect("invalid route path");
+
+    app.run(([127, 0, 0, 1], 3000)).unwrap();
+}
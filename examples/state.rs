@@ -0,0 +1,41 @@
+use std::{sync::Arc, time::Duration};
+
+use genuine::{extract::State, App, FromRequest, Handler, Request};
+use http::Response;
+use hyper::body::Bytes;
+
+/// Stands in for a real database connection pool.
+struct Pool {
+    url: String,
+}
+
+impl Pool {
+    async fn connect(url: &str) -> Pool {
+        // pretend to establish connections
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        Pool {
+            url: url.to_owned(),
+        }
+    }
+}
+
+struct Status;
+
+impl Handler for Status {
+    fn handle_request(&self, req: Request) -> Response<Bytes> {
+        let State(pool) = State::<Arc<Pool>>::from_request(&req).unwrap();
+        Response::new(format!("connected to {}", pool.url).into())
+    }
+}
+
+#[tokio::main]
+async fn main() -> std::io::Result<()> {
+    let mut app = App::new();
+
+    app.init_state(|| async { Arc::new(Pool::connect("postgres://localhost/app").await) })
+        .await;
+
+    app.get("/status", Status).expect("invalid route path");
+
+    app.serve(([127, 0, 0, 1], 3000)).await
+}
@@ -0,0 +1,35 @@
+use std::time::Duration;
+
+use genuine::{body::BodyStream, App};
+use http::{
+    header::{CACHE_CONTROL, CONTENT_TYPE},
+    Response,
+};
+use hyper::body::Bytes;
+
+/// Sends ten server-sent events, one every second, without buffering them.
+fn ticks() -> Response<BodyStream> {
+    let ticks = futures::stream::unfold(1, |tick| async move {
+        if tick > 10 {
+            return None;
+        }
+        tokio::time::sleep(Duration::from_secs(1)).await;
+        let event = Bytes::from(format!("data: tick {tick}\n\n"));
+        Some((Ok(event), tick + 1))
+    });
+
+    Response::builder()
+        .header(CONTENT_TYPE, "text/event-stream")
+        .header(CACHE_CONTROL, "no-cache")
+        .body(BodyStream::new(ticks))
+        .unwrap()
+}
+
+fn main() {
+    let mut app = App::new();
+
+    app.get("/ticks", ticks as fn() -> _)
+        .expect("invalid route path");
+
+    app.run(([127, 0, 0, 1], 3000)).unwrap();
+}
@@ -1,14 +1,15 @@
-use std::net::SocketAddr;
+use std::{future::Future, net::SocketAddr, sync::Arc};
 
-use http::Method;
+use http::{header::IntoHeaderName, HeaderValue, Method};
 
 use crate::{
+    middleware::Middleware,
     router::{
         groups::Group,
-        routes::{Handler, Route},
-        Router,
+        routes::{AsyncHandler, Handler, ParseError, Route, RouteInfo},
+        Router, TrailingSlashPolicy,
     },
-    server::Server,
+    server::{BindFuture, Server, ServerBuilder},
 };
 
 pub struct App {
@@ -39,7 +40,168 @@ impl App {
         self
     }
 
-    pub fn add<H>(&mut self, method: Method, pattern: &str, handle: H) -> &mut Route
+    /// Mount all routes of `other` below `prefix`, e.g. to compose an app
+    /// from parts that are set up independently.
+    ///
+    /// The middlewares, default headers and state of `other` only apply to
+    /// its own routes. Its other settings, like the maximum body size, are
+    /// replaced by those of this app.
+    ///
+    /// The root route `/` of `other` is routed at `prefix` itself, like the
+    /// root of a [`Group`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `prefix` is not a valid group prefix, see
+    /// [`Group::try_new`], or if a nested route conflicts with a route of
+    /// this app, see [`Group::try_add_route`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use genuine::App;
+    ///
+    /// let mut v1 = App::new();
+    /// v1.get("/users", (|| "users") as fn() -> _).unwrap();
+    ///
+    /// let mut app = App::new();
+    /// app.nest("/v1", v1);
+    /// ```
+    pub fn nest(&mut self, prefix: &str, other: App) -> &mut App {
+        if let Err(err) = self.router.nest(prefix, other.router) {
+            panic!("cannot nest app at `{prefix}`: {err}");
+        }
+        self
+    }
+
+    /// Set a header on every response, unless the route, its group or its
+    /// handler set it already.
+    pub fn default_response_header<K>(&mut self, name: K, value: HeaderValue) -> &mut App
+    where
+        K: IntoHeaderName,
+    {
+        self.router.headers.insert(name, value);
+        self
+    }
+
+    /// Add a middleware that runs before the handler of every route.
+    pub fn before<M>(&mut self, middleware: M) -> &mut App
+    where
+        M: Middleware + Send + Sync + 'static,
+    {
+        self.router.before.push(Box::new(middleware));
+        self
+    }
+
+    /// Add a middleware that runs after the handler of every route.
+    pub fn after<M>(&mut self, middleware: M) -> &mut App
+    where
+        M: Middleware + Send + Sync + 'static,
+    {
+        self.router.after.push(Box::new(middleware));
+        self
+    }
+
+    /// Answer requests with bodies larger than `bytes` with `413 Payload Too
+    /// Large`. The default is 64 KiB.
+    ///
+    /// Routes can override this with
+    /// [`Route::max_body_size`](crate::Route::max_body_size), e.g. to allow
+    /// file uploads.
+    pub fn max_body_size(&mut self, bytes: u64) -> &mut App {
+        self.router.max_body_size = bytes;
+        self
+    }
+
+    /// Answer `OPTIONS` requests without an `OPTIONS` route with `405 Method
+    /// Not Allowed` instead of `204 No Content` and an `Allow` header.
+    pub fn disable_auto_options(&mut self) -> &mut App {
+        self.router.auto_options = false;
+        self
+    }
+
+    /// Answer requests to paths without routes with `handler`, instead of
+    /// `404 Not Found`.
+    ///
+    /// Only the middlewares of the app run around it.
+    ///
+    /// ```
+    /// use genuine::App;
+    /// use http::{StatusCode, Uri};
+    ///
+    /// fn not_found(uri: Uri) -> (StatusCode, String) {
+    ///     (StatusCode::NOT_FOUND, format!("nothing at {}", uri.path()))
+    /// }
+    ///
+    /// let mut app = App::new();
+    /// app.not_found(not_found as fn(_) -> _);
+    /// ```
+    pub fn not_found<H: Handler>(&mut self, handler: H) -> &mut App {
+        self.router.not_found = Some(Arc::new(handler));
+        self
+    }
+
+    /// Answer requests to paths without routes for their method with
+    /// `handler`, instead of `405 Method Not Allowed`.
+    ///
+    /// The response gets an `Allow` header listing the methods of the path,
+    /// unless the handler sets one. Only the middlewares of the app run
+    /// around it.
+    pub fn method_not_allowed<H: Handler>(&mut self, handler: H) -> &mut App {
+        self.router.method_not_allowed = Some(Arc::new(handler));
+        self
+    }
+
+    /// Set how trailing slashes in request paths are handled. By default,
+    /// they are ignored.
+    pub fn trailing_slash(&mut self, policy: TrailingSlashPolicy) -> &mut App {
+        self.router.trailing_slash = policy;
+        self
+    }
+
+    /// Share `state` with all handlers, which get it through the
+    /// [`State`](crate::extract::State) extractor.
+    ///
+    /// Each type can be registered once, registering it again replaces the
+    /// previous value.
+    pub fn with_state<T>(&mut self, state: T) -> &mut App
+    where
+        T: Clone + Send + Sync + 'static,
+    {
+        self.router.state.insert(state);
+        self
+    }
+
+    /// Sign cookies with `key`, see [`CookieJar`](crate::cookies::CookieJar).
+    ///
+    /// Use the same key for all instances of the app, and keep it secret:
+    /// anyone with the key can forge signed cookies.
+    #[cfg(feature = "cookie")]
+    pub fn cookie_key(&mut self, key: crate::cookies::Key) -> &mut App {
+        self.with_state(crate::cookies::CookieKey(key))
+    }
+
+    /// Like [`App::with_state`], but the state is created by awaiting
+    /// `init`, e.g. to open a database connection pool before the server
+    /// starts accepting connections.
+    ///
+    /// Use [`App::serve`] to serve the app from the same runtime.
+    pub async fn init_state<T, F, Fut>(&mut self, init: F) -> &mut App
+    where
+        T: Clone + Send + Sync + 'static,
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = T>,
+    {
+        let state = init().await;
+        self.with_state(state)
+    }
+
+    pub fn add<H>(
+        &mut self,
+        method: Method,
+        pattern: &str,
+        handle: H,
+    ) -> Result<&mut Route, ParseError>
     where
         H: Handler,
     {
@@ -50,19 +212,342 @@ impl App {
             .add(method, pattern, handle)
     }
 
-    pub fn get<H>(&mut self, pattern: &str, handle: H) -> &mut Route
+    /// Like [`App::add`], but returns the app, so that routes can be added
+    /// in a chain.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `pattern` is not a valid route path.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use genuine::App;
+    /// use http::Method;
+    ///
+    /// let mut app = App::new();
+    ///
+    /// app.route(Method::GET, "/users", (|| "List users") as fn() -> _)
+    ///     .route(Method::POST, "/users", (|| "Create user") as fn() -> _);
+    /// ```
+    pub fn route<H>(&mut self, method: Method, pattern: &str, handle: H) -> &mut App
+    where
+        H: Handler,
+    {
+        self.router
+            .groups
+            .get_mut(0)
+            .unwrap()
+            .route(method, pattern, handle);
+        self
+    }
+
+    /// Like [`App::add`], but with an [`AsyncHandler`].
+    pub fn add_async<H>(
+        &mut self,
+        method: Method,
+        pattern: &str,
+        handle: H,
+    ) -> Result<&mut Route, ParseError>
+    where
+        H: AsyncHandler,
+    {
+        self.router
+            .groups
+            .get_mut(0)
+            .unwrap()
+            .add_async(method, pattern, handle)
+    }
+
+    pub fn add_route(&mut self, route: Route) -> &mut Route {
+        self.router.groups.get_mut(0).unwrap().add_route(route)
+    }
+
+    pub fn get<H>(&mut self, pattern: &str, handle: H) -> Result<&mut Route, ParseError>
     where
         H: Handler,
     {
         self.add(Method::GET, pattern, handle)
     }
 
+    pub fn post<H>(&mut self, pattern: &str, handle: H) -> Result<&mut Route, ParseError>
+    where
+        H: Handler,
+    {
+        self.add(Method::POST, pattern, handle)
+    }
+
+    pub fn put<H>(&mut self, pattern: &str, handle: H) -> Result<&mut Route, ParseError>
+    where
+        H: Handler,
+    {
+        self.add(Method::PUT, pattern, handle)
+    }
+
+    pub fn patch<H>(&mut self, pattern: &str, handle: H) -> Result<&mut Route, ParseError>
+    where
+        H: Handler,
+    {
+        self.add(Method::PATCH, pattern, handle)
+    }
+
+    pub fn delete<H>(&mut self, pattern: &str, handle: H) -> Result<&mut Route, ParseError>
+    where
+        H: Handler,
+    {
+        self.add(Method::DELETE, pattern, handle)
+    }
+
+    /// Add a `HEAD` route, which takes priority over answering `HEAD`
+    /// requests with the `GET` route of the path.
+    pub fn head<H>(&mut self, pattern: &str, handle: H) -> Result<&mut Route, ParseError>
+    where
+        H: Handler,
+    {
+        self.add(Method::HEAD, pattern, handle)
+    }
+
+    pub fn options<H>(&mut self, pattern: &str, handle: H) -> Result<&mut Route, ParseError>
+    where
+        H: Handler,
+    {
+        self.add(Method::OPTIONS, pattern, handle)
+    }
+
+    /// Add a route for any method, e.g. for a proxy, see
+    /// [`Group::any`].
+    pub fn any<H>(&mut self, pattern: &str, handle: H) -> Result<&mut Route, ParseError>
+    where
+        H: Handler,
+    {
+        self.router.groups.get_mut(0).unwrap().any(pattern, handle)
+    }
+
+    /// Register a handler for `CONNECT` requests, which establish tunnels.
+    ///
+    /// `CONNECT` requests target an authority (`host:port`) instead of a
+    /// path, so `authority_pattern` is matched against that, e.g.
+    /// `"example.com:443"`, or `"{authority}"` to accept any target. Use
+    /// [`Request::tunnel_authority`](crate::Request::tunnel_authority) to get
+    /// the requested host and port.
+    pub fn connect<H>(
+        &mut self,
+        authority_pattern: &str,
+        handle: H,
+    ) -> Result<&mut Route, ParseError>
+    where
+        H: Handler,
+    {
+        self.add(Method::CONNECT, &format!("/{authority_pattern}"), handle)
+    }
+
+    /// Returns all routes of the app, e.g. to print a routing table.
+    ///
+    /// Routes are listed group by group, in the order the groups were
+    /// mounted, starting with the routes added to the app itself. Within a
+    /// group, they are sorted by path and method.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use genuine::App;
+    ///
+    /// let mut app = App::new();
+    /// app.get("/users", (|| "users") as fn() -> _).unwrap();
+    /// app.post("/users", (|| "created") as fn() -> _).unwrap();
+    ///
+    /// app.routes().for_each(|route| println!("{route}"));
+    /// ```
+    pub fn routes(&self) -> impl Iterator<Item = RouteInfo<'_>> {
+        self.router.groups.iter().flat_map(Group::route_infos)
+    }
+
+    /// Returns the URL of the route called `name`, see
+    /// [`Urls::url_for`](crate::url::Urls::url_for).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use genuine::App;
+    ///
+    /// let mut app = App::new();
+    /// app.get("/users/{id}", (|| "user") as fn() -> _)
+    ///     .unwrap()
+    ///     .name("user");
+    ///
+    /// assert_eq!(app.url_for("user", &[("id", "42")]).unwrap(), "/users/42");
+    /// ```
+    pub fn url_for(
+        &self,
+        name: &str,
+        params: &[(&str, &str)],
+    ) -> Result<String, crate::url::UrlGenerationError> {
+        self.router.url_for(name, params)
+    }
+
+    /// Freezes the app into its router, e.g. to respond without a server.
+    pub(crate) fn into_router(self) -> Router {
+        self.router
+    }
+
+    /// Serve the app on `addr`, blocking the current thread.
+    ///
+    /// This consumes the app, the router is frozen once the server starts. It
+    /// is thus a compile-time error to register routes afterwards:
+    ///
+    /// ```compile_fail
+    /// use genuine::App;
+    ///
+    /// let mut app = App::new();
+    ///
+    /// app.run(([127, 0, 0, 1], 3000)).unwrap();
+    ///
+    /// app.get("/", (|| "Hello world!") as fn() -> _).unwrap();
+    /// ```
     pub fn run<A: Into<SocketAddr>>(self, addr: A) -> std::io::Result<()> {
-        let server = Server::new(addr.into(), self.router);
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        rt.block_on(async {
+            let (addr, serving) = self.bind_with_addr(addr)?;
+            tracing::info!(%addr, "listening");
+            serving.await
+        })
+    }
+
+    /// Bind the app to `addr` from a running tokio runtime, and return the
+    /// address it listens on, with a future that serves it.
+    ///
+    /// With port `0`, the OS picks a free port, so tests can start the app
+    /// in the background and then connect to it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use genuine::App;
+    /// use tokio::{
+    ///     io::{AsyncReadExt, AsyncWriteExt},
+    ///     net::TcpStream,
+    /// };
+    ///
+    /// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+    /// let mut app = App::new();
+    /// app.get("/hello", (|| "Hello world!") as fn() -> _).unwrap();
+    ///
+    /// let (addr, serving) = app.bind_with_addr(([127, 0, 0, 1], 0)).unwrap();
+    /// tokio::spawn(serving);
+    ///
+    /// let mut client = TcpStream::connect(addr).await.unwrap();
+    /// client
+    ///     .write_all(b"GET /hello HTTP/1.1\r\nhost: localhost\r\nconnection: close\r\n\r\n")
+    ///     .await
+    ///     .unwrap();
+    /// let mut res = String::new();
+    /// client.read_to_string(&mut res).await.unwrap();
+    /// assert!(res.ends_with("Hello world!"));
+    /// # });
+    /// ```
+    pub fn bind_with_addr<A: Into<SocketAddr>>(
+        self,
+        addr: A,
+    ) -> std::io::Result<(SocketAddr, BindFuture)> {
+        Server::new(addr.into(), self).bind_with_addr()
+    }
+
+    /// Like [`App::run`], but with sockets configured by `builder`.
+    pub fn run_with<A: Into<SocketAddr>>(
+        self,
+        addr: A,
+        builder: ServerBuilder,
+    ) -> std::io::Result<()> {
+        let server = builder.build(addr.into(), self);
+
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        rt.block_on(server.bind())
+    }
+
+    /// Like [`App::run`], but listens on all of `addrs`, see
+    /// [`Server::bind_all`].
+    ///
+    /// ```no_run
+    /// use std::net::SocketAddr;
+    ///
+    /// use genuine::App;
+    ///
+    /// let app = App::new();
+    ///
+    /// let addrs: [SocketAddr; 2] = ["0.0.0.0:8080".parse().unwrap(), "[::]:8080".parse().unwrap()];
+    /// app.run_multi(&addrs).unwrap();
+    /// ```
+    pub fn run_multi(self, addrs: &[SocketAddr]) -> std::io::Result<()> {
+        let server = Server::from_app(self);
+
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        rt.block_on(server.bind_all(addrs.iter().copied()))
+    }
+
+    /// Like [`App::run`], but listens on the Unix domain socket at `path`.
+    #[cfg(unix)]
+    pub fn run_unix<P: AsRef<std::path::Path>>(self, path: P) -> std::io::Result<()> {
+        let server = Server::from_app(self);
+
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        rt.block_on(server.bind_unix(path))
+    }
+
+    /// Like [`App::run`], but serves HTTPS, see [`tls`](crate::tls).
+    #[cfg(feature = "tls")]
+    pub fn run_tls<A: Into<SocketAddr>>(
+        self,
+        addr: A,
+        config: std::sync::Arc<crate::tls::rustls::ServerConfig>,
+    ) -> std::io::Result<()> {
+        let server = Server::new(addr.into(), self).tls(config);
 
         let rt = tokio::runtime::Runtime::new().unwrap();
         rt.block_on(server.bind())
     }
+
+    /// Like [`App::run_tls`], but with the server configured by `builder`,
+    /// e.g. to serve HTTP/2, see [`tls`](crate::tls).
+    #[cfg(feature = "tls")]
+    pub fn run_tls_with<A: Into<SocketAddr>>(
+        self,
+        addr: A,
+        builder: ServerBuilder,
+        config: std::sync::Arc<crate::tls::rustls::ServerConfig>,
+    ) -> std::io::Result<()> {
+        let server = builder.build(addr.into(), self).tls(config);
+
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        rt.block_on(server.bind())
+    }
+
+    /// Like [`App::run`], but shuts the server down once `signal`
+    /// completes, e.g. on `Ctrl+C`, see [`Server::with_shutdown`].
+    ///
+    /// The signal is polled on the runtime of the server, so it can use
+    /// tokio, e.g. `tokio::signal::ctrl_c()` with tokio's `signal` feature.
+    ///
+    /// To configure the server as well, e.g. its [drain
+    /// timeout](ServerBuilder::drain_timeout), use
+    /// [`ServerBuilder::build`] and [`Server::with_shutdown`].
+    pub fn run_with_shutdown<A, F>(self, addr: A, signal: F) -> std::io::Result<()>
+    where
+        A: Into<SocketAddr>,
+        F: Future<Output = ()> + Send + 'static,
+    {
+        let server = Server::new(addr.into(), self).with_shutdown(signal);
+
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        rt.block_on(server.bind())
+    }
+
+    /// Serve the app on `addr` from an already running tokio runtime.
+    ///
+    /// Unlike [`App::run`] this does not start a runtime of its own, so the
+    /// app can be set up asynchronously first, see [`App::init_state`].
+    pub async fn serve<A: Into<SocketAddr>>(self, addr: A) -> std::io::Result<()> {
+        Server::new(addr.into(), self).bind().await
+    }
 }
 
 impl Default for App {
@@ -70,3 +555,119 @@ impl Default for App {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use http::Response;
+    use http_body_util::{BodyExt, Full};
+    use hyper::body::Bytes;
+
+    use super::*;
+    use crate::{extract::State, FromRequest, Request};
+
+    struct Pool {
+        users: Vec<&'static str>,
+    }
+
+    struct ListUsers;
+
+    impl Handler for ListUsers {
+        fn handle_request(&self, req: Request) -> Response<Bytes> {
+            let State(pool) = State::<Arc<Pool>>::from_request(&req).unwrap();
+            Response::new(pool.users.join(", ").into())
+        }
+    }
+
+    #[test]
+    fn routes_are_listed_in_a_deterministic_order() {
+        let hello = (|| "hello") as fn() -> _;
+
+        let mut app = App::new();
+        app.post("/users", hello).unwrap();
+        app.get("/users", hello).unwrap().name("users");
+        app.delete("/users/{id}", hello).unwrap();
+        app.mount("/admin", |group| {
+            group.get("/stats", hello).unwrap();
+        });
+
+        let routes: Vec<_> = app.routes().map(|route| route.to_string()).collect();
+        assert_eq!(
+            routes,
+            [
+                "GET     /users  (users)",
+                "POST    /users",
+                "DELETE  /users/{id}",
+                "GET     /admin/stats",
+            ]
+        );
+
+        let stats = app.routes().last().unwrap();
+        assert_eq!(stats.method, Method::GET);
+        assert_eq!(stats.path, "/admin/stats");
+        assert_eq!(stats.group_prefix, "/admin");
+        assert_eq!(stats.name, None);
+    }
+
+    #[test]
+    fn run_with_shutdown_returns_once_signalled() {
+        let app = App::new();
+        let (stop, stopped) = tokio::sync::oneshot::channel::<()>();
+        stop.send(()).unwrap();
+
+        app.run_with_shutdown(([127, 0, 0, 1], 0), async {
+            stopped.await.ok();
+        })
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn state_from_async_init_is_available_in_handlers() {
+        let mut app = App::new();
+        app.init_state(|| async {
+            tokio::task::yield_now().await;
+            Arc::new(Pool {
+                users: vec!["alice", "bob"],
+            })
+        })
+        .await;
+        app.get("/users", ListUsers).unwrap();
+
+        let req = http::Request::get("/users")
+            .body(Full::<Bytes>::default())
+            .unwrap();
+        let res = app.router.respond(req).await.unwrap();
+        let body = res.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(body, "alice, bob");
+    }
+
+    #[cfg(feature = "serde_json")]
+    #[tokio::test]
+    async fn state_and_body_are_extracted_together() {
+        use crate::extract::Json;
+
+        #[derive(serde::Deserialize)]
+        struct NewUser {
+            name: String,
+        }
+
+        fn create_user(State(pool): State<Arc<Pool>>, Json(user): Json<NewUser>) -> String {
+            format!("{} joined {}", user.name, pool.users.join(", "))
+        }
+
+        let mut app = App::new();
+        app.with_state(Arc::new(Pool {
+            users: vec!["alice", "bob"],
+        }));
+        app.post("/users", create_user as fn(_, _) -> _).unwrap();
+
+        let req = http::Request::post("/users")
+            .header("content-type", "application/json")
+            .body(Full::new(Bytes::from_static(br#"{"name":"carol"}"#)))
+            .unwrap();
+        let res = app.router.respond(req).await.unwrap();
+        let body = res.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(body, "carol joined alice, bob");
+    }
+}
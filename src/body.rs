@@ -0,0 +1,314 @@
+//! Streaming request and response bodies.
+
+use std::{
+    fmt, io,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{ready, Context, Poll},
+};
+
+use futures_core::{stream::BoxStream, Stream};
+use http::{Request, Response};
+use http_body_util::{combinators::UnsyncBoxBody, BodyExt, Limited};
+use hyper::body::{Body, Frame};
+use tokio::io::{AsyncRead, ReadBuf};
+
+use crate::{BoxError, FromRequest, Rejection};
+
+/// The bytes of buffered bodies, as used by hyper.
+pub use hyper::body::Bytes;
+
+/// A response body that is sent chunk by chunk as the stream yields it,
+/// instead of being buffered in full.
+///
+/// Handlers respond with a stream by returning a `Response<BodyStream>`.
+/// Middlewares see such responses with an empty body.
+///
+/// # Examples
+///
+/// ```
+/// use genuine::body::BodyStream;
+/// use http::Response;
+/// use hyper::body::Bytes;
+///
+/// fn download() -> Response<BodyStream> {
+///     let chunks = [Ok(Bytes::from("large ")), Ok(Bytes::from("file"))];
+///     Response::new(BodyStream::new(futures::stream::iter(chunks)))
+/// }
+/// ```
+pub struct BodyStream(BoxStream<'static, Result<Bytes, io::Error>>);
+
+impl BodyStream {
+    pub fn new<S>(stream: S) -> Self
+    where
+        S: Stream<Item = Result<Bytes, io::Error>> + Send + 'static,
+    {
+        Self(Box::pin(stream))
+    }
+}
+
+impl From<BoxStream<'static, Result<Bytes, io::Error>>> for BodyStream {
+    fn from(stream: BoxStream<'static, Result<Bytes, io::Error>>) -> Self {
+        Self(stream)
+    }
+}
+
+impl fmt::Debug for BodyStream {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BodyStream").finish_non_exhaustive()
+    }
+}
+
+/// Carries the [`BodyStream`] of a response through the middlewares, in the
+/// extensions of a response with an empty body.
+#[derive(Clone)]
+pub(crate) struct Streaming(Arc<Mutex<Option<BodyStream>>>);
+
+impl Streaming {
+    pub(crate) fn attach(res: &mut Response<Bytes>, stream: BodyStream) {
+        let streaming = Streaming(Arc::new(Mutex::new(Some(stream))));
+        res.extensions_mut().insert(streaming);
+    }
+
+    pub(crate) fn take(res: &mut Response<Bytes>) -> Option<BodyStream> {
+        let Streaming(stream) = res.extensions_mut().remove::<Streaming>()?;
+        let mut stream = stream.lock().unwrap_or_else(|err| err.into_inner());
+        stream.take()
+    }
+}
+
+/// The [`Body`] of a streaming response.
+pub(crate) struct StreamingBody(pub(crate) BodyStream);
+
+impl Body for StreamingBody {
+    type Data = Bytes;
+    type Error = BoxError;
+
+    fn poll_frame(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Bytes>, BoxError>>> {
+        match ready!(self.0 .0.as_mut().poll_next(cx)) {
+            Some(Ok(chunk)) => Poll::Ready(Some(Ok(Frame::data(chunk)))),
+            Some(Err(err)) => Poll::Ready(Some(Err(err.into()))),
+            None => Poll::Ready(None),
+        }
+    }
+}
+
+/// Carries the body of a request to the [`BodyReader`] of its handler, for
+/// routes that [stream their body](crate::Route::stream_body).
+#[derive(Clone)]
+pub(crate) struct StreamedBody(Arc<Mutex<Option<UnsyncBoxBody<Bytes, BoxError>>>>);
+
+impl StreamedBody {
+    /// Moves the body of `req` into its extensions, limited to `limit`
+    /// bytes, and leaves it with an empty body.
+    pub(crate) fn attach<B>(req: Request<B>, limit: u64) -> Request<Bytes>
+    where
+        B: Body<Data = Bytes> + Send + 'static,
+        B::Error: Into<BoxError>,
+    {
+        let (mut parts, body) = req.into_parts();
+        let limit = usize::try_from(limit).unwrap_or(usize::MAX);
+        let body = Limited::new(body, limit).boxed_unsync();
+        parts
+            .extensions
+            .insert(StreamedBody(Arc::new(Mutex::new(Some(body)))));
+        Request::from_parts(parts, Bytes::new())
+    }
+}
+
+/// The route does not [stream its body](crate::Route::stream_body), or
+/// another [`BodyReader`] took it already.
+#[derive(Debug, thiserror::Error, PartialEq, Eq)]
+#[error("request body is not streamed")]
+pub struct BodyNotStreamed;
+
+impl Rejection for BodyNotStreamed {}
+
+/// Reads a request body as it arrives, without buffering all of it.
+///
+/// [`BodyReader`] implements [`AsyncRead`], so the body can be piped into a
+/// file or parser chunk by chunk. Trailers are skipped.
+///
+/// Handlers of routes that [stream their body](crate::Route::stream_body)
+/// extract it.
+pub struct BodyReader<B = UnsyncBoxBody<Bytes, BoxError>> {
+    body: B,
+    chunk: Bytes,
+    done: bool,
+}
+
+impl<B> BodyReader<B> {
+    /// Create a reader over the body of `req`.
+    pub fn new(req: Request<B>) -> Self {
+        Self {
+            body: req.into_body(),
+            chunk: Bytes::new(),
+            done: false,
+        }
+    }
+}
+
+impl FromRequest for BodyReader {
+    type Error = BodyNotStreamed;
+
+    fn from_request(req: &crate::Request) -> Result<Self, Self::Error> {
+        let StreamedBody(body) = req.extensions().get().ok_or(BodyNotStreamed)?;
+        let body = body.lock().unwrap_or_else(|err| err.into_inner()).take();
+        Ok(Self {
+            body: body.ok_or(BodyNotStreamed)?,
+            chunk: Bytes::new(),
+            done: false,
+        })
+    }
+}
+
+impl<B> AsyncRead for BodyReader<B>
+where
+    B: Body<Data = Bytes> + Unpin,
+    B::Error: Into<BoxError>,
+{
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        loop {
+            if !self.chunk.is_empty() {
+                let len = self.chunk.len().min(buf.remaining());
+                buf.put_slice(&self.chunk.split_to(len));
+                return Poll::Ready(Ok(()));
+            }
+
+            if self.done {
+                return Poll::Ready(Ok(()));
+            }
+
+            match ready!(Pin::new(&mut self.body).poll_frame(cx)) {
+                Some(Ok(frame)) => {
+                    if let Ok(data) = frame.into_data() {
+                        self.chunk = data;
+                    }
+                }
+                Some(Err(err)) => return Poll::Ready(Err(io::Error::other(err.into()))),
+                None => self.done = true,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::Infallible;
+
+    use http_body_util::Full;
+    use hyper::body::{Bytes, Frame};
+    use tokio::io::AsyncReadExt;
+
+    use super::*;
+
+    /// A body that yields `chunks` chunks of `chunk_size` bytes each.
+    struct Chunked {
+        chunks: usize,
+        chunk_size: usize,
+    }
+
+    impl Body for Chunked {
+        type Data = Bytes;
+        type Error = Infallible;
+
+        fn poll_frame(
+            mut self: Pin<&mut Self>,
+            _: &mut Context<'_>,
+        ) -> Poll<Option<Result<Frame<Bytes>, Infallible>>> {
+            if self.chunks == 0 {
+                return Poll::Ready(None);
+            }
+
+            let byte = (self.chunks % 256) as u8;
+            self.chunks -= 1;
+            let chunk = Bytes::from(vec![byte; self.chunk_size]);
+            Poll::Ready(Some(Ok(Frame::data(chunk))))
+        }
+    }
+
+    #[tokio::test]
+    async fn reads_whole_body() {
+        let body = Chunked {
+            chunks: 256,
+            chunk_size: 4096,
+        };
+        let mut reader = BodyReader::new(Request::new(body));
+
+        let mut buffer = Vec::new();
+        reader.read_to_end(&mut buffer).await.unwrap();
+
+        assert_eq!(buffer.len(), 1024 * 1024);
+        for (i, chunk) in buffer.chunks(4096).enumerate() {
+            let byte = ((256 - i) % 256) as u8;
+            assert!(chunk.iter().all(|&x| x == byte));
+        }
+    }
+
+    #[tokio::test]
+    async fn reads_into_small_buffers() {
+        let body = Chunked {
+            chunks: 2,
+            chunk_size: 10,
+        };
+        let mut reader = BodyReader::new(Request::new(body));
+
+        let mut buffer = [0; 3];
+        let mut total = 0;
+        loop {
+            match reader.read(&mut buffer).await.unwrap() {
+                0 => break,
+                n => total += n,
+            }
+        }
+
+        assert_eq!(total, 20);
+    }
+
+    async fn upload(mut body: BodyReader) -> String {
+        let mut received = Vec::new();
+        match body.read_to_end(&mut received).await {
+            Ok(len) => len.to_string(),
+            Err(err) => err.to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn streamed_bodies_reach_the_handler() {
+        let mut router = crate::router::Router::new();
+        router.groups[0]
+            .add_async(http::Method::POST, "/upload", upload as fn(_) -> _)
+            .unwrap()
+            .stream_body()
+            .max_body_size(64 * 1024);
+        router.groups[0]
+            .add_async(http::Method::POST, "/buffered", upload as fn(_) -> _)
+            .unwrap();
+        let send = |uri, chunks| {
+            let req = Request::post(uri).body(Chunked {
+                chunks,
+                chunk_size: 4096,
+            });
+            router.respond(req.unwrap())
+        };
+
+        let res = send("/upload", 16).await.unwrap();
+        let body = res.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(body, "65536");
+
+        let res = send("/upload", 17).await.unwrap();
+        let body = res.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(body, "length limit exceeded");
+
+        let req = Request::post("/buffered").body(Full::new(Bytes::from("hi")));
+        let res = router.respond(req.unwrap()).await.unwrap();
+        assert_eq!(res.status(), 422);
+    }
+}
@@ -0,0 +1,224 @@
+//! Cookies, parsed and sent with the [`cookie`](https://docs.rs/cookie)
+//! crate.
+//!
+//! Handlers read the cookies of a request with the [`CookieJar`] extractor,
+//! and send changes back by returning the jar along with the response.
+
+use http::{
+    header::{COOKIE, SET_COOKIE},
+    HeaderValue, Response,
+};
+use hyper::body::Bytes;
+
+pub use cookie::{Cookie, Key, SameSite};
+
+use crate::{FromRequest, IntoResponse, Request};
+
+/// The key that signs cookies, see [`App::cookie_key`](crate::App::cookie_key).
+#[derive(Clone)]
+pub(crate) struct CookieKey(pub(crate) Key);
+
+/// The cookies of a request, and those to send back.
+///
+/// Cookies added or removed are sent as `Set-Cookie` headers when the jar is
+/// returned with the response, as in `(jar, response)`.
+///
+/// If the app has a key, set with [`App::cookie_key`](crate::App::cookie_key),
+/// cookies can also be signed, so clients cannot tamper with them.
+///
+/// # Examples
+///
+/// ```
+/// use genuine::cookies::{Cookie, CookieJar};
+///
+/// fn visit(jar: CookieJar) -> (CookieJar, String) {
+///     let visits: u32 = jar
+///         .get("visits")
+///         .and_then(|cookie| cookie.value().parse().ok())
+///         .unwrap_or(0);
+///
+///     let jar = jar.add(Cookie::new("visits", (visits + 1).to_string()));
+///     (jar, format!("visit number {}", visits + 1))
+/// }
+/// ```
+#[derive(Clone)]
+pub struct CookieJar {
+    jar: cookie::CookieJar,
+    key: Option<Key>,
+}
+
+impl std::fmt::Debug for CookieJar {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CookieJar")
+            .field("jar", &self.jar)
+            .field("signed", &self.key.is_some())
+            .finish()
+    }
+}
+
+impl CookieJar {
+    /// Returns the cookie called `name`.
+    ///
+    /// Signed cookies are returned with their signature, use
+    /// [`CookieJar::get_signed`] to verify it.
+    pub fn get(&self, name: &str) -> Option<&Cookie<'static>> {
+        self.jar.get(name)
+    }
+
+    /// Returns the signed cookie called `name`, without its signature.
+    ///
+    /// Returns [`None`] if the cookie is missing, its signature is wrong,
+    /// e.g. because the client changed its value, or the app has no key.
+    pub fn get_signed(&self, name: &str) -> Option<Cookie<'static>> {
+        self.jar.signed(self.key.as_ref()?).get(name)
+    }
+
+    /// Add `cookie`, replacing any cookie of the same name.
+    #[allow(clippy::should_implement_trait)]
+    pub fn add<C: Into<Cookie<'static>>>(mut self, cookie: C) -> Self {
+        self.jar.add(cookie);
+        self
+    }
+
+    /// Like [`CookieJar::add`], but signs the value of `cookie`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the app has no key.
+    pub fn add_signed<C: Into<Cookie<'static>>>(mut self, cookie: C) -> Self {
+        let key = self
+            .key
+            .as_ref()
+            .expect("signed cookies need a key, see `App::cookie_key`");
+        self.jar.signed_mut(key).add(cookie);
+        self
+    }
+
+    /// Remove the cookie called `name`, which expires it on the client.
+    pub fn remove(mut self, name: &str) -> Self {
+        self.jar.remove(Cookie::from(name.to_owned()));
+        self
+    }
+
+    /// Returns all cookies.
+    pub fn iter(&self) -> impl Iterator<Item = &Cookie<'static>> {
+        self.jar.iter()
+    }
+}
+
+impl FromRequest for CookieJar {
+    type Error = std::convert::Infallible;
+
+    /// Parses all `Cookie` headers, skipping cookies that are malformed.
+    fn from_request(req: &Request) -> Result<Self, Self::Error> {
+        let mut jar = cookie::CookieJar::new();
+        let cookies = req
+            .headers()
+            .get_all(COOKIE)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| Cookie::split_parse(value.to_owned()))
+            .filter_map(Result::ok);
+        for cookie in cookies {
+            jar.add_original(cookie);
+        }
+
+        Ok(Self {
+            jar,
+            key: req.extensions().get::<CookieKey>().map(|key| key.0.clone()),
+        })
+    }
+}
+
+/// Adds a `Set-Cookie` header for every cookie added to or removed from the
+/// jar.
+impl<T: IntoResponse> IntoResponse for (CookieJar, T) {
+    fn into_response(self) -> Response<Bytes> {
+        let (jar, res) = self;
+        let mut res = res.into_response();
+        for cookie in jar.jar.delta() {
+            // cookies with invalid header values cannot be sent
+            if let Ok(value) = HeaderValue::from_str(&cookie.to_string()) {
+                res.headers_mut().append(SET_COOKIE, value);
+            }
+        }
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(cookies: &str, key: Option<&Key>) -> Request {
+        let mut req = http::Request::get("/")
+            .header(COOKIE, cookies)
+            .body(Bytes::new())
+            .unwrap();
+        if let Some(key) = key {
+            req.extensions_mut().insert(CookieKey(key.clone()));
+        }
+        Request::new(req, Vec::new())
+    }
+
+    fn set_cookies(res: &Response<Bytes>) -> Vec<&str> {
+        res.headers()
+            .get_all(SET_COOKIE)
+            .iter()
+            .map(|value| value.to_str().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn cookies_are_parsed_and_changes_sent_back() {
+        let jar = CookieJar::from_request(&request("theme=dark; lang=en", None)).unwrap();
+        assert_eq!(jar.get("theme").unwrap().value(), "dark");
+        assert_eq!(jar.get("lang").unwrap().value(), "en");
+
+        let jar = jar.add(Cookie::new("theme", "light")).remove("lang");
+        let res = (jar, "ok").into_response();
+
+        let mut cookies = set_cookies(&res);
+        cookies.sort();
+        assert_eq!(cookies.len(), 2);
+        assert!(cookies[0].starts_with("lang=; "), "{}", cookies[0]);
+        assert!(cookies[0].contains("Max-Age=0"), "{}", cookies[0]);
+        assert_eq!(cookies[1], "theme=light");
+    }
+
+    #[test]
+    fn unchanged_cookies_are_not_sent() {
+        let jar = CookieJar::from_request(&request("theme=dark", None)).unwrap();
+        assert!(set_cookies(&(jar, "ok").into_response()).is_empty());
+    }
+
+    #[test]
+    fn signed_cookies_round_trip() {
+        let key = Key::generate();
+        let jar = CookieJar::from_request(&request("", Some(&key))).unwrap();
+        let res = (jar.add_signed(Cookie::new("user", "alice")), "ok").into_response();
+        let signed = set_cookies(&res)[0].to_owned();
+        assert_ne!(signed, "user=alice");
+
+        let jar = CookieJar::from_request(&request(&signed, Some(&key))).unwrap();
+        assert_eq!(jar.get_signed("user").unwrap().value(), "alice");
+
+        let other = CookieJar::from_request(&request(&signed, Some(&Key::generate()))).unwrap();
+        assert!(other.get_signed("user").is_none());
+    }
+
+    #[test]
+    fn tampered_cookies_are_rejected() {
+        let key = Key::generate();
+        let jar = CookieJar::from_request(&request("", Some(&key))).unwrap();
+        let res = (jar.add_signed(Cookie::new("user", "alice")), "ok").into_response();
+        let tampered = set_cookies(&res)[0].replace("alice", "admin");
+
+        let jar = CookieJar::from_request(&request(&tampered, Some(&key))).unwrap();
+        assert!(jar.get("user").is_some());
+        assert!(jar.get_signed("user").is_none());
+
+        let unsigned = CookieJar::from_request(&request("user=admin", Some(&key))).unwrap();
+        assert!(unsigned.get_signed("user").is_none());
+    }
+}
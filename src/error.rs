@@ -0,0 +1,34 @@
+//! The error type of the crate.
+
+use crate::{url::UrlGenerationError, ParseError, RouteConflict};
+
+/// Any error of this crate, e.g. to use `?` on different errors while
+/// setting up and running an app.
+///
+/// # Examples
+///
+/// ```no_run
+/// use genuine::{App, GenuineError, Path};
+///
+/// fn main() -> Result<(), GenuineError> {
+///     let path = Path::new("/users/{id}".to_owned())?;
+///     println!("serving {}", path.as_ref());
+///
+///     let mut app = App::new();
+///     app.get(path.as_ref(), (|| "user") as fn() -> _)?;
+///     app.run(([127, 0, 0, 1], 3000))?;
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum GenuineError {
+    #[error("invalid route path:\n{0}")]
+    Parse(#[from] ParseError),
+    #[error(transparent)]
+    RouteConflict(#[from] RouteConflict),
+    #[error(transparent)]
+    UrlGeneration(#[from] UrlGenerationError),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
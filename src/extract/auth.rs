@@ -0,0 +1,104 @@
+use http::header::AUTHORIZATION;
+
+use crate::{FromRequest, Rejection, Request};
+
+/// A token from the `Authorization: Bearer <token>` header.
+///
+/// Use it as the credential of the
+/// [`Authenticate`](crate::middleware::Authenticate) middleware.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BearerToken(pub String);
+
+/// The request has no bearer token, see [`BearerToken`].
+#[derive(Debug, thiserror::Error, PartialEq, Eq)]
+#[error("missing or malformed bearer token")]
+pub struct MissingBearerToken;
+
+impl Rejection for MissingBearerToken {}
+
+impl FromRequest for BearerToken {
+    type Error = MissingBearerToken;
+
+    fn from_request(req: &Request) -> Result<Self, Self::Error> {
+        let value = req
+            .headers()
+            .get(AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .ok_or(MissingBearerToken)?;
+
+        let (scheme, token) = value.split_once(' ').ok_or(MissingBearerToken)?;
+        let token = token.trim();
+        if !scheme.eq_ignore_ascii_case("bearer") || token.is_empty() {
+            return Err(MissingBearerToken);
+        }
+
+        Ok(Self(token.to_owned()))
+    }
+}
+
+/// The credential that the [`Authenticate`](crate::middleware::Authenticate)
+/// middleware extracted from the request.
+///
+/// Unlike extracting `T` itself, this does not parse the request again.
+///
+/// # Examples
+///
+/// ```
+/// use genuine::extract::{Auth, BearerToken};
+///
+/// fn profile(Auth(BearerToken(token)): Auth<BearerToken>) -> String {
+///     format!("profile for {token}")
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Auth<T>(pub T);
+
+/// The request was not authenticated, because the
+/// [`Authenticate`](crate::middleware::Authenticate) middleware does not run
+/// for its route.
+#[derive(Debug, thiserror::Error, PartialEq, Eq)]
+#[error("request was not authenticated")]
+pub struct Unauthenticated;
+
+impl Rejection for Unauthenticated {}
+
+impl<T> FromRequest for Auth<T>
+where
+    T: Clone + Send + Sync + 'static,
+{
+    type Error = Unauthenticated;
+
+    fn from_request(req: &Request) -> Result<Self, Self::Error> {
+        req.extensions()
+            .get::<T>()
+            .cloned()
+            .map(Auth)
+            .ok_or(Unauthenticated)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use hyper::body::Bytes;
+
+    use super::*;
+
+    fn request(authorization: &str) -> Request {
+        let req = http::Request::get("/")
+            .header(AUTHORIZATION, authorization)
+            .body(Bytes::new())
+            .unwrap();
+        Request::new(req, Vec::new())
+    }
+
+    #[test]
+    fn bearer_token_is_parsed() {
+        let token = BearerToken::from_request(&request("Bearer s3cr3t"));
+        assert_eq!(token, Ok(BearerToken("s3cr3t".into())));
+
+        for invalid in ["Basic dXNlcg==", "Bearer", "Bearer  ", "s3cr3t"] {
+            let token = BearerToken::from_request(&request(invalid));
+            assert_eq!(token, Err(MissingBearerToken), "{invalid}");
+        }
+    }
+}
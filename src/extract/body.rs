@@ -0,0 +1,56 @@
+use std::{convert::Infallible, str::Utf8Error};
+
+use hyper::body::Bytes;
+
+use crate::{FromRequest, Rejection, Request};
+
+/// The raw request body, without any content type check.
+///
+/// The body is buffered already, so this only bumps a reference count.
+impl FromRequest for Bytes {
+    type Error = Infallible;
+
+    fn from_request(req: &Request) -> Result<Self, Self::Error> {
+        Ok(req.body().clone())
+    }
+}
+
+/// The request body as text, which must be valid UTF-8.
+impl Rejection for std::str::Utf8Error {}
+
+impl FromRequest for String {
+    type Error = Utf8Error;
+
+    fn from_request(req: &Request) -> Result<Self, Self::Error> {
+        std::str::from_utf8(req.body()).map(str::to_owned)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(body: &'static [u8]) -> Request {
+        Request::new(http::Request::new(Bytes::from_static(body)), Vec::new())
+    }
+
+    #[test]
+    fn bytes_share_the_body() {
+        let req = request(b"\x00\xff binary");
+
+        let body = Bytes::from_request(&req).unwrap();
+        assert_eq!(body, &b"\x00\xff binary"[..]);
+        assert_eq!(body.as_ptr(), req.body().as_ptr());
+    }
+
+    #[test]
+    fn string_requires_utf8() {
+        assert_eq!(
+            String::from_request(&request("grüß".as_bytes())).unwrap(),
+            "grüß"
+        );
+
+        let err = String::from_request(&request(b"caf\xe9")).unwrap_err();
+        assert_eq!(err.valid_up_to(), 3);
+    }
+}
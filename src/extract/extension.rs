@@ -0,0 +1,72 @@
+use crate::{FromRequest, Rejection, Request};
+
+/// Extracts a value that a middleware inserted into the extensions of the
+/// request.
+///
+/// # Examples
+///
+/// ```
+/// use genuine::{extract::Extension, middleware::Middleware};
+/// use http::{Request, Response};
+/// use hyper::body::Bytes;
+///
+/// #[derive(Clone)]
+/// struct Tenant(String);
+///
+/// struct ResolveTenant;
+///
+/// impl Middleware for ResolveTenant {
+///     fn before(&self, req: &mut Request<Bytes>) -> Option<Response<Bytes>> {
+///         let host = req.uri().host().unwrap_or("default").to_owned();
+///         req.extensions_mut().insert(Tenant(host));
+///         None
+///     }
+/// }
+///
+/// fn dashboard(Extension(Tenant(tenant)): Extension<Tenant>) -> String {
+///     format!("dashboard of {tenant}")
+/// }
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Extension<T>(pub T);
+
+/// The request has no extension of the requested type.
+#[derive(Debug, thiserror::Error)]
+#[error("no extension of type `{0}` was inserted")]
+pub struct MissingExtension(&'static str);
+
+impl Rejection for MissingExtension {}
+
+impl<T> FromRequest for Extension<T>
+where
+    T: Clone + Send + Sync + 'static,
+{
+    type Error = MissingExtension;
+
+    fn from_request(req: &Request) -> Result<Self, Self::Error> {
+        req.extensions()
+            .get::<T>()
+            .cloned()
+            .map(Extension)
+            .ok_or(MissingExtension(std::any::type_name::<T>()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use hyper::body::Bytes;
+
+    use super::*;
+
+    #[test]
+    fn extensions_are_extracted() {
+        let mut req = Request::new(http::Request::new(Bytes::new()), Vec::new());
+        req.extensions_mut().insert("alice");
+
+        let Extension(user) = Extension::<&str>::from_request(&req).unwrap();
+        assert_eq!(user, "alice");
+
+        let err = Extension::<u32>::from_request(&req).unwrap_err();
+        assert_eq!(err.to_string(), "no extension of type `u32` was inserted");
+    }
+}
@@ -0,0 +1,221 @@
+use http::{
+    header::{HeaderValue, CONTENT_TYPE},
+    Response, StatusCode,
+};
+use hyper::body::Bytes;
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{FromRequest, IntoResponse, Rejection, Request};
+
+const FORM_URLENCODED: &str = "application/x-www-form-urlencoded";
+
+/// Deserializes a URL-encoded form body into `T`, or serializes `T` into a
+/// URL-encoded response body.
+///
+/// As an extractor, the request must have a `Content-Type` of
+/// `application/x-www-form-urlencoded`. Forms are flat, use
+/// `#[serde(flatten)]` to split their fields across structs.
+///
+/// # Examples
+///
+/// ```
+/// use genuine::extract::Form;
+/// use serde::Deserialize;
+///
+/// #[derive(Deserialize)]
+/// struct Login {
+///     user: String,
+///     password: String,
+/// }
+///
+/// fn login(Form(login): Form<Login>) -> String {
+///     format!("Welcome back, {}!", login.user)
+/// }
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Form<T>(pub T);
+
+/// The request body could not be extracted as a [`Form`].
+#[derive(Debug, thiserror::Error)]
+pub enum FormRejection {
+    /// The `Content-Type` is not a URL-encoded form, or missing.
+    #[error("expected a form body, got content type {0:?}")]
+    WrongContentType(Option<String>),
+    #[error("missing form field `{0}`")]
+    MissingField(String),
+    #[error("invalid form body: {0}")]
+    Invalid(serde_urlencoded::de::Error),
+}
+
+impl From<serde_urlencoded::de::Error> for FormRejection {
+    fn from(err: serde_urlencoded::de::Error) -> Self {
+        // serde reports missing fields as "missing field `name`"
+        let missing = err
+            .to_string()
+            .strip_prefix("missing field `")
+            .and_then(|rest| rest.strip_suffix('`'))
+            .map(str::to_owned);
+
+        match missing {
+            Some(field) => Self::MissingField(field),
+            None => Self::Invalid(err),
+        }
+    }
+}
+
+impl IntoResponse for FormRejection {
+    fn into_response(self) -> Response<Bytes> {
+        let status = match self {
+            Self::WrongContentType(_) => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            Self::MissingField(_) | Self::Invalid(_) => StatusCode::BAD_REQUEST,
+        };
+
+        Response::builder()
+            .status(status)
+            .body(self.to_string().into())
+            .unwrap()
+    }
+}
+
+impl Rejection for FormRejection {
+    fn respond(self) -> Response<Bytes> {
+        self.into_response()
+    }
+}
+
+impl<T> FromRequest for Form<T>
+where
+    T: DeserializeOwned,
+{
+    type Error = FormRejection;
+
+    fn from_request(req: &Request) -> Result<Self, Self::Error> {
+        let content_type = req
+            .headers()
+            .get(CONTENT_TYPE)
+            .map(|value| String::from_utf8_lossy(value.as_bytes()).into_owned());
+
+        let is_form = content_type.as_deref().is_some_and(|content_type| {
+            let mime = content_type.split(';').next().unwrap_or_default();
+            mime.trim().eq_ignore_ascii_case(FORM_URLENCODED)
+        });
+        if !is_form {
+            return Err(FormRejection::WrongContentType(content_type));
+        }
+
+        Ok(Form(serde_urlencoded::from_bytes(req.body())?))
+    }
+}
+
+impl<T> IntoResponse for Form<T>
+where
+    T: Serialize,
+{
+    fn into_response(self) -> Response<Bytes> {
+        match serde_urlencoded::to_string(&self.0) {
+            Ok(body) => Response::builder()
+                .header(CONTENT_TYPE, HeaderValue::from_static(FORM_URLENCODED))
+                .body(body.into())
+                .unwrap(),
+            Err(err) => Response::builder()
+                .status(StatusCode::INTERNAL_SERVER_ERROR)
+                .body(err.to_string().into())
+                .unwrap(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    use super::*;
+
+    #[derive(Debug, PartialEq, Deserialize, Serialize)]
+    struct Address {
+        street: String,
+        city: String,
+    }
+
+    #[derive(Debug, PartialEq, Deserialize, Serialize)]
+    struct Signup {
+        name: String,
+        #[serde(flatten)]
+        address: Address,
+    }
+
+    fn post(content_type: &str, body: &'static str) -> Request {
+        let req = http::Request::post("/signup")
+            .header(CONTENT_TYPE, content_type)
+            .body(Bytes::from_static(body.as_bytes()))
+            .unwrap();
+        Request::new(req, Vec::new())
+    }
+
+    #[test]
+    fn nested_struct_is_deserialized_and_decoded() {
+        let req = post(
+            FORM_URLENCODED,
+            "name=Ferris+%26+Co.&street=R%C3%BCstweg+1&city=K%C3%B6ln%3F",
+        );
+
+        let Form(signup) = Form::<Signup>::from_request(&req).unwrap();
+        assert_eq!(
+            signup,
+            Signup {
+                name: "Ferris & Co.".into(),
+                address: Address {
+                    street: "Rüstweg 1".into(),
+                    city: "Köln?".into(),
+                },
+            }
+        );
+    }
+
+    #[test]
+    fn form_round_trip() {
+        let signup = Signup {
+            name: "a=b&c".into(),
+            address: Address {
+                street: "Main St".into(),
+                city: "Zürich".into(),
+            },
+        };
+
+        let res = Form(signup).into_response();
+        assert_eq!(res.headers()[CONTENT_TYPE], FORM_URLENCODED);
+        assert_eq!(res.body(), "name=a%3Db%26c&street=Main+St&city=Z%C3%BCrich");
+    }
+
+    #[test]
+    fn wrong_content_type_is_rejected() {
+        let req = post("application/json", "name=Ferris");
+        let err = Form::<Signup>::from_request(&req).unwrap_err();
+        assert!(matches!(err, FormRejection::WrongContentType(Some(_))));
+        assert_eq!(
+            err.into_response().status(),
+            StatusCode::UNSUPPORTED_MEDIA_TYPE
+        );
+    }
+
+    #[test]
+    fn missing_fields_are_named() {
+        let req = post(FORM_URLENCODED, "name=Ferris&street=Main+St");
+        let err = Form::<Signup>::from_request(&req).unwrap_err();
+        assert!(matches!(&err, FormRejection::MissingField(field) if field == "city"));
+        assert_eq!(err.into_response().status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[test]
+    fn unparsable_values_are_rejected() {
+        #[derive(Debug, Deserialize)]
+        struct Age {
+            #[allow(dead_code)]
+            age: u8,
+        }
+
+        let req = post(FORM_URLENCODED, "age=old");
+        let err = Form::<Age>::from_request(&req).unwrap_err();
+        assert!(matches!(err, FormRejection::Invalid(_)));
+    }
+}
@@ -0,0 +1,64 @@
+use std::{convert::Infallible, fmt::Write};
+
+use sha2::{Digest, Sha256};
+
+use crate::{FromRequest, Request};
+
+/// The hex-encoded SHA-256 hash of the request body.
+///
+/// Useful to log body hashes or to check them against a signature.
+///
+/// # Examples
+///
+/// ```
+/// use genuine::extract::BodySha256;
+///
+/// let BodySha256(hash) = BodySha256::digest(b"");
+/// assert_eq!(
+///     hash,
+///     "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct BodySha256(pub String);
+
+impl BodySha256 {
+    /// Hashes `bytes`.
+    pub fn digest(bytes: &[u8]) -> Self {
+        let hash = Sha256::digest(bytes);
+        let hex = hash
+            .iter()
+            .fold(String::with_capacity(64), |mut hex, byte| {
+                let _ = write!(hex, "{byte:02x}");
+                hex
+            });
+        Self(hex)
+    }
+}
+
+impl FromRequest for BodySha256 {
+    type Error = Infallible;
+
+    fn from_request(req: &Request) -> Result<Self, Self::Error> {
+        Ok(Self::digest(req.body()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use hyper::body::Bytes;
+
+    use super::*;
+
+    #[test]
+    fn hashes_request_body() {
+        let req = http::Request::new(Bytes::from_static(b"hello world"));
+        let req = Request::new(req, Vec::new());
+
+        let BodySha256(hash) = BodySha256::from_request(&req).unwrap();
+        assert_eq!(
+            hash,
+            "b94d27b9934d3e08a52e52d7da7dabfac484efe37a5380ee9088f7ace2efcde9"
+        );
+    }
+}
@@ -0,0 +1,85 @@
+use std::{convert::Infallible, marker::PhantomData};
+
+use http::{HeaderName, HeaderValue};
+
+use crate::{FromRequest, Request};
+
+/// Names the header a [`RawHeader`] extractor collects.
+pub trait RawHeaderName {
+    const NAME: HeaderName;
+}
+
+/// All values of the header called [`N::NAME`](RawHeaderName::NAME), as
+/// they were sent.
+///
+/// Use this for headers that the typed `Header` extractor of the `headers`
+/// feature does not cover. If the request does not have the header, `values`
+/// is empty.
+///
+/// # Examples
+///
+/// ```
+/// use genuine::extract::{RawHeader, RawHeaderName};
+/// use http::HeaderName;
+///
+/// struct Tenant;
+///
+/// impl RawHeaderName for Tenant {
+///     const NAME: HeaderName = HeaderName::from_static("x-tenant");
+/// }
+///
+/// fn tenants(tenant: RawHeader<Tenant>) -> String {
+///     format!("{} tenants", tenant.values.len())
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct RawHeader<N> {
+    pub name: HeaderName,
+    pub values: Vec<HeaderValue>,
+    marker: PhantomData<N>,
+}
+
+impl<N: RawHeaderName> FromRequest for RawHeader<N> {
+    type Error = Infallible;
+
+    fn from_request(req: &Request) -> Result<Self, Self::Error> {
+        let values = req.headers().get_all(N::NAME).iter().cloned().collect();
+
+        Ok(Self {
+            name: N::NAME,
+            values,
+            marker: PhantomData,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use hyper::body::Bytes;
+
+    use super::*;
+
+    struct Forwarded;
+
+    impl RawHeaderName for Forwarded {
+        const NAME: HeaderName = HeaderName::from_static("x-forwarded-for");
+    }
+
+    #[test]
+    fn all_values_are_collected() {
+        let req = http::Request::get("/")
+            .header("x-forwarded-for", "203.0.113.7")
+            .header("x-forwarded-for", "198.51.100.1")
+            .body(Bytes::new())
+            .unwrap();
+        let req = Request::new(req, Vec::new());
+
+        let header = RawHeader::<Forwarded>::from_request(&req).unwrap();
+        assert_eq!(header.name, "x-forwarded-for");
+        assert_eq!(header.values, ["203.0.113.7", "198.51.100.1"]);
+
+        let req = Request::new(http::Request::new(Bytes::new()), Vec::new());
+        let header = RawHeader::<Forwarded>::from_request(&req).unwrap();
+        assert!(header.values.is_empty());
+    }
+}
@@ -0,0 +1,298 @@
+use http::{
+    header::{HeaderValue, CONTENT_TYPE},
+    Response, StatusCode,
+};
+use hyper::body::Bytes;
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{FromRequest, IntoResponse, Rejection, Request};
+
+/// Deserializes a JSON request body into `T`, or serializes `T` into a JSON
+/// response body.
+///
+/// As an extractor, the request must have a `Content-Type` of
+/// `application/json`, or another JSON type like `application/ld+json`.
+///
+/// # Examples
+///
+/// ```
+/// use genuine::extract::Json;
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(Deserialize, Serialize)]
+/// struct User {
+///     name: String,
+/// }
+///
+/// fn create_user(Json(user): Json<User>) -> Json<User> {
+///     Json(user)
+/// }
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Json<T>(pub T);
+
+/// The request body could not be extracted as [`Json`].
+#[derive(Debug, thiserror::Error)]
+pub enum JsonRejection {
+    /// The `Content-Type` is not JSON, or missing.
+    #[error("expected a JSON body, got content type {}", .0.as_deref().unwrap_or("none"))]
+    WrongContentType(Option<String>),
+    #[error("expected a JSON body, got an empty body")]
+    EmptyBody,
+    #[error("invalid JSON body: {0}")]
+    Invalid(#[from] serde_json::Error),
+}
+
+impl IntoResponse for JsonRejection {
+    fn into_response(self) -> Response<Bytes> {
+        let status = match self {
+            Self::WrongContentType(_) => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            Self::EmptyBody | Self::Invalid(_) => StatusCode::BAD_REQUEST,
+        };
+
+        Response::builder()
+            .status(status)
+            .body(self.to_string().into())
+            .unwrap()
+    }
+}
+
+impl Rejection for JsonRejection {
+    fn respond(self) -> Response<Bytes> {
+        self.into_response()
+    }
+}
+
+impl<T> FromRequest for Json<T>
+where
+    T: DeserializeOwned,
+{
+    type Error = JsonRejection;
+
+    fn from_request(req: &Request) -> Result<Self, Self::Error> {
+        let content_type = req
+            .headers()
+            .get(CONTENT_TYPE)
+            .map(|value| String::from_utf8_lossy(value.as_bytes()).into_owned());
+        if !content_type.as_deref().is_some_and(is_json) {
+            return Err(JsonRejection::WrongContentType(content_type));
+        }
+
+        if req.body().is_empty() {
+            return Err(JsonRejection::EmptyBody);
+        }
+
+        Ok(Json(serde_json::from_slice(req.body())?))
+    }
+}
+
+impl<T> IntoResponse for Json<T>
+where
+    T: Serialize,
+{
+    fn into_response(self) -> Response<Bytes> {
+        match serde_json::to_vec(&self.0) {
+            Ok(body) => Response::builder()
+                .header(CONTENT_TYPE, HeaderValue::from_static("application/json"))
+                .body(body.into())
+                .unwrap(),
+            Err(err) => Response::builder()
+                .status(StatusCode::INTERNAL_SERVER_ERROR)
+                .body(err.to_string().into())
+                .unwrap(),
+        }
+    }
+}
+
+/// Responds with the value as JSON, like [`Json`].
+impl IntoResponse for serde_json::Value {
+    fn into_response(self) -> Response<Bytes> {
+        Json(self).into_response()
+    }
+}
+
+/// Returns whether `content_type` is `application/json` or
+/// `application/*+json`, with any parameters.
+fn is_json(content_type: &str) -> bool {
+    let mime = content_type.split(';').next().unwrap_or_default().trim();
+    let Some((kind, subtype)) = mime.split_once('/') else {
+        return false;
+    };
+
+    kind.eq_ignore_ascii_case("application")
+        && (subtype.eq_ignore_ascii_case("json") || subtype.to_ascii_lowercase().ends_with("+json"))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    use super::*;
+
+    #[derive(Debug, PartialEq, Deserialize, Serialize)]
+    struct User {
+        name: String,
+        admin: bool,
+    }
+
+    fn post(content_type: Option<&str>, body: &'static str) -> Request {
+        let mut req = http::Request::post("/users");
+        if let Some(content_type) = content_type {
+            req = req.header(CONTENT_TYPE, content_type);
+        }
+        Request::new(
+            req.body(Bytes::from_static(body.as_bytes())).unwrap(),
+            Vec::new(),
+        )
+    }
+
+    #[test]
+    fn json_round_trip() {
+        let body = r#"{"name":"ferris","admin":true}"#;
+        let req = post(Some("application/json; charset=utf-8"), body);
+
+        let Json(user) = Json::<User>::from_request(&req).unwrap();
+        assert_eq!(
+            user,
+            User {
+                name: "ferris".into(),
+                admin: true
+            }
+        );
+
+        let res = Json(user).into_response();
+        assert_eq!(res.headers()[CONTENT_TYPE], "application/json");
+        assert_eq!(res.body(), body);
+    }
+
+    #[test]
+    fn wrong_content_type_is_rejected() {
+        let req = post(Some("text/plain"), r#"{"name":"ferris","admin":true}"#);
+        let err = Json::<User>::from_request(&req).unwrap_err();
+        assert!(matches!(&err, JsonRejection::WrongContentType(Some(ty)) if ty == "text/plain"));
+        assert_eq!(
+            err.into_response().status(),
+            StatusCode::UNSUPPORTED_MEDIA_TYPE
+        );
+
+        let req = post(None, "{}");
+        let err = Json::<User>::from_request(&req).unwrap_err();
+        assert!(matches!(err, JsonRejection::WrongContentType(None)));
+
+        let req = post(
+            Some("application/ld+json"),
+            r#"{"name":"ferris","admin":false}"#,
+        );
+        assert!(Json::<User>::from_request(&req).is_ok());
+    }
+
+    #[test]
+    fn empty_body_is_rejected() {
+        let req = post(Some("application/json"), "");
+        let err = Json::<User>::from_request(&req).unwrap_err();
+        assert!(matches!(err, JsonRejection::EmptyBody));
+        assert_eq!(err.into_response().status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[test]
+    fn invalid_json_is_rejected() {
+        let req = post(Some("application/json"), r#"{"name":"ferris"}"#);
+        let err = Json::<User>::from_request(&req).unwrap_err();
+        assert!(matches!(err, JsonRejection::Invalid(_)));
+        assert_eq!(
+            err.to_string(),
+            "invalid JSON body: missing field `admin` at line 1 column 17"
+        );
+    }
+
+    #[test]
+    fn single_json_param_handlers() {
+        fn create_user(Json(user): Json<User>) -> &'static str {
+            if user.admin {
+                "admin created"
+            } else {
+                "user created"
+            }
+        }
+        let handler = create_user as fn(_) -> _;
+
+        let req = post(
+            Some("application/json"),
+            r#"{"name":"ferris","admin":true}"#,
+        );
+        let res = crate::Handler::handle_request(&handler, req);
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.body(), "admin created");
+
+        let req = post(Some("application/json"), r#"{"name":"ferris"}"#);
+        let res = crate::Handler::handle_request(&handler, req);
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    }
+
+    struct Echo;
+
+    impl crate::Handler for Echo {
+        fn handle_request(&self, req: Request) -> Response<Bytes> {
+            let Json(value) = Json::<serde_json::Value>::from_request(&req).unwrap();
+            value.into_response()
+        }
+    }
+
+    #[test]
+    fn nested_value_round_trip() {
+        let value = serde_json::json!({
+            "team": "rustaceans",
+            "members": [
+                { "name": "ferris", "roles": ["mascot", "crab"], "age": null },
+                { "name": "corro", "roles": [], "age": 7.5 },
+            ],
+            "meta": { "nested": { "deeply": true } },
+        });
+        let body = serde_json::to_vec(&value).unwrap();
+        let req = http::Request::post("/echo")
+            .header(CONTENT_TYPE, "application/json")
+            .body(Bytes::from(body))
+            .unwrap();
+
+        let res = crate::Handler::handle_request(&Echo, Request::new(req, Vec::new()));
+
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.headers()[CONTENT_TYPE], "application/json");
+        let echoed: serde_json::Value = serde_json::from_slice(res.body()).unwrap();
+        assert_eq!(echoed, value);
+    }
+
+    #[tokio::test]
+    async fn rejections_reach_the_client() {
+        use crate::{testing::TestClient, App};
+
+        fn create_user(Json(user): Json<User>) -> Json<User> {
+            Json(user)
+        }
+
+        let mut app = App::new();
+        app.post("/users", create_user as fn(_) -> _).unwrap();
+        let client = TestClient::new(app);
+
+        let res = client
+            .post("/users")
+            .header("content-type", "application/json")
+            .body(r#"{"name":"ferris""#)
+            .send()
+            .await;
+        crate::assert_status!(res, 400);
+        assert!(res.text().await.starts_with("invalid JSON body"));
+
+        let res = client
+            .post("/users")
+            .header("content-type", "text/plain")
+            .body(r#"{"name":"ferris","admin":true}"#)
+            .send()
+            .await;
+        crate::assert_status!(res, 415);
+        assert_eq!(
+            res.text().await,
+            "expected a JSON body, got content type text/plain"
+        );
+    }
+}
@@ -0,0 +1,40 @@
+//! Extractors for use as handler parameters.
+
+mod auth;
+mod body;
+mod extension;
+#[cfg(feature = "serde_urlencoded")]
+mod form;
+#[cfg(feature = "sha2")]
+mod hash;
+mod header;
+#[cfg(feature = "serde_json")]
+mod json;
+#[cfg(feature = "multer")]
+mod multipart;
+mod parts;
+mod path;
+#[cfg(feature = "serde_urlencoded")]
+mod query;
+mod state;
+#[cfg(feature = "headers")]
+mod typed_header;
+
+pub use auth::{Auth, BearerToken, MissingBearerToken, Unauthenticated};
+pub use extension::{Extension, MissingExtension};
+#[cfg(feature = "serde_urlencoded")]
+pub use form::{Form, FormRejection};
+#[cfg(feature = "sha2")]
+pub use hash::BodySha256;
+pub use header::{RawHeader, RawHeaderName};
+#[cfg(feature = "serde_json")]
+pub use json::{Json, JsonRejection};
+#[cfg(feature = "multer")]
+pub use multipart::{Multipart, MultipartRejection, Part};
+pub use parts::MissingPeerAddr;
+pub use path::{ParamError, PathParams};
+#[cfg(feature = "serde_urlencoded")]
+pub use query::{Query, QueryRejection};
+pub use state::{MissingState, State};
+#[cfg(feature = "headers")]
+pub use typed_header::{Header, HeaderRejection};
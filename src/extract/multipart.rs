@@ -0,0 +1,265 @@
+use std::{
+    convert::Infallible,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use futures_core::Stream;
+use http::{header::CONTENT_TYPE, Response, StatusCode};
+use hyper::body::Bytes;
+use multer::{Constraints, SizeLimit};
+
+use crate::{FromRequest, IntoResponse, Rejection, Request};
+
+/// The parts of a `multipart/form-data` body, e.g. a form with file
+/// uploads.
+///
+/// Parts are parsed one at a time, as [`Multipart::next_part`] asks for
+/// them, so the body is never copied as a whole. It is already in memory,
+/// though, limited by the maximum body size of the route.
+///
+/// # Examples
+///
+/// ```
+/// use genuine::{
+///     extract::{Multipart, MultipartRejection},
+///     FromRequest, Request,
+/// };
+///
+/// async fn upload(req: Request) -> Result<String, MultipartRejection> {
+///     let mut multipart = Multipart::from_request(&req)?.field_limit(1024 * 1024);
+///
+///     let mut uploaded = Vec::new();
+///     while let Some(part) = multipart.next_part().await? {
+///         let name = part.filename().unwrap_or("unnamed").to_owned();
+///         let bytes = part.bytes().await?;
+///         uploaded.push(format!("{name} ({} bytes)", bytes.len()));
+///     }
+///     Ok(uploaded.join(", "))
+/// }
+/// ```
+pub struct Multipart {
+    body: Bytes,
+    boundary: String,
+    field_limit: Option<u64>,
+    parts: Option<multer::Multipart<'static>>,
+}
+
+impl std::fmt::Debug for Multipart {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Multipart")
+            .field("boundary", &self.boundary)
+            .field("field_limit", &self.field_limit)
+            .finish_non_exhaustive()
+    }
+}
+
+/// A single part of a [`Multipart`] body.
+#[derive(Debug)]
+pub struct Part(multer::Field<'static>);
+
+/// The request body is not valid `multipart/form-data`, see [`Multipart`].
+#[derive(Debug, thiserror::Error)]
+pub enum MultipartRejection {
+    /// The `Content-Type` is not `multipart/form-data`, or missing.
+    #[error("expected a multipart/form-data body, got content type {0:?}")]
+    WrongContentType(Option<String>),
+    #[error("missing multipart boundary")]
+    MissingBoundary,
+    #[error("multipart field {field:?} is larger than {limit} bytes")]
+    FieldTooLarge { field: Option<String>, limit: u64 },
+    #[error("invalid multipart body: {0}")]
+    Invalid(multer::Error),
+}
+
+impl From<multer::Error> for MultipartRejection {
+    fn from(err: multer::Error) -> Self {
+        match err {
+            multer::Error::FieldSizeExceeded { limit, field_name } => Self::FieldTooLarge {
+                field: field_name,
+                limit,
+            },
+            err => Self::Invalid(err),
+        }
+    }
+}
+
+impl IntoResponse for MultipartRejection {
+    fn into_response(self) -> Response<Bytes> {
+        let status = match self {
+            Self::WrongContentType(_) => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            Self::FieldTooLarge { .. } => StatusCode::PAYLOAD_TOO_LARGE,
+            Self::MissingBoundary | Self::Invalid(_) => StatusCode::BAD_REQUEST,
+        };
+
+        Response::builder()
+            .status(status)
+            .body(self.to_string().into())
+            .unwrap()
+    }
+}
+
+impl Multipart {
+    /// Reject parts larger than `bytes`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if parts were read already.
+    pub fn field_limit(mut self, bytes: u64) -> Self {
+        assert!(self.parts.is_none(), "field limit set after reading parts");
+        self.field_limit = Some(bytes);
+        self
+    }
+
+    /// Returns the next part, or [`None`] after the last one.
+    ///
+    /// The previous part must be dropped or read before.
+    pub async fn next_part(&mut self) -> Result<Option<Part>, MultipartRejection> {
+        let parts = self.parts.get_or_insert_with(|| {
+            let mut limit = SizeLimit::new();
+            if let Some(bytes) = self.field_limit {
+                limit = limit.per_field(bytes);
+            }
+            let body = Once(Some(self.body.clone()));
+            let constraints = Constraints::new().size_limit(limit);
+            multer::Multipart::with_constraints(body, &self.boundary, constraints)
+        });
+
+        Ok(parts.next_field().await?.map(Part))
+    }
+}
+
+impl Part {
+    /// The name of the form field.
+    pub fn name(&self) -> Option<&str> {
+        self.0.name()
+    }
+
+    /// The name of the uploaded file, if the part is one.
+    pub fn filename(&self) -> Option<&str> {
+        self.0.file_name()
+    }
+
+    pub fn content_type(&self) -> Option<&str> {
+        self.0.content_type().map(AsRef::as_ref)
+    }
+
+    /// Read the whole content of the part.
+    pub async fn bytes(self) -> Result<Bytes, MultipartRejection> {
+        Ok(self.0.bytes().await?)
+    }
+}
+
+impl Rejection for MultipartRejection {
+    fn respond(self) -> Response<Bytes> {
+        self.into_response()
+    }
+}
+
+impl FromRequest for Multipart {
+    type Error = MultipartRejection;
+
+    fn from_request(req: &Request) -> Result<Self, Self::Error> {
+        let content_type = req
+            .headers()
+            .get(CONTENT_TYPE)
+            .map(|value| String::from_utf8_lossy(value.as_bytes()).into_owned());
+        let is_multipart = content_type.as_deref().is_some_and(|content_type| {
+            let essence = content_type.split(';').next().unwrap_or_default().trim();
+            essence.eq_ignore_ascii_case("multipart/form-data")
+        });
+        if !is_multipart {
+            return Err(MultipartRejection::WrongContentType(content_type));
+        }
+
+        let boundary = multer::parse_boundary(content_type.unwrap_or_default())
+            .map_err(|_| MultipartRejection::MissingBoundary)?;
+
+        Ok(Self {
+            body: req.body().clone(),
+            boundary,
+            field_limit: None,
+            parts: None,
+        })
+    }
+}
+
+/// A stream of the single, buffered chunk of a body.
+struct Once(Option<Bytes>);
+
+impl Stream for Once {
+    type Item = Result<Bytes, Infallible>;
+
+    fn poll_next(mut self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        Poll::Ready(self.0.take().map(Ok))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BODY: &str = "--X-BOUNDARY\r\n\
+        Content-Disposition: form-data; name=\"title\"\r\n\
+        \r\n\
+        Holiday\r\n\
+        --X-BOUNDARY\r\n\
+        Content-Disposition: form-data; name=\"photo\"; filename=\"beach.png\"\r\n\
+        Content-Type: image/png\r\n\
+        \r\n\
+        \u{89}PNG sand and sea\r\n\
+        --X-BOUNDARY--\r\n";
+
+    fn request(content_type: &str) -> Request {
+        let req = http::Request::post("/upload")
+            .header(CONTENT_TYPE, content_type)
+            .body(Bytes::from_static(BODY.as_bytes()))
+            .unwrap();
+        Request::new(req, Vec::new())
+    }
+
+    #[tokio::test]
+    async fn parts_are_read_one_by_one() {
+        let req = request("multipart/form-data; boundary=X-BOUNDARY");
+        let mut multipart = Multipart::from_request(&req).unwrap();
+
+        let title = multipart.next_part().await.unwrap().unwrap();
+        assert_eq!(title.name(), Some("title"));
+        assert_eq!(title.filename(), None);
+        assert_eq!(title.bytes().await.unwrap(), "Holiday");
+
+        let photo = multipart.next_part().await.unwrap().unwrap();
+        assert_eq!(photo.name(), Some("photo"));
+        assert_eq!(photo.filename(), Some("beach.png"));
+        assert_eq!(photo.content_type(), Some("image/png"));
+        assert_eq!(photo.bytes().await.unwrap(), "\u{89}PNG sand and sea");
+
+        assert!(multipart.next_part().await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn large_fields_are_rejected() {
+        let req = request("multipart/form-data; boundary=X-BOUNDARY");
+        let mut multipart = Multipart::from_request(&req).unwrap().field_limit(10);
+
+        let title = multipart.next_part().await.unwrap().unwrap();
+        assert_eq!(title.bytes().await.unwrap(), "Holiday");
+
+        let photo = multipart.next_part().await.unwrap().unwrap();
+        let err = photo.bytes().await.unwrap_err();
+        assert!(matches!(
+            err,
+            MultipartRejection::FieldTooLarge { limit: 10, .. }
+        ));
+        assert_eq!(err.into_response().status(), StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    #[test]
+    fn other_content_types_are_rejected() {
+        let err = Multipart::from_request(&request("application/json")).unwrap_err();
+        assert!(matches!(err, MultipartRejection::WrongContentType(Some(_))));
+
+        let err = Multipart::from_request(&request("multipart/form-data")).unwrap_err();
+        assert!(matches!(err, MultipartRejection::MissingBoundary));
+    }
+}
@@ -0,0 +1,72 @@
+use std::{convert::Infallible, net::SocketAddr};
+
+use http::{Method, Uri};
+
+use crate::{FromRequest, Rejection, Request};
+
+/// The request method.
+impl FromRequest for Method {
+    type Error = Infallible;
+
+    fn from_request(req: &Request) -> Result<Self, Self::Error> {
+        Ok(req.method().clone())
+    }
+}
+
+/// The full request URI, including the query string.
+impl FromRequest for Uri {
+    type Error = Infallible;
+
+    fn from_request(req: &Request) -> Result<Self, Self::Error> {
+        Ok(req.uri().clone())
+    }
+}
+
+/// The address of the client that sent the request.
+///
+/// This is the direct peer of the connection. Behind a proxy or load
+/// balancer, that is the address of the proxy, not of the client, which
+/// proxies usually pass on in the `X-Forwarded-For` header instead.
+impl Rejection for MissingPeerAddr {}
+
+impl FromRequest for SocketAddr {
+    type Error = MissingPeerAddr;
+
+    fn from_request(req: &Request) -> Result<Self, Self::Error> {
+        req.extensions()
+            .get::<SocketAddr>()
+            .copied()
+            .ok_or(MissingPeerAddr)
+    }
+}
+
+/// The address of the client is unknown, e.g. because the request came in
+/// over a Unix domain socket.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+#[error("the address of the client is unknown")]
+pub struct MissingPeerAddr;
+
+#[cfg(test)]
+mod tests {
+    use hyper::body::Bytes;
+
+    use super::*;
+    use crate::Handler;
+
+    #[test]
+    fn handlers_get_method_and_uri() {
+        let describe = (|method: Method, uri: Uri| format!("{method} {uri}")) as fn(_, _) -> _;
+
+        let req = http::Request::patch("/users/42?notify=true")
+            .body(Bytes::new())
+            .unwrap();
+        let res = describe.handle_request(Request::new(req, Vec::new()));
+        assert_eq!(res.body(), "PATCH /users/42?notify=true");
+    }
+
+    #[test]
+    fn peer_address_requires_a_connection() {
+        let req = Request::new(http::Request::new(Bytes::new()), Vec::new());
+        assert_eq!(SocketAddr::from_request(&req), Err(MissingPeerAddr));
+    }
+}
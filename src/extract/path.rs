@@ -0,0 +1,116 @@
+use std::{collections::HashMap, convert::Infallible, str::FromStr};
+
+use crate::{FromRequest, Request};
+
+/// All path parameters of the matched route, by name.
+///
+/// This is an alternative to extracting parameters one by one, for routes
+/// with many of them.
+///
+/// # Examples
+///
+/// ```
+/// use genuine::extract::PathParams;
+///
+/// let params = PathParams([("id".to_owned(), "42".to_owned())].into());
+///
+/// assert_eq!(params.get::<u32>("id"), Ok(42));
+/// assert!(params.get::<u32>("name").is_err());
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PathParams(pub HashMap<String, String>);
+
+/// A path parameter is missing or cannot be parsed, see [`PathParams::get`].
+#[derive(Debug, thiserror::Error, PartialEq)]
+pub enum ParamError {
+    #[error("no path parameter named `{0}`")]
+    Missing(String),
+    #[error("path parameter `{name}` has invalid value {value:?}")]
+    Invalid { name: String, value: String },
+}
+
+impl PathParams {
+    /// Parse the parameter called `name`.
+    pub fn get<T: FromStr>(&self, name: &str) -> Result<T, ParamError> {
+        let value = self
+            .0
+            .get(name)
+            .ok_or_else(|| ParamError::Missing(name.to_owned()))?;
+
+        value.parse().map_err(|_| ParamError::Invalid {
+            name: name.to_owned(),
+            value: value.clone(),
+        })
+    }
+}
+
+impl FromRequest for PathParams {
+    type Error = Infallible;
+
+    fn from_request(req: &Request) -> Result<Self, Self::Error> {
+        let params = req
+            .matches()
+            .iter()
+            .map(|m| (m.name.clone(), m.value.clone()))
+            .collect();
+        Ok(Self(params))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use http::{Method, Response};
+    use http_body_util::{BodyExt, Full};
+    use hyper::body::Bytes;
+
+    use super::*;
+    use crate::{router::Router, Handler};
+
+    struct Describe;
+
+    impl Handler for Describe {
+        fn handle_request(&self, req: Request) -> Response<Bytes> {
+            let params = PathParams::from_request(&req).unwrap();
+            let org: String = params.get("org").unwrap();
+            let repo: String = params.get("repo").unwrap();
+            let number: u32 = params.get("number").unwrap();
+            Response::new(format!("{org}/{repo}#{number}").into())
+        }
+    }
+
+    #[tokio::test]
+    async fn all_params_of_the_route_are_collected() {
+        let mut router = Router::new();
+        router.groups[0]
+            .add(
+                Method::GET,
+                "/orgs/{org}/repos/{repo}/issues/{number}",
+                Describe,
+            )
+            .unwrap();
+
+        let req = http::Request::get("/orgs/rust-lang/repos/rust/issues/42")
+            .body(Full::<Bytes>::default())
+            .unwrap();
+        let res = router.respond(req).await.unwrap();
+        let body = res.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(body, "rust-lang/rust#42");
+    }
+
+    #[test]
+    fn invalid_values_are_reported() {
+        let params = PathParams([("number".to_owned(), "forty-two".to_owned())].into());
+
+        assert_eq!(
+            params.get::<u32>("number"),
+            Err(ParamError::Invalid {
+                name: "number".into(),
+                value: "forty-two".into()
+            })
+        );
+        assert_eq!(
+            params.get::<u32>("org"),
+            Err(ParamError::Missing("org".into()))
+        );
+    }
+}
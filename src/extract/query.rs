@@ -0,0 +1,149 @@
+use http::{Response, StatusCode};
+use hyper::body::Bytes;
+use serde::de::DeserializeOwned;
+
+use crate::{FromRequest, IntoResponse, Rejection, Request};
+
+/// Deserializes the query string of the request URI into `T`.
+///
+/// A request without a query string is treated like an empty one, so types
+/// whose fields are all optional, or have `#[serde(default)]`, are still
+/// extracted. Otherwise the extractor fails with
+/// [`QueryRejection::MissingQuery`].
+///
+/// # Examples
+///
+/// ```
+/// use genuine::extract::Query;
+/// use serde::Deserialize;
+///
+/// #[derive(Deserialize)]
+/// struct Search {
+///     q: String,
+///     page: Option<u32>,
+/// }
+///
+/// fn search(Query(search): Query<Search>) -> String {
+///     format!("{} (page {})", search.q, search.page.unwrap_or(1))
+/// }
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Query<T>(pub T);
+
+/// The query string could not be deserialized, see [`Query`].
+#[derive(Debug, thiserror::Error)]
+pub enum QueryRejection {
+    #[error("missing query string: {0}")]
+    MissingQuery(serde_urlencoded::de::Error),
+    #[error("invalid query string: {0}")]
+    Invalid(serde_urlencoded::de::Error),
+}
+
+impl IntoResponse for QueryRejection {
+    fn into_response(self) -> Response<Bytes> {
+        Response::builder()
+            .status(StatusCode::BAD_REQUEST)
+            .body(self.to_string().into())
+            .unwrap()
+    }
+}
+
+impl Rejection for QueryRejection {
+    fn respond(self) -> Response<Bytes> {
+        self.into_response()
+    }
+}
+
+impl<T> FromRequest for Query<T>
+where
+    T: DeserializeOwned,
+{
+    type Error = QueryRejection;
+
+    fn from_request(req: &Request) -> Result<Self, Self::Error> {
+        match req.uri().query() {
+            Some(query) => serde_urlencoded::from_str(query)
+                .map(Query)
+                .map_err(QueryRejection::Invalid),
+            None => serde_urlencoded::from_str("")
+                .map(Query)
+                .map_err(QueryRejection::MissingQuery),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    use super::*;
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Search {
+        q: String,
+        page: Option<u32>,
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Filter {
+        tag: Option<String>,
+    }
+
+    fn request(uri: &str) -> Request {
+        Request::new(
+            http::Request::get(uri).body(Bytes::new()).unwrap(),
+            Vec::new(),
+        )
+    }
+
+    #[test]
+    fn query_is_deserialized() {
+        let Query(search) =
+            Query::<Search>::from_request(&request("/?q=web+framework&page=2")).unwrap();
+        assert_eq!(
+            search,
+            Search {
+                q: "web framework".into(),
+                page: Some(2),
+            }
+        );
+
+        let Query(search) = Query::<Search>::from_request(&request("/?q=rust")).unwrap();
+        assert_eq!(search.page, None);
+    }
+
+    #[test]
+    fn missing_required_fields_are_rejected() {
+        let err = Query::<Search>::from_request(&request("/?page=2")).unwrap_err();
+        assert!(matches!(err, QueryRejection::Invalid(_)));
+        assert_eq!(err.to_string(), "invalid query string: missing field `q`");
+
+        let err = Query::<Search>::from_request(&request("/?q=rust&page=two")).unwrap_err();
+        assert!(matches!(err, QueryRejection::Invalid(_)));
+        assert_eq!(err.into_response().status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[test]
+    fn single_query_param_handlers() {
+        fn search(Query(search): Query<Search>) -> String {
+            format!("{} on page {}", search.q, search.page.unwrap_or(1))
+        }
+        let handler = search as fn(_) -> _;
+
+        let res = crate::Handler::handle_request(&handler, request("/?q=rust&page=3"));
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.body(), "rust on page 3");
+
+        let res = crate::Handler::handle_request(&handler, request("/?page=3"));
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[test]
+    fn absent_query_is_empty() {
+        let Query(filter) = Query::<Filter>::from_request(&request("/posts")).unwrap();
+        assert_eq!(filter, Filter { tag: None });
+
+        let err = Query::<Search>::from_request(&request("/posts")).unwrap_err();
+        assert!(matches!(err, QueryRejection::MissingQuery(_)));
+    }
+}
@@ -0,0 +1,57 @@
+use crate::{FromRequest, Rejection, Request};
+
+/// Extracts shared application state registered with
+/// [`App::with_state`](crate::App::with_state) or
+/// [`App::init_state`](crate::App::init_state).
+///
+/// The state is cloned for every request, so wrap anything expensive to
+/// clone in an [`Arc`](std::sync::Arc).
+#[derive(Debug, Clone, Copy, Default)]
+pub struct State<T>(pub T);
+
+/// The requested state type was never registered with the app.
+#[derive(Debug, thiserror::Error)]
+#[error("no state of type `{0}` was registered")]
+pub struct MissingState(&'static str);
+
+impl Rejection for MissingState {}
+
+impl<T> FromRequest for State<T>
+where
+    T: Clone + Send + Sync + 'static,
+{
+    type Error = MissingState;
+
+    fn from_request(req: &Request) -> Result<Self, Self::Error> {
+        req.extensions()
+            .get::<T>()
+            .cloned()
+            .map(State)
+            .ok_or(MissingState(std::any::type_name::<T>()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use hyper::body::Bytes;
+
+    use super::*;
+
+    #[test]
+    fn state_is_read_from_extensions() {
+        let mut req = http::Request::new(Bytes::new());
+        req.extensions_mut().insert(42u32);
+        let req = Request::new(req, Vec::new());
+
+        let State(answer) = State::<u32>::from_request(&req).unwrap();
+        assert_eq!(answer, 42);
+    }
+
+    #[test]
+    fn missing_state_names_the_type() {
+        let req = Request::new(http::Request::new(Bytes::new()), Vec::new());
+
+        let err = State::<u32>::from_request(&req).unwrap_err();
+        assert_eq!(err.to_string(), "no state of type `u32` was registered");
+    }
+}
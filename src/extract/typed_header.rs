@@ -0,0 +1,105 @@
+use headers::HeaderMapExt;
+use http::{HeaderName, Response, StatusCode};
+use hyper::body::Bytes;
+
+use crate::{FromRequest, IntoResponse, Rejection, Request};
+
+/// Extracts a header parsed by its [`headers::Header`] implementation.
+///
+/// Any header of the [`headers`] crate, like
+/// [`Authorization`](headers::Authorization),
+/// [`ContentType`](headers::ContentType) or [`Cookie`](headers::Cookie), can
+/// be extracted this way.
+///
+/// # Examples
+///
+/// ```
+/// use genuine::extract::Header;
+/// use headers::{authorization::Bearer, Authorization};
+///
+/// fn token(Header(auth): Header<Authorization<Bearer>>) -> String {
+///     auth.token().to_owned()
+/// }
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct Header<T>(pub T);
+
+/// A [`Header`] could not be extracted.
+#[derive(Debug, thiserror::Error, PartialEq)]
+pub enum HeaderRejection {
+    #[error("missing header `{0}`")]
+    MissingHeader(&'static HeaderName),
+    #[error("invalid header `{0}`")]
+    InvalidHeader(&'static HeaderName),
+}
+
+impl IntoResponse for HeaderRejection {
+    fn into_response(self) -> Response<Bytes> {
+        Response::builder()
+            .status(StatusCode::BAD_REQUEST)
+            .body(self.to_string().into())
+            .unwrap()
+    }
+}
+
+impl Rejection for HeaderRejection {
+    fn respond(self) -> Response<Bytes> {
+        self.into_response()
+    }
+}
+
+impl<T> FromRequest for Header<T>
+where
+    T: headers::Header,
+{
+    type Error = HeaderRejection;
+
+    fn from_request(req: &Request) -> Result<Self, Self::Error> {
+        match req.headers().typed_try_get::<T>() {
+            Ok(Some(header)) => Ok(Header(header)),
+            Ok(None) => Err(HeaderRejection::MissingHeader(T::name())),
+            Err(_) => Err(HeaderRejection::InvalidHeader(T::name())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use headers::{authorization::Bearer, Authorization, ContentType};
+
+    use super::*;
+
+    fn request(name: &str, value: &str) -> Request {
+        let req = http::Request::get("/")
+            .header(name, value)
+            .body(Bytes::new())
+            .unwrap();
+        Request::new(req, Vec::new())
+    }
+
+    #[test]
+    fn typed_headers_are_parsed() {
+        let req = request("authorization", "Bearer secret");
+        let Header(auth) = Header::<Authorization<Bearer>>::from_request(&req).unwrap();
+        assert_eq!(auth.token(), "secret");
+
+        let req = request("content-type", "application/json");
+        let Header(content_type) = Header::<ContentType>::from_request(&req).unwrap();
+        assert_eq!(content_type, ContentType::json());
+    }
+
+    #[test]
+    fn missing_and_invalid_headers_are_rejected() {
+        let req = request("accept", "*/*");
+        let err = Header::<ContentType>::from_request(&req).unwrap_err();
+        assert_eq!(err.to_string(), "missing header `content-type`");
+
+        let req = request("authorization", "Basic");
+        let err = Header::<Authorization<Bearer>>::from_request(&req).unwrap_err();
+        assert_eq!(
+            err,
+            HeaderRejection::InvalidHeader(&http::header::AUTHORIZATION)
+        );
+        assert_eq!(err.into_response().status(), StatusCode::BAD_REQUEST);
+    }
+}
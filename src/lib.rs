@@ -1,7 +1,81 @@
+//! A tiny web framework.
+//!
+//! # Feature flags
+//!
+//! All features are off by default. Each one enables the dependency it is
+//! named after, except for `compression`, `tls` and `ws`:
+//!
+//! - `compression`: the `Compress` middleware, to compress responses with
+//!   [`async-compression`](https://docs.rs/async-compression).
+//! - `cookie`: the `cookies` module, to read and set cookies, including
+//!   signed ones.
+//! - `encoding_rs`: the `CharsetDecode` middleware, to decode
+//!   request bodies that are not UTF-8.
+//! - `headers`: the typed `Header` extractor, for any header implemented by
+//!   the [`headers`](https://docs.rs/headers) crate.
+//! - `multer`: the `Multipart` extractor, for `multipart/form-data` bodies.
+//! - `prometheus`: the `metrics` module, with a Prometheus metrics endpoint.
+//! - `serde_json`: the `Json` extractor and response.
+//! - `serde_urlencoded`: the `Query` and `Form` extractors.
+//! - `sha2`: the `BodySha256` extractor.
+//! - `tls`: the `tls` module, to serve HTTPS with
+//!   [`tokio-rustls`](https://docs.rs/tokio-rustls).
+//! - `ws`: the `WebSocketUpgrade` extractor, to accept WebSocket connections
+//!   with [`tokio-tungstenite`](https://docs.rs/tokio-tungstenite).
+//!
+//! # Dependencies
+//!
+//! The [`http`] crate is re-exported, and so is the [`Bytes`](body::Bytes)
+//! type of bodies, so apps use the same versions as this crate without
+//! depending on them directly:
+//!
+//! ```
+//! use genuine::{
+//!     body::Bytes,
+//!     http::{Method, Response},
+//!     App,
+//! };
+//!
+//! fn raw() -> Response<Bytes> {
+//!     Response::new(Bytes::from_static(b"raw"))
+//! }
+//!
+//! let mut app = App::new();
+//! app.add(Method::GET, "/raw", raw as fn() -> _).unwrap();
+//! ```
+
 mod app;
+pub mod body;
+#[cfg(feature = "cookie")]
+pub mod cookies;
+mod error;
+pub mod extract;
+mod macros;
+#[cfg(feature = "prometheus")]
+pub mod metrics;
+pub mod middleware;
 mod request;
+pub mod response;
 mod router;
 mod server;
+pub mod sse;
+pub mod static_files;
+pub mod testing;
+#[cfg(feature = "tls")]
+pub mod tls;
+pub mod url;
+pub mod ws;
+
+pub use http;
 
 pub use app::App;
-pub use router::routes::{FromRequest, Handler, IntoResponse, Param};
+pub use error::GenuineError;
+pub use request::Request;
+pub use router::groups::{Group, RouteConflict};
+pub use router::routes::{
+    AsyncFromRequest, AsyncHandler, BodyTransform, BoxError, BoxedFuture, FromRequest, Handler,
+    HandlerFactory, IntoResponse, Named, NamedParamError, Param, ParamExtractError, ParamName,
+    ParseError, ParseErrorKind, Part, Path, Rejection, Route, RouteInfo,
+};
+pub use router::TrailingSlashPolicy;
+pub use server::{BindFuture, Server, ServerBuilder};
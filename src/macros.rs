@@ -0,0 +1,163 @@
+/// Build a pre-configured [`Route`](crate::Route).
+///
+/// The route is described by its method, path and handler, optionally
+/// followed by `before` and `after` middleware, a `name` and a
+/// `description`, in any order. The macro evaluates to a
+/// `Result<Route, ParseError>`, which fails if the path is invalid.
+///
+/// # Examples
+///
+/// ```
+/// use genuine::{route, App};
+///
+/// fn get_user() -> &'static str {
+///     "Ferris"
+/// }
+///
+/// let mut app = App::new();
+///
+/// let route = route!(
+///     GET "/users/{id}" => get_user as fn() -> _,
+///     name: "user_show",
+///     description: "Get a user by ID",
+/// )
+/// .expect("invalid route path");
+///
+/// app.add_route(route);
+/// ```
+#[macro_export]
+macro_rules! route {
+    ($method:ident $path:literal => $handler:expr $(, $($options:tt)*)?) => {
+        $crate::Path::new(::std::string::String::from($path)).map(|path| {
+            #[allow(unused_mut)]
+            let mut route = $crate::Route::new(
+                $crate::http::Method::$method,
+                path,
+                ::std::boxed::Box::new($handler),
+            );
+            $crate::__route_options!(route $(, $($options)*)?);
+            route
+        })
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __route_options {
+    ($route:ident $(,)?) => {};
+    ($route:ident, before: [$($middleware:expr),* $(,)?] $($rest:tt)*) => {
+        $($route.before($middleware);)*
+        $crate::__route_options!($route $($rest)*);
+    };
+    ($route:ident, after: [$($middleware:expr),* $(,)?] $($rest:tt)*) => {
+        $($route.after($middleware);)*
+        $crate::__route_options!($route $($rest)*);
+    };
+    ($route:ident, name: $name:expr $(, $($rest:tt)*)?) => {
+        $route.name($name);
+        $crate::__route_options!($route $(, $($rest)*)?);
+    };
+    ($route:ident, description: $description:expr $(, $($rest:tt)*)?) => {
+        $route.description($description);
+        $crate::__route_options!($route $(, $($rest)*)?);
+    };
+}
+
+/// Asserts that a response has a status, given as a number or a
+/// [`StatusCode`](http::StatusCode).
+///
+/// Works with anything that has a `status()` method, like the
+/// [`TestResponse`](crate::testing::TestResponse) of a test client.
+///
+/// # Examples
+///
+/// ```
+/// use genuine::assert_status;
+/// use http::{Response, StatusCode};
+///
+/// let res = Response::builder().status(404).body(()).unwrap();
+/// assert_status!(res, 404);
+/// assert_status!(res, StatusCode::NOT_FOUND);
+/// ```
+#[macro_export]
+macro_rules! assert_status {
+    ($res:expr, $status:expr $(,)?) => {
+        match (&$res.status(), &$status) {
+            (status, expected) => assert!(
+                *status == *expected,
+                "expected status {}, got {}",
+                expected,
+                status
+            ),
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use http::Method;
+
+    use crate::{middleware::Middleware, ParseErrorKind};
+
+    struct Auth;
+
+    impl Middleware for Auth {}
+
+    struct Log;
+
+    impl Middleware for Log {}
+
+    fn hello() -> &'static str {
+        "Hello world!"
+    }
+
+    #[test]
+    fn route_with_method_path_and_handler() {
+        let route = route!(POST "/users" => hello as fn() -> _).unwrap();
+
+        assert_eq!(route.method(), Method::POST);
+        assert_eq!(route.to_string(), "POST /users");
+        assert!(route.before.is_empty());
+        assert!(route.after.is_empty());
+        assert_eq!(route.name, None);
+    }
+
+    #[test]
+    fn route_with_all_options() {
+        let route = route!(
+            GET "/users/{id}" => hello as fn() -> _,
+            before: [Auth, Log],
+            after: [Log],
+            name: "user_show",
+            description: "Get a user by ID",
+        )
+        .unwrap();
+
+        assert_eq!(route.to_string(), "GET /users/{id}");
+        assert_eq!(route.before.len(), 2);
+        assert_eq!(route.after.len(), 1);
+        assert_eq!(route.name.as_deref(), Some("user_show"));
+        assert_eq!(route.description.as_deref(), Some("Get a user by ID"));
+    }
+
+    #[test]
+    fn route_options_in_any_order() {
+        let route = route!(
+            GET "/" => hello as fn() -> _,
+            name: "index",
+            after: [Log],
+            before: [Auth]
+        )
+        .unwrap();
+
+        assert_eq!(route.before.len(), 1);
+        assert_eq!(route.after.len(), 1);
+        assert_eq!(route.name.as_deref(), Some("index"));
+    }
+
+    #[test]
+    fn route_with_invalid_path() {
+        let err = route!(GET "users" => hello as fn() -> _).unwrap_err();
+        assert_eq!(err.kind(), &ParseErrorKind::IsNotAbsolute { pos: 0 });
+    }
+}
@@ -0,0 +1,80 @@
+use std::sync::Arc;
+
+use http::{header::CONTENT_TYPE, Response, StatusCode};
+use hyper::body::Bytes;
+use prometheus::{Encoder, Registry, TextEncoder};
+
+use crate::{Handler, Request};
+
+/// Content type of the Prometheus text exposition format.
+const TEXT_FORMAT: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+/// Serves the metrics of a [`Registry`] in the Prometheus text exposition
+/// format.
+///
+/// # Examples
+///
+/// ```
+/// use std::sync::Arc;
+///
+/// use genuine::{metrics::PrometheusEndpoint, App};
+/// use prometheus::Registry;
+///
+/// let registry = Arc::new(Registry::new());
+/// let mut app = App::new();
+///
+/// app.get("/metrics", PrometheusEndpoint::new(registry)).unwrap();
+/// ```
+pub struct PrometheusEndpoint {
+    registry: Arc<Registry>,
+}
+
+impl PrometheusEndpoint {
+    pub fn new(registry: Arc<Registry>) -> Self {
+        Self { registry }
+    }
+}
+
+impl Handler for PrometheusEndpoint {
+    fn handle_request(&self, _: Request) -> Response<Bytes> {
+        let mut buffer = Vec::new();
+        let encoded = TextEncoder::new().encode(&self.registry.gather(), &mut buffer);
+
+        match encoded {
+            Ok(()) => Response::builder()
+                .header(CONTENT_TYPE, TEXT_FORMAT)
+                .body(buffer.into())
+                .unwrap(),
+            Err(err) => Response::builder()
+                .status(StatusCode::INTERNAL_SERVER_ERROR)
+                .body(err.to_string().into())
+                .unwrap(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use prometheus::IntCounter;
+
+    use super::*;
+
+    #[test]
+    fn endpoint_exposes_registered_metrics() {
+        let registry = Arc::new(Registry::new());
+        let counter = IntCounter::new("requests_total", "Number of requests").unwrap();
+        registry.register(Box::new(counter.clone())).unwrap();
+        counter.inc_by(3);
+
+        let endpoint = PrometheusEndpoint::new(registry);
+        let req = Request::new(http::Request::new(Bytes::new()), Vec::new());
+        let res = endpoint.handle_request(req);
+
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.headers()[CONTENT_TYPE], TEXT_FORMAT);
+
+        let body = std::str::from_utf8(res.body()).unwrap();
+        assert!(body.contains("# TYPE requests_total counter\n"));
+        assert!(body.contains("requests_total 3\n"));
+    }
+}
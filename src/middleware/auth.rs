@@ -0,0 +1,138 @@
+use std::{fmt, marker::PhantomData, mem};
+
+use http::{header::WWW_AUTHENTICATE, HeaderValue, Request, Response, StatusCode};
+use hyper::body::Bytes;
+
+use super::Middleware;
+use crate::FromRequest;
+
+/// Rejects requests without a valid credential `T` with
+/// `401 Unauthorized`.
+///
+/// The credential is extracted like a handler parameter, e.g. a
+/// [`BearerToken`](crate::extract::BearerToken). If that succeeds, it is
+/// stored in the request, and handlers get it with the
+/// [`Auth`](crate::extract::Auth) extractor. Otherwise the response carries
+/// a `WWW-Authenticate` header with the given challenge.
+///
+/// # Examples
+///
+/// ```
+/// use genuine::{
+///     extract::{Auth, BearerToken, PathParams},
+///     middleware::Authenticate,
+///     App,
+/// };
+///
+/// fn post(Auth(BearerToken(token)): Auth<BearerToken>, params: PathParams) -> String {
+///     format!("{token} reads post {}", params.0["id"])
+/// }
+///
+/// let mut app = App::new();
+/// app.before(Authenticate::<BearerToken>::new(r#"Bearer realm="api""#));
+/// app.get("/posts/{id}", post as fn(_, _) -> _).unwrap();
+/// ```
+pub struct Authenticate<T> {
+    challenge: HeaderValue,
+    credential: PhantomData<fn() -> T>,
+}
+
+impl<T> Authenticate<T> {
+    /// # Panics
+    ///
+    /// Panics if `challenge` is not a valid header value.
+    pub fn new(challenge: &str) -> Self {
+        Self {
+            challenge: HeaderValue::from_str(challenge).expect("invalid authentication challenge"),
+            credential: PhantomData,
+        }
+    }
+}
+
+impl<T> Clone for Authenticate<T> {
+    fn clone(&self) -> Self {
+        Self {
+            challenge: self.challenge.clone(),
+            credential: PhantomData,
+        }
+    }
+}
+
+impl<T> fmt::Debug for Authenticate<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Authenticate")
+            .field("challenge", &self.challenge)
+            .finish()
+    }
+}
+
+impl<T> Middleware for Authenticate<T>
+where
+    T: FromRequest + Clone + Send + Sync + 'static,
+{
+    fn before(&self, req: &mut Request<Bytes>) -> Option<Response<Bytes>> {
+        // extractors take the request of the router, so lend it ours
+        let lent = crate::Request::new(mem::take(req), Vec::new());
+        let credential = T::from_request(&lent);
+        *req = lent.into_inner();
+
+        match credential {
+            Ok(credential) => {
+                req.extensions_mut().insert(credential);
+                None
+            }
+            Err(_) => {
+                let res = Response::builder()
+                    .status(StatusCode::UNAUTHORIZED)
+                    .header(WWW_AUTHENTICATE, self.challenge.clone())
+                    .body(Bytes::from_static(b"Unauthorized"))
+                    .unwrap();
+                Some(res)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use http::Method;
+
+    use super::*;
+    use crate::{
+        extract::{Auth, BearerToken, PathParams},
+        router::Router,
+        testing::TestClient,
+    };
+
+    fn post(Auth(BearerToken(token)): Auth<BearerToken>, params: PathParams) -> String {
+        format!("{token} reads post {}", params.0["id"])
+    }
+
+    #[tokio::test]
+    async fn credentials_reach_the_handler() {
+        let mut router = Router::new();
+        router
+            .before
+            .push(Box::new(Authenticate::<BearerToken>::new("Bearer")));
+        router.groups[0]
+            .add(Method::GET, "/posts/{id}", post as fn(_, _) -> _)
+            .unwrap();
+        let client = TestClient::from_router(router);
+
+        let res = client
+            .get("/posts/7")
+            .header("authorization", "Bearer alice")
+            .send()
+            .await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.text().await, "alice reads post 7");
+
+        let res = client
+            .get("/posts/7")
+            .header("authorization", "Basic YWxpY2U=")
+            .send()
+            .await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(res.headers()[WWW_AUTHENTICATE], "Bearer");
+    }
+}
@@ -0,0 +1,448 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, Mutex},
+};
+
+use http::{
+    header::{ACCEPT_ENCODING, AUTHORIZATION, CACHE_CONTROL, COOKIE, SET_COOKIE, VARY},
+    HeaderMap, Method, Response, StatusCode,
+};
+use hyper::body::Bytes;
+
+use super::Middleware;
+use crate::Request;
+
+/// The cache key function of the matched route, see
+/// [`Route::cache_key`](crate::Route::cache_key).
+#[derive(Clone, Copy)]
+pub(crate) struct CacheKey(pub(crate) fn(&Request) -> String);
+
+/// Asks the router to look the request up in a cache once all `before`
+/// middlewares let it through, see [`CacheLookup::respond`].
+#[derive(Clone)]
+pub(crate) struct CacheLookup(LruCache);
+
+impl CacheLookup {
+    /// Returns the cached response to `req`, or remembers the key to cache
+    /// the response under for the `after` hook of [`LruCache`].
+    pub(crate) fn respond(&self, req: &mut http::Request<Bytes>) -> Option<Response<Bytes>> {
+        let cache = &self.0;
+        let wrapped = Request::new(std::mem::take(req), Vec::new());
+        let cached = cache.get(&wrapped);
+        let key = cached.is_none().then(|| cache.entry_key(&wrapped));
+        *req = wrapped.into_inner();
+
+        if let Some(key) = key {
+            req.extensions_mut().insert(Missed(key));
+        }
+        cached
+    }
+}
+
+/// The key a missed response is cached under, passed from the router to
+/// the `after` hook of [`LruCache`].
+#[derive(Clone)]
+struct Missed(String);
+
+#[derive(Debug, Clone)]
+struct Entry {
+    status: StatusCode,
+    headers: HeaderMap,
+    body: Bytes,
+}
+
+#[derive(Debug, Default)]
+struct Entries {
+    entries: HashMap<String, Entry>,
+    /// Keys from least to most recently used.
+    order: VecDeque<String>,
+}
+
+impl Entries {
+    fn touch(&mut self, key: &str) {
+        if let Some(pos) = self.order.iter().position(|k| k == key) {
+            let key = self.order.remove(pos).unwrap();
+            self.order.push_back(key);
+        }
+    }
+}
+
+/// Caches responses, evicting the least recently used one when full.
+///
+/// Responses are cached under the full request URI, unless the matched
+/// route has a [`cache_key`](crate::Route::cache_key). Cloning the cache is
+/// cheap, all clones share the same entries.
+///
+/// Responses are kept apart by the `Accept-Encoding` of the request, so
+/// that a compressed response is only served to clients that can decode it.
+/// Responses that set a cookie, are `Cache-Control: private` or `no-store`,
+/// or vary by other request headers are not cached.
+///
+/// Registered with both [`App::before`](crate::App::before) and
+/// [`App::after`](crate::App::after), the cache answers `GET` and `HEAD`
+/// requests it has a response for without calling the handler, and caches
+/// the successful responses of the others. The lookup happens after all
+/// `before` middlewares, including those of groups and routes, let the
+/// request through. Requests with credentials, i.e. an `Authorization` or
+/// `Cookie` header, are neither answered from nor stored in the cache.
+/// Path parameters are not available to cache key functions there, since
+/// they run before the handler. Handlers can also use the cache directly
+/// with [`LruCache::get`] and [`LruCache::insert`].
+///
+/// # Examples
+///
+/// ```
+/// use genuine::{middleware::LruCache, App};
+///
+/// let cache = LruCache::new(100);
+/// let mut app = App::new();
+/// app.before(cache.clone()).after(cache);
+/// ```
+///
+/// ```
+/// use genuine::{middleware::LruCache, Request};
+/// use http::Response;
+/// use hyper::body::Bytes;
+///
+/// let cache = LruCache::new(100);
+/// let req = Request::new(http::Request::get("/users").body(Bytes::new()).unwrap(), Vec::new());
+///
+/// assert!(cache.get(&req).is_none());
+/// cache.insert(&req, &Response::new(Bytes::from_static(b"alice, bob")));
+/// assert_eq!(cache.get(&req).unwrap().body(), "alice, bob");
+/// ```
+#[derive(Debug, Clone)]
+pub struct LruCache {
+    capacity: usize,
+    entries: Arc<Mutex<Entries>>,
+}
+
+impl LruCache {
+    /// Create a cache holding up to `capacity` responses.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: Arc::default(),
+        }
+    }
+
+    /// Returns the cache key of `req`.
+    pub fn key(&self, req: &Request) -> String {
+        match req.extensions().get::<CacheKey>() {
+            Some(CacheKey(key)) => key(req),
+            None => req.uri().to_string(),
+        }
+    }
+
+    /// Returns the key of `req` together with its `Accept-Encoding`, which
+    /// the response is stored under.
+    fn entry_key(&self, req: &Request) -> String {
+        let key = self.key(req);
+        match req.headers().get(ACCEPT_ENCODING) {
+            Some(codings) => format!("{key}\n{}", String::from_utf8_lossy(codings.as_bytes())),
+            None => key,
+        }
+    }
+
+    /// Returns a copy of the response cached for `req`.
+    pub fn get(&self, req: &Request) -> Option<Response<Bytes>> {
+        let key = self.entry_key(req);
+        let mut entries = self.lock();
+
+        let entry = entries.entries.get(&key)?.clone();
+        entries.touch(&key);
+
+        let mut res = Response::new(entry.body);
+        *res.status_mut() = entry.status;
+        *res.headers_mut() = entry.headers;
+        Some(res)
+    }
+
+    /// Cache `res` as the response to `req`, unless it must not be shared,
+    /// see [`LruCache`].
+    pub fn insert(&self, req: &Request, res: &Response<Bytes>) {
+        self.insert_with_key(self.entry_key(req), res);
+    }
+
+    fn insert_with_key(&self, key: String, res: &Response<Bytes>) {
+        if self.capacity == 0 || !is_shareable(res.headers()) {
+            return;
+        }
+
+        let entry = Entry {
+            status: res.status(),
+            headers: res.headers().clone(),
+            body: res.body().clone(),
+        };
+
+        let mut entries = self.lock();
+        if entries.entries.insert(key.clone(), entry).is_some() {
+            entries.touch(&key);
+            return;
+        }
+
+        entries.order.push_back(key);
+        if entries.order.len() > self.capacity {
+            let oldest = entries.order.pop_front().unwrap();
+            entries.entries.remove(&oldest);
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Entries> {
+        self.entries.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+/// Whether a response with `headers` may be served to other clients.
+fn is_shareable(headers: &HeaderMap) -> bool {
+    let values = |name| {
+        headers
+            .get_all(name)
+            .into_iter()
+            .map(|value| value.to_str().unwrap_or("*"))
+            .flat_map(|value| value.split(','))
+            .map(str::trim)
+    };
+
+    let private = values(CACHE_CONTROL).any(|directive| {
+        let name = directive.split('=').next().unwrap_or_default().trim();
+        name.eq_ignore_ascii_case("private") || name.eq_ignore_ascii_case("no-store")
+    });
+    // only responses that vary by the content coding are told apart
+    let varies = values(VARY).any(|name| !name.eq_ignore_ascii_case("accept-encoding"));
+
+    !private && !varies && !headers.contains_key(SET_COOKIE)
+}
+
+impl Middleware for LruCache {
+    fn before(&self, req: &mut http::Request<Bytes>) -> Option<Response<Bytes>> {
+        let cacheable = matches!(*req.method(), Method::GET | Method::HEAD)
+            && !req.headers().contains_key(AUTHORIZATION)
+            && !req.headers().contains_key(COOKIE);
+
+        // the router looks the request up once the middlewares of its group
+        // and route ran, e.g. to authenticate it
+        if cacheable {
+            req.extensions_mut().insert(CacheLookup(self.clone()));
+        }
+        None
+    }
+
+    fn after(&self, res: &mut Response<Bytes>) {
+        if !res.status().is_success() {
+            return;
+        }
+        if let Some(Missed(key)) = res.extensions_mut().remove::<Missed>() {
+            self.insert_with_key(key, res);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use http::{HeaderValue, Method};
+
+    use super::*;
+    use crate::{
+        extract::{Auth, BearerToken},
+        middleware::Authenticate,
+        router::Router,
+        testing::TestClient,
+        Group, Handler,
+    };
+
+    fn request(uri: &str) -> Request {
+        Request::new(
+            http::Request::get(uri).body(Bytes::new()).unwrap(),
+            Vec::new(),
+        )
+    }
+
+    fn response(body: &'static str) -> Response<Bytes> {
+        Response::new(Bytes::from_static(body.as_bytes()))
+    }
+
+    #[derive(Clone)]
+    struct Search {
+        cache: LruCache,
+        searches: Arc<AtomicUsize>,
+    }
+
+    impl Handler for Search {
+        fn handle_request(&self, req: Request) -> Response<Bytes> {
+            if let Some(res) = self.cache.get(&req) {
+                return res;
+            }
+
+            let n = self.searches.fetch_add(1, Ordering::SeqCst) + 1;
+            let res = Response::new(format!("search #{n}").into());
+            self.cache.insert(&req, &res);
+            res
+        }
+    }
+
+    #[derive(Clone, Default)]
+    struct Count(Arc<AtomicUsize>);
+
+    impl Handler for Count {
+        fn handle_request(&self, req: Request) -> Response<Bytes> {
+            let n = self.0.fetch_add(1, Ordering::SeqCst) + 1;
+            let status = match req.uri().path() {
+                "/missing" => StatusCode::NOT_FOUND,
+                _ => StatusCode::OK,
+            };
+            let mut res = Response::new(format!("count #{n}").into());
+            *res.status_mut() = status;
+            res
+        }
+    }
+
+    #[tokio::test]
+    async fn middleware_serves_cached_responses() {
+        let count = Count::default();
+        let cache = LruCache::new(10);
+        let mut router = Router::new();
+        router.before.push(Box::new(cache.clone()));
+        router.after.push(Box::new(cache));
+        let group = &mut router.groups[0];
+        for path in ["/users", "/missing"] {
+            group.add(Method::GET, path, count.clone()).unwrap();
+            group.add(Method::POST, path, count.clone()).unwrap();
+        }
+        let client = &TestClient::from_router(router);
+        let send =
+            |method, uri| async move { client.request(method, uri).send().await.text().await };
+
+        assert_eq!(send(Method::GET, "/users").await, "count #1");
+        assert_eq!(send(Method::GET, "/users").await, "count #1");
+        assert_eq!(send(Method::POST, "/users").await, "count #2");
+        assert_eq!(send(Method::POST, "/users").await, "count #3");
+        assert_eq!(send(Method::GET, "/missing").await, "count #4");
+        assert_eq!(send(Method::GET, "/missing").await, "count #5");
+    }
+
+    fn query(req: &Request) -> String {
+        let query = req.uri().query().unwrap_or_default();
+        let q = query
+            .split('&')
+            .find_map(|pair| pair.strip_prefix("q="))
+            .unwrap_or_default();
+        format!("search:{q}")
+    }
+
+    #[tokio::test]
+    async fn custom_key_shares_responses_across_pages() {
+        let search = Search {
+            cache: LruCache::new(10),
+            searches: Arc::default(),
+        };
+        let mut router = Router::new();
+        router.groups[0]
+            .add(Method::GET, "/search", search.clone())
+            .unwrap()
+            .cache_key(query);
+        let client = &TestClient::from_router(router);
+        let body = |uri| async move { client.get(uri).send().await.text().await };
+
+        assert_eq!(body("/search?q=rust&page=1").await, "search #1");
+        assert_eq!(body("/search?q=rust&page=2").await, "search #1");
+        assert_eq!(body("/search?q=go&page=1").await, "search #2");
+        assert_eq!(search.searches.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn full_uri_is_the_default_key() {
+        let cache = LruCache::new(10);
+        cache.insert(&request("/search?q=rust&page=1"), &response("page 1"));
+
+        assert!(cache.get(&request("/search?q=rust&page=2")).is_none());
+        assert_eq!(
+            cache.key(&request("/search?q=rust&page=1")),
+            "/search?q=rust&page=1"
+        );
+    }
+
+    fn secret(Auth(BearerToken(token)): Auth<BearerToken>) -> String {
+        format!("secret of {token}")
+    }
+
+    #[tokio::test]
+    async fn group_middlewares_run_before_the_lookup() {
+        let cache = LruCache::new(10);
+        let mut admin = Group::new("/admin");
+        admin
+            .before
+            .push(Box::new(Authenticate::<BearerToken>::new("Bearer")));
+        admin.get("/me", secret as fn(_) -> _).unwrap();
+
+        let mut router = Router::new();
+        router.before.push(Box::new(cache.clone()));
+        router.after.push(Box::new(cache));
+        router.groups.push(admin);
+        let client = TestClient::from_router(router);
+        let me = |token: &str| {
+            client
+                .get("/admin/me")
+                .header("authorization", format!("Bearer {token}"))
+        };
+
+        assert_eq!(me("alice").send().await.text().await, "secret of alice");
+        assert_eq!(client.get("/admin/me").send().await.status(), 401);
+        assert_eq!(me("bob").send().await.text().await, "secret of bob");
+    }
+
+    #[test]
+    fn private_responses_are_not_cached() {
+        let cache = LruCache::new(10);
+
+        for (name, value) in [
+            (SET_COOKIE, "session=alice"),
+            (CACHE_CONTROL, "private"),
+            (CACHE_CONTROL, "max-age=60, no-store"),
+            (VARY, "accept-language"),
+        ] {
+            let mut res = response("private");
+            res.headers_mut()
+                .insert(name, HeaderValue::from_static(value));
+            cache.insert(&request("/me"), &res);
+            assert!(cache.get(&request("/me")).is_none(), "{value}");
+        }
+
+        let mut res = response("shared");
+        res.headers_mut()
+            .insert(VARY, HeaderValue::from_static("Accept-Encoding"));
+        cache.insert(&request("/me"), &res);
+        assert!(cache.get(&request("/me")).is_some());
+    }
+
+    #[test]
+    fn content_codings_are_cached_apart() {
+        let cache = LruCache::new(10);
+        let gzip = |uri| {
+            let req = http::Request::get(uri)
+                .header(ACCEPT_ENCODING, "gzip")
+                .body(Bytes::new())
+                .unwrap();
+            Request::new(req, Vec::new())
+        };
+        cache.insert(&gzip("/users"), &response("gzipped"));
+
+        assert!(cache.get(&request("/users")).is_none());
+        assert_eq!(cache.get(&gzip("/users")).unwrap().body(), "gzipped");
+    }
+
+    #[test]
+    fn least_recently_used_response_is_evicted() {
+        let cache = LruCache::new(2);
+        cache.insert(&request("/a"), &response("a"));
+        cache.insert(&request("/b"), &response("b"));
+        cache.get(&request("/a"));
+        cache.insert(&request("/c"), &response("c"));
+
+        assert!(cache.get(&request("/a")).is_some());
+        assert!(cache.get(&request("/b")).is_none());
+        assert!(cache.get(&request("/c")).is_some());
+    }
+}
@@ -0,0 +1,221 @@
+use encoding_rs::{Encoding, UTF_8};
+use http::{
+    header::{CONTENT_LENGTH, CONTENT_TYPE},
+    HeaderValue, Request, Response, StatusCode,
+};
+use hyper::body::Bytes;
+
+use super::Middleware;
+use crate::IntoResponse;
+
+/// The request body is in a charset [`CharsetDecode`] does not know.
+#[derive(Debug, thiserror::Error, PartialEq)]
+#[error("unsupported charset {0:?}")]
+pub struct UnsupportedCharset(pub String);
+
+impl IntoResponse for UnsupportedCharset {
+    fn into_response(self) -> Response<Bytes> {
+        Response::builder()
+            .status(StatusCode::UNSUPPORTED_MEDIA_TYPE)
+            .body(self.to_string().into())
+            .unwrap()
+    }
+}
+
+/// Transcodes request bodies to UTF-8 according to the `charset` parameter
+/// of their `Content-Type`.
+///
+/// Bodies without a charset, or that are UTF-8 already, are left alone.
+/// Afterwards the `Content-Type` says `charset=utf-8`, so extractors that
+/// expect text can treat the body as UTF-8. Malformed input is decoded
+/// lossily, with invalid sequences replaced by U+FFFD.
+///
+/// As a `before` middleware it decodes the request before the handler
+/// extracts it, and answers `415 Unsupported Media Type` if the charset is
+/// unknown.
+///
+/// # Examples
+///
+/// ```
+/// use genuine::{middleware::CharsetDecode, App};
+///
+/// let mut app = App::new();
+/// app.before(CharsetDecode);
+/// ```
+///
+/// The body can also be decoded by hand:
+///
+/// ```
+/// use genuine::middleware::CharsetDecode;
+/// use http::Request;
+/// use hyper::body::Bytes;
+///
+/// let req = Request::post("/notes")
+///     .header("content-type", "text/plain; charset=windows-1252")
+///     .body(Bytes::from_static(b"caf\xe9"))
+///     .unwrap();
+///
+/// let req = CharsetDecode.decode(req).unwrap();
+/// assert_eq!(req.body(), "café");
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CharsetDecode;
+
+impl CharsetDecode {
+    /// Transcode the body of `req` to UTF-8.
+    ///
+    /// Returns [`UnsupportedCharset`], which responds with
+    /// `415 Unsupported Media Type`, if the charset is unknown.
+    pub fn decode(&self, req: Request<Bytes>) -> Result<Request<Bytes>, UnsupportedCharset> {
+        let Some((mime, label)) = req
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(split_charset)
+        else {
+            return Ok(req);
+        };
+
+        let Some(encoding) = Encoding::for_label(label.as_bytes()) else {
+            return Err(UnsupportedCharset(label.to_owned()));
+        };
+
+        if encoding == UTF_8 {
+            return Ok(req);
+        }
+
+        let content_type = format!("{mime}; charset=utf-8");
+        let (mut parts, body) = req.into_parts();
+        let (decoded, _, _) = encoding.decode(&body);
+        let body = Bytes::from(decoded.into_owned());
+
+        // `mime` came from a valid header value, so this one is valid too
+        parts
+            .headers
+            .insert(CONTENT_TYPE, HeaderValue::from_str(&content_type).unwrap());
+        if parts.headers.contains_key(CONTENT_LENGTH) {
+            parts.headers.insert(CONTENT_LENGTH, body.len().into());
+        }
+
+        Ok(Request::from_parts(parts, body))
+    }
+}
+
+impl Middleware for CharsetDecode {
+    fn before(&self, req: &mut Request<Bytes>) -> Option<Response<Bytes>> {
+        match self.decode(std::mem::take(req)) {
+            Ok(decoded) => {
+                *req = decoded;
+                None
+            }
+            Err(err) => Some(err.into_response()),
+        }
+    }
+}
+
+/// Splits a `Content-Type` into everything but the charset, and the charset
+/// label. Returns [`None`] if there is no charset parameter.
+fn split_charset(content_type: &str) -> Option<(String, &str)> {
+    let mut charset = None;
+    let mut rest = Vec::new();
+
+    for param in content_type.split(';').map(str::trim) {
+        match param.split_once('=') {
+            Some((name, value)) if name.trim().eq_ignore_ascii_case("charset") => {
+                charset = Some(value.trim().trim_matches('"'));
+            }
+            _ => rest.push(param),
+        }
+    }
+
+    charset.map(|charset| (rest.join("; "), charset))
+}
+
+#[cfg(test)]
+mod tests {
+    use http::Method;
+
+    use super::*;
+    use crate::{router::Router, testing::TestClient};
+
+    fn echo(body: String) -> String {
+        body
+    }
+
+    #[tokio::test]
+    async fn router_decodes_bodies_before_extraction() {
+        let mut router = Router::new();
+        router.before.push(Box::new(CharsetDecode));
+        router.groups[0]
+            .add(Method::POST, "/", echo as fn(_) -> _)
+            .unwrap();
+        let client = TestClient::from_router(router);
+
+        for (content_type, status, body) in [
+            ("text/plain; charset=windows-1252", 200, "café"),
+            (
+                "text/plain; charset=klingon",
+                415,
+                "unsupported charset \"klingon\"",
+            ),
+        ] {
+            let res = client
+                .post("/")
+                .header("content-type", content_type)
+                .body(&b"caf\xe9"[..])
+                .send()
+                .await;
+
+            assert_eq!(res.status(), status, "{content_type}");
+            assert_eq!(res.text().await, body);
+        }
+    }
+
+    fn post(content_type: &str, body: &'static [u8]) -> Request<Bytes> {
+        Request::post("/")
+            .header(CONTENT_TYPE, content_type)
+            .header(CONTENT_LENGTH, body.len())
+            .body(Bytes::from_static(body))
+            .unwrap()
+    }
+
+    #[test]
+    fn windows_1252_is_transcoded() {
+        let req = post("text/plain; charset=windows-1252", b"na\xefve caf\xe9");
+        let req = CharsetDecode.decode(req).unwrap();
+
+        assert_eq!(std::str::from_utf8(req.body()), Ok("naïve café"));
+        assert_eq!(req.headers()[CONTENT_TYPE], "text/plain; charset=utf-8");
+        assert_eq!(req.headers()[CONTENT_LENGTH], "12");
+    }
+
+    #[test]
+    fn other_parameters_are_kept() {
+        let req = post("text/plain; format=flowed; charset=\"ISO-8859-1\"", b"\xe9");
+        let req = CharsetDecode.decode(req).unwrap();
+
+        assert_eq!(req.body(), "é");
+        assert_eq!(
+            req.headers()[CONTENT_TYPE],
+            "text/plain; format=flowed; charset=utf-8"
+        );
+    }
+
+    #[test]
+    fn bodies_without_charset_are_untouched() {
+        let req = post("application/octet-stream", b"\xff\xfe");
+        let req = CharsetDecode.decode(req).unwrap();
+        assert_eq!(req.body(), &b"\xff\xfe"[..]);
+    }
+
+    #[test]
+    fn unknown_charsets_are_rejected() {
+        let req = post("text/plain; charset=klingon", b"");
+        let err = CharsetDecode.decode(req).unwrap_err();
+        assert_eq!(err, UnsupportedCharset("klingon".into()));
+        assert_eq!(
+            err.into_response().status(),
+            StatusCode::UNSUPPORTED_MEDIA_TYPE
+        );
+    }
+}
@@ -0,0 +1,354 @@
+use std::{
+    io,
+    pin::pin,
+    sync::Arc,
+    task::{Context, Poll, Wake, Waker},
+};
+
+use async_compression::{
+    tokio::bufread::{BrotliEncoder, DeflateEncoder, GzipEncoder},
+    Level,
+};
+use http::{
+    header::{
+        ACCEPT_ENCODING, CONTENT_ENCODING, CONTENT_LENGTH, CONTENT_RANGE, CONTENT_TYPE, ETAG,
+    },
+    HeaderMap, HeaderValue, Request, Response, StatusCode,
+};
+use hyper::body::Bytes;
+use tokio::io::{AsyncRead, ReadBuf};
+
+use super::Middleware;
+use crate::{body::Streaming, response::append_vary};
+
+/// Responses with smaller bodies are not compressed, unless configured
+/// otherwise.
+const DEFAULT_MIN_SIZE: usize = 1024;
+
+/// A content coding that [`Compress`] supports, from most to least
+/// preferred.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Coding {
+    Brotli,
+    Gzip,
+    Deflate,
+}
+
+impl Coding {
+    const ALL: [Coding; 3] = [Coding::Brotli, Coding::Gzip, Coding::Deflate];
+
+    fn name(self) -> &'static str {
+        match self {
+            Coding::Brotli => "br",
+            Coding::Gzip => "gzip",
+            Coding::Deflate => "deflate",
+        }
+    }
+
+    /// Picks the most preferred coding the `Accept-Encoding` headers allow.
+    fn negotiate(headers: &HeaderMap) -> Option<Coding> {
+        let mut accepted = Vec::new();
+        let mut rejected = Vec::new();
+        let mut wildcard = false;
+
+        let codings = headers
+            .get_all(ACCEPT_ENCODING)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','));
+        for coding in codings {
+            let mut params = coding.split(';').map(str::trim);
+            let name = params.next().unwrap_or_default().to_ascii_lowercase();
+            let refused = params.any(|param| {
+                param
+                    .strip_prefix("q=")
+                    .and_then(|q| q.parse::<f32>().ok())
+                    .is_some_and(|q| q == 0.0)
+            });
+
+            match Coding::ALL.into_iter().find(|c| c.name() == name) {
+                Some(coding) if refused => rejected.push(coding),
+                Some(coding) => accepted.push(coding),
+                None => wildcard |= name == "*" && !refused,
+            }
+        }
+
+        Coding::ALL
+            .into_iter()
+            .find(|coding| accepted.contains(coding) || (wildcard && !rejected.contains(coding)))
+    }
+
+    fn encode(self, body: &[u8]) -> io::Result<Vec<u8>> {
+        match self {
+            // the best brotli quality is too slow to compress on the fly
+            Coding::Brotli => read_to_end(BrotliEncoder::with_quality(body, Level::Precise(4))),
+            Coding::Gzip => read_to_end(GzipEncoder::new(body)),
+            Coding::Deflate => read_to_end(DeflateEncoder::new(body)),
+        }
+    }
+}
+
+struct NoopWaker;
+
+impl Wake for NoopWaker {
+    fn wake(self: Arc<Self>) {}
+}
+
+/// Reads all of `reader`, which must never wait, i.e. encode an in-memory
+/// body.
+fn read_to_end<R: AsyncRead>(reader: R) -> io::Result<Vec<u8>> {
+    let mut reader = pin!(reader);
+    let waker = Waker::from(Arc::new(NoopWaker));
+    let mut cx = Context::from_waker(&waker);
+
+    let mut encoded = Vec::new();
+    let mut chunk = [0; 8 * 1024];
+    loop {
+        let mut buf = ReadBuf::new(&mut chunk);
+        match reader.as_mut().poll_read(&mut cx, &mut buf) {
+            Poll::Ready(Ok(())) if buf.filled().is_empty() => return Ok(encoded),
+            Poll::Ready(Ok(())) => encoded.extend_from_slice(buf.filled()),
+            Poll::Ready(Err(err)) => return Err(err),
+            Poll::Pending => unreachable!("encoding an in-memory body never waits"),
+        }
+    }
+}
+
+/// Compresses responses with brotli, gzip or deflate, whichever the client
+/// accepts, in this order of preference.
+///
+/// Register it with both [`App::before`](crate::App::before), where it
+/// reads the `Accept-Encoding` header, and [`App::after`](crate::App::after),
+/// where it compresses the response.
+///
+/// Responses smaller than 1 KiB by default, responses that already have a
+/// `Content-Encoding`, partial responses, whose ranges refer to the
+/// uncompressed body, and images, audio and video, which are compressed
+/// already, are sent as they are.
+///
+/// The `ETag` of a compressed response is made weak, since its bytes differ
+/// from those of the uncompressed one.
+///
+/// # Examples
+///
+/// ```
+/// use genuine::{middleware::Compress, App};
+///
+/// let compress = Compress::new().min_size(4096);
+///
+/// let mut app = App::new();
+/// app.before(compress).after(compress);
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct Compress {
+    min_size: usize,
+}
+
+impl Compress {
+    pub fn new() -> Self {
+        Self {
+            min_size: DEFAULT_MIN_SIZE,
+        }
+    }
+
+    /// Only compress responses with at least `min_size` bytes.
+    pub fn min_size(mut self, min_size: usize) -> Self {
+        self.min_size = min_size;
+        self
+    }
+
+    fn is_compressible(&self, res: &Response<Bytes>) -> bool {
+        let content_type = res
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+        let compressed = ["image/", "audio/", "video/"]
+            .iter()
+            .any(|media| content_type.starts_with(media))
+            && !content_type.starts_with("image/svg+xml");
+
+        // streams are sent as they are, their body is empty here
+        let streaming = res.extensions().get::<Streaming>().is_some();
+        let partial = res.status() == StatusCode::PARTIAL_CONTENT
+            || res.headers().contains_key(CONTENT_RANGE);
+
+        res.body().len() >= self.min_size
+            && !compressed
+            && !streaming
+            && !partial
+            && !res.headers().contains_key(CONTENT_ENCODING)
+    }
+}
+
+impl Default for Compress {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Middleware for Compress {
+    fn before(&self, req: &mut Request<Bytes>) -> Option<Response<Bytes>> {
+        if let Some(coding) = Coding::negotiate(req.headers()) {
+            req.extensions_mut().insert(coding);
+        }
+        None
+    }
+
+    fn after(&self, res: &mut Response<Bytes>) {
+        if !self.is_compressible(res) {
+            return;
+        }
+        append_vary(res.headers_mut(), &ACCEPT_ENCODING);
+
+        let Some(&coding) = res.extensions().get::<Coding>() else {
+            return;
+        };
+        let Ok(encoded) = coding.encode(res.body()) else {
+            tracing::warn!(coding = coding.name(), "failed to compress response");
+            return;
+        };
+
+        let headers = res.headers_mut();
+        headers.insert(CONTENT_ENCODING, coding.name().parse().unwrap());
+        headers.insert(CONTENT_LENGTH, encoded.len().into());
+        if let Some(etag) = headers.get_mut(ETAG) {
+            weaken(etag);
+        }
+        *res.body_mut() = encoded.into();
+    }
+}
+
+/// Makes a strong entity tag weak, e.g. `"v1"` becomes `W/"v1"`.
+fn weaken(etag: &mut HeaderValue) {
+    if !etag.as_bytes().starts_with(b"W/") {
+        let mut weak = b"W/".to_vec();
+        weak.extend_from_slice(etag.as_bytes());
+        // prefixing a valid header value keeps it valid
+        *etag = HeaderValue::from_bytes(&weak).unwrap();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use http::{header::VARY, HeaderValue, Method};
+
+    use super::*;
+    use crate::{router::Router, testing::TestClient};
+
+    fn accept(value: &'static str) -> HeaderMap {
+        HeaderMap::from_iter([(ACCEPT_ENCODING, HeaderValue::from_static(value))])
+    }
+
+    #[test]
+    fn preferred_coding_is_negotiated() {
+        let negotiate = |value| Coding::negotiate(&accept(value));
+
+        assert_eq!(negotiate("gzip, deflate, br"), Some(Coding::Brotli));
+        assert_eq!(negotiate("deflate, gzip"), Some(Coding::Gzip));
+        assert_eq!(negotiate("br;q=0, deflate"), Some(Coding::Deflate));
+        assert_eq!(negotiate("*, br;q=0"), Some(Coding::Gzip));
+        assert_eq!(negotiate("identity"), None);
+        assert_eq!(Coding::negotiate(&HeaderMap::new()), None);
+    }
+
+    fn client() -> TestClient {
+        let mut router = Router::new();
+        router.before.push(Box::new(Compress::new()));
+        router.after.push(Box::new(Compress::new()));
+        router.groups[0]
+            .add(
+                Method::GET,
+                "/long",
+                (|| "genuine ".repeat(1000)) as fn() -> _,
+            )
+            .unwrap();
+        router.groups[0]
+            .add(Method::GET, "/short", (|| "genuine") as fn() -> _)
+            .unwrap();
+        TestClient::from_router(router)
+    }
+
+    #[tokio::test]
+    async fn large_responses_are_compressed() {
+        let client = client();
+
+        let res = client
+            .get("/long")
+            .header("accept-encoding", "gzip")
+            .send()
+            .await;
+        let headers = res.headers().clone();
+        let body = res.bytes().await;
+        assert_eq!(headers[CONTENT_ENCODING], "gzip");
+        assert_eq!(headers[CONTENT_LENGTH], body.len().to_string());
+        assert_eq!(headers[VARY], "accept-encoding");
+        assert!(body.len() < 1000);
+        assert_eq!(&body[..2], [0x1f, 0x8b]);
+
+        let res = client
+            .get("/long")
+            .header("accept-encoding", "identity")
+            .send()
+            .await;
+        assert!(!res.headers().contains_key(CONTENT_ENCODING));
+        assert_eq!(res.headers()[VARY], "accept-encoding");
+        assert_eq!(res.bytes().await.len(), 8000);
+    }
+
+    #[tokio::test]
+    async fn small_responses_are_not_compressed() {
+        let res = client()
+            .get("/short")
+            .header("accept-encoding", "br, gzip")
+            .send()
+            .await;
+
+        assert!(!res.headers().contains_key(CONTENT_ENCODING));
+        assert!(!res.headers().contains_key(VARY));
+        assert_eq!(res.text().await, "genuine");
+    }
+
+    #[test]
+    fn partial_responses_are_not_compressed() {
+        let compress = Compress::new().min_size(0);
+        let mut res = Response::new(Bytes::from_static(b"genuine"));
+        *res.status_mut() = StatusCode::PARTIAL_CONTENT;
+        res.headers_mut()
+            .insert(CONTENT_RANGE, HeaderValue::from_static("bytes 0-6/8000"));
+        res.extensions_mut().insert(Coding::Gzip);
+
+        compress.after(&mut res);
+        assert!(!res.headers().contains_key(CONTENT_ENCODING));
+        assert_eq!(res.body(), &b"genuine"[..]);
+    }
+
+    #[test]
+    fn compressed_etags_are_weak() {
+        let compress = Compress::new().min_size(0);
+        let respond = |etag| {
+            let mut res = Response::new(Bytes::from_static(b"genuine"));
+            res.headers_mut()
+                .insert(ETAG, HeaderValue::from_static(etag));
+            res.extensions_mut().insert(Coding::Gzip);
+            compress.after(&mut res);
+            res.headers()[ETAG].clone()
+        };
+
+        assert_eq!(respond(r#""v1""#), r#"W/"v1""#);
+        assert_eq!(respond(r#"W/"v1""#), r#"W/"v1""#);
+    }
+
+    #[test]
+    fn media_is_not_compressed() {
+        let compress = Compress::new().min_size(0);
+        let mut res = Response::new(Bytes::from_static(b"\x89PNG"));
+        res.headers_mut()
+            .insert(CONTENT_TYPE, HeaderValue::from_static("image/png"));
+        res.extensions_mut().insert(Coding::Gzip);
+
+        compress.after(&mut res);
+        assert_eq!(res.body(), &b"\x89PNG"[..]);
+    }
+}
@@ -0,0 +1,208 @@
+use std::time::Duration;
+
+use http::{
+    header::{
+        ACCESS_CONTROL_ALLOW_HEADERS, ACCESS_CONTROL_ALLOW_METHODS, ACCESS_CONTROL_ALLOW_ORIGIN,
+        ACCESS_CONTROL_MAX_AGE, ACCESS_CONTROL_REQUEST_METHOD, ORIGIN,
+    },
+    HeaderMap, HeaderName, HeaderValue, Method, Request, Response, StatusCode,
+};
+use hyper::body::Bytes;
+
+use super::Middleware;
+use crate::response::append_vary;
+
+/// Allows browsers to call the app from other origins, see
+/// [CORS](https://developer.mozilla.org/en-US/docs/Web/HTTP/CORS).
+///
+/// Register it with both [`App::before`](crate::App::before), where it
+/// answers preflight requests, and [`App::after`](crate::App::after), where
+/// it adds the CORS headers to all other responses.
+///
+/// By default, any origin may use `GET`, `HEAD` and `POST` without custom
+/// headers.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use genuine::{middleware::Cors, App};
+/// use http::Method;
+///
+/// let cors = Cors::new()
+///     .allow_origin("https://example.com")
+///     .allow_methods([Method::GET, Method::POST])
+///     .allow_headers(["Content-Type"])
+///     .max_age(Duration::from_secs(86400));
+///
+/// let mut app = App::new();
+/// app.before(cors.clone()).after(cors);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Cors {
+    origin: HeaderValue,
+    methods: HeaderValue,
+    headers: Option<HeaderValue>,
+    max_age: Option<Duration>,
+}
+
+impl Cors {
+    pub fn new() -> Self {
+        Self {
+            origin: HeaderValue::from_static("*"),
+            methods: HeaderValue::from_static("GET, HEAD, POST"),
+            headers: None,
+            max_age: None,
+        }
+    }
+
+    /// Only allow requests from `origin`, e.g. `https://example.com`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `origin` is not a valid header value.
+    pub fn allow_origin(mut self, origin: &str) -> Self {
+        self.origin = HeaderValue::from_str(origin).expect("invalid CORS origin");
+        self
+    }
+
+    /// Allow requests with these methods.
+    pub fn allow_methods<I>(mut self, methods: I) -> Self
+    where
+        I: IntoIterator<Item = Method>,
+    {
+        let methods: Vec<_> = methods.into_iter().map(|m| m.to_string()).collect();
+        // methods are tokens, so they form a valid header value
+        self.methods = HeaderValue::from_str(&methods.join(", ")).unwrap();
+        self
+    }
+
+    /// Allow requests to send these headers.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any of `headers` is not a valid header name.
+    pub fn allow_headers<I>(mut self, headers: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+    {
+        let headers: Vec<_> = headers
+            .into_iter()
+            .map(|name| {
+                HeaderName::try_from(name.as_ref())
+                    .expect("invalid CORS header name")
+                    .to_string()
+            })
+            .collect();
+        self.headers = Some(HeaderValue::from_str(&headers.join(", ")).unwrap());
+        self
+    }
+
+    /// Let browsers cache the answer to a preflight request for `max_age`.
+    pub fn max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = Some(max_age);
+        self
+    }
+
+    fn apply(&self, headers: &mut HeaderMap) {
+        headers.insert(ACCESS_CONTROL_ALLOW_ORIGIN, self.origin.clone());
+        headers.insert(ACCESS_CONTROL_ALLOW_METHODS, self.methods.clone());
+        if let Some(allowed) = &self.headers {
+            headers.insert(ACCESS_CONTROL_ALLOW_HEADERS, allowed.clone());
+        }
+        if self.origin != "*" {
+            append_vary(headers, &ORIGIN);
+        }
+    }
+}
+
+impl Default for Cors {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Middleware for Cors {
+    /// Answers preflight requests with `204 No Content`.
+    fn before(&self, req: &mut Request<Bytes>) -> Option<Response<Bytes>> {
+        let preflight = req.method() == Method::OPTIONS
+            && req.headers().contains_key(ORIGIN)
+            && req.headers().contains_key(ACCESS_CONTROL_REQUEST_METHOD);
+        if !preflight {
+            return None;
+        }
+
+        let mut res = Response::new(Bytes::new());
+        *res.status_mut() = StatusCode::NO_CONTENT;
+        self.apply(res.headers_mut());
+        if let Some(max_age) = self.max_age {
+            res.headers_mut()
+                .insert(ACCESS_CONTROL_MAX_AGE, max_age.as_secs().into());
+        }
+        Some(res)
+    }
+
+    fn after(&self, res: &mut Response<Bytes>) {
+        self.apply(res.headers_mut());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use http::header::VARY;
+
+    use super::*;
+    use crate::{router::Router, testing::TestClient};
+
+    fn client(cors: Cors) -> TestClient {
+        let mut router = Router::new();
+        router.before.push(Box::new(cors.clone()));
+        router.after.push(Box::new(cors));
+        router.groups[0]
+            .add(Method::GET, "/api/users", (|| "alice, bob") as fn() -> _)
+            .unwrap();
+        TestClient::from_router(router)
+    }
+
+    #[tokio::test]
+    async fn preflight_requests_are_answered() {
+        let cors = Cors::new()
+            .allow_origin("https://example.com")
+            .allow_methods([Method::GET, Method::POST])
+            .allow_headers(["Content-Type"])
+            .max_age(Duration::from_secs(86400));
+        let res = client(cors)
+            .request(Method::OPTIONS, "/api/users")
+            .header("origin", "https://example.com")
+            .header("access-control-request-method", "POST")
+            .send()
+            .await;
+
+        assert_eq!(res.status(), StatusCode::NO_CONTENT);
+        let headers = res.headers();
+        assert_eq!(headers[ACCESS_CONTROL_ALLOW_ORIGIN], "https://example.com");
+        assert_eq!(headers[ACCESS_CONTROL_ALLOW_METHODS], "GET, POST");
+        assert_eq!(headers[ACCESS_CONTROL_ALLOW_HEADERS], "content-type");
+        assert_eq!(headers[ACCESS_CONTROL_MAX_AGE], "86400");
+        assert_eq!(headers[VARY], "origin");
+    }
+
+    #[tokio::test]
+    async fn responses_get_cors_headers() {
+        let res = client(Cors::new())
+            .get("/api/users")
+            .header("origin", "https://example.com")
+            .send()
+            .await;
+
+        assert_eq!(res.headers()[ACCESS_CONTROL_ALLOW_ORIGIN], "*");
+        assert_eq!(
+            res.headers()[ACCESS_CONTROL_ALLOW_METHODS],
+            "GET, HEAD, POST"
+        );
+        assert!(!res.headers().contains_key(VARY));
+        assert_eq!(res.text().await, "alice, bob");
+    }
+}
@@ -0,0 +1,176 @@
+use std::fmt::Write;
+
+use http::{HeaderMap, Request, Response};
+use hyper::body::Bytes;
+
+use super::Middleware;
+
+/// Request bodies longer than this are truncated in the dump.
+const MAX_BODY_LEN: usize = 1024;
+
+/// Dumps every request and response to `stderr`.
+///
+/// Unlike an access log, this shows the full request line, all headers and
+/// the (truncated) body, which makes it useful while developing. It is a
+/// no-op in release builds unless constructed with [`DebugDump::force`].
+///
+/// # Examples
+///
+/// ```
+/// use genuine::middleware::DebugDump;
+///
+/// let dump = DebugDump::new();
+/// assert_eq!(dump.is_enabled(), cfg!(debug_assertions));
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct DebugDump {
+    enabled: bool,
+}
+
+impl DebugDump {
+    /// Create a dump that is only enabled in debug builds.
+    pub fn new() -> Self {
+        Self {
+            enabled: cfg!(debug_assertions),
+        }
+    }
+
+    /// Create a dump that is enabled regardless of the build profile.
+    pub fn force() -> Self {
+        Self { enabled: true }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Print the request line, headers and body of `req` to `stderr`.
+    pub fn dump_request(&self, req: &Request<Bytes>) {
+        if self.enabled {
+            eprint!("{}", format_request(req));
+        }
+    }
+
+    /// Print the status line and headers of `res` to `stderr`.
+    pub fn dump_response(&self, res: &Response<Bytes>) {
+        if self.enabled {
+            eprint!("{}", format_response(res));
+        }
+    }
+}
+
+impl Default for DebugDump {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Middleware for DebugDump {
+    fn before(&self, req: &mut Request<Bytes>) -> Option<Response<Bytes>> {
+        self.dump_request(req);
+        None
+    }
+
+    fn after(&self, res: &mut Response<Bytes>) {
+        self.dump_response(res);
+    }
+}
+
+fn format_request(req: &Request<Bytes>) -> String {
+    let mut out = format!("> {} {} {:?}\n", req.method(), req.uri(), req.version());
+    format_headers(&mut out, '>', req.headers());
+
+    let body = req.body();
+    if !body.is_empty() {
+        let shown = &body[..body.len().min(MAX_BODY_LEN)];
+        let _ = writeln!(out, "> {}", String::from_utf8_lossy(shown));
+        if body.len() > MAX_BODY_LEN {
+            let _ = writeln!(out, "> ... ({} bytes total)", body.len());
+        }
+    }
+
+    out
+}
+
+fn format_response(res: &Response<Bytes>) -> String {
+    let mut out = format!("< {:?} {}\n", res.version(), res.status());
+    format_headers(&mut out, '<', res.headers());
+    out
+}
+
+fn format_headers(out: &mut String, marker: char, headers: &HeaderMap) {
+    for (name, value) in headers {
+        let value = String::from_utf8_lossy(value.as_bytes());
+        let _ = writeln!(out, "{marker} {name}: {value}");
+    }
+    let _ = writeln!(out, "{marker}");
+}
+
+#[cfg(test)]
+mod tests {
+    use std::process::Command;
+
+    use http::Method;
+    use http_body_util::Full;
+
+    use super::*;
+    use crate::router::Router;
+
+    const CHILD_ENV: &str = "GENUINE_DEBUG_DUMP_CHILD";
+
+    fn created() -> Response<Bytes> {
+        Response::builder()
+            .status(201)
+            .header("content-type", "text/plain")
+            .body(Bytes::from_static(b"Created"))
+            .unwrap()
+    }
+
+    // libtest captures `eprint!` of the test itself, so the test runs the
+    // router in a child process and reads its real stderr
+    #[tokio::test]
+    async fn router_dumps_requests_and_responses_to_stderr() {
+        if std::env::var_os(CHILD_ENV).is_some() {
+            let mut router = Router::new();
+            router.before.push(Box::new(DebugDump::force()));
+            router.after.push(Box::new(DebugDump::force()));
+            router.groups[0]
+                .add(Method::POST, "/users", created as fn() -> _)
+                .unwrap();
+
+            let req = Request::post("/users?page=2")
+                .header("x-ferris", "crab")
+                .body(Full::new(Bytes::from("a".repeat(MAX_BODY_LEN + 1))))
+                .unwrap();
+            router.respond(req).await.unwrap();
+            return;
+        }
+
+        let output = Command::new(std::env::current_exe().unwrap())
+            .args([
+                "--exact",
+                "middleware::debug::tests::router_dumps_requests_and_responses_to_stderr",
+                "--nocapture",
+            ])
+            .env(CHILD_ENV, "1")
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+
+        let stderr = String::from_utf8(output.stderr).unwrap();
+        assert!(
+            stderr.contains("> POST /users?page=2 HTTP/1.1\n"),
+            "{stderr}"
+        );
+        assert!(stderr.contains("> x-ferris: crab\n"), "{stderr}");
+        assert!(stderr.contains(&format!("> {}\n", "a".repeat(MAX_BODY_LEN))));
+        assert!(stderr.contains("> ... (1025 bytes total)\n"), "{stderr}");
+        assert!(stderr.contains("< HTTP/1.1 201 Created\n"), "{stderr}");
+        assert!(stderr.contains("< content-type: text/plain\n"), "{stderr}");
+    }
+
+    #[test]
+    fn force_enables_dump_in_every_profile() {
+        assert!(DebugDump::force().is_enabled());
+    }
+}
@@ -0,0 +1,290 @@
+use std::{
+    fmt::Write,
+    net::SocketAddr,
+    sync::Arc,
+    time::{Instant, SystemTime, UNIX_EPOCH},
+};
+
+use http::{
+    header::{REFERER, USER_AGENT},
+    HeaderMap, HeaderName, Method, Request, Response, Uri, Version,
+};
+use hyper::body::Bytes;
+use tracing::subscriber::NoSubscriber;
+
+use super::Middleware;
+
+/// How [`Logger`] formats a request.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LogFormat {
+    /// The combined log format of Apache, followed by the response time in
+    /// microseconds:
+    ///
+    /// ```text
+    /// 127.0.0.1 - - [10/Oct/2000:13:55:36 +0000] "GET /users HTTP/1.1" 200 2326 "-" "curl/8.5.0" 1042
+    /// ```
+    #[default]
+    Combined,
+    /// A JSON object per request:
+    ///
+    /// ```text
+    /// {"remote":"127.0.0.1","method":"GET","uri":"/users","status":200,"size":2326,"duration_us":1042}
+    /// ```
+    Json,
+}
+
+/// What the logger learns about a request before it is handled.
+#[derive(Debug, Clone)]
+struct Started {
+    at: Instant,
+    time: SystemTime,
+    remote: Option<SocketAddr>,
+    method: Method,
+    uri: Uri,
+    version: Version,
+    referer: Option<String>,
+    user_agent: Option<String>,
+}
+
+/// Logs every request with its method, URI, status, response size and
+/// response time.
+///
+/// Each request is logged as one line, see [`LogFormat`]. Lines are emitted
+/// through [`tracing`] at the `INFO` level, within the span of the request,
+/// or printed to stderr if no tracing subscriber is installed.
+///
+/// Add the logger first, with [`App::before`](crate::App::before) and
+/// [`App::after`](crate::App::after), so it sees requests before and
+/// responses after all other middlewares.
+///
+/// # Examples
+///
+/// ```
+/// use genuine::{
+///     middleware::{LogFormat, Logger},
+///     App,
+/// };
+///
+/// let logger = Logger::new()
+///     .format(LogFormat::Json)
+///     .exclude_paths(&["/health"]);
+///
+/// let mut app = App::new();
+/// app.before(logger.clone()).after(logger);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Logger {
+    format: LogFormat,
+    excluded: Arc<[String]>,
+}
+
+impl Logger {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn format(mut self, format: LogFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Do not log requests to these paths, e.g. health checks.
+    pub fn exclude_paths(mut self, paths: &[&str]) -> Self {
+        self.excluded = paths.iter().map(|&path| path.to_owned()).collect();
+        self
+    }
+
+    fn line(&self, started: &Started, res: &Response<Bytes>) -> String {
+        let status = res.status().as_u16();
+        let size = res.body().len();
+        let duration_us = started.at.elapsed().as_micros();
+        let remote = started.remote.map(|addr| addr.ip().to_string());
+
+        match self.format {
+            LogFormat::Combined => {
+                let quoted = |value: &Option<String>| match value {
+                    Some(value) => format!("{value:?}"),
+                    None => r#""-""#.to_owned(),
+                };
+                format!(
+                    r#"{} - - [{}] "{} {} {:?}" {status} {} {} {} {duration_us}"#,
+                    remote.as_deref().unwrap_or("-"),
+                    clf_time(started.time),
+                    started.method,
+                    started.uri,
+                    started.version,
+                    if size == 0 {
+                        "-".to_owned()
+                    } else {
+                        size.to_string()
+                    },
+                    quoted(&started.referer),
+                    quoted(&started.user_agent),
+                )
+            }
+            LogFormat::Json => {
+                let mut line = String::from("{");
+                if let Some(remote) = &remote {
+                    let _ = write!(line, r#""remote":{},"#, json_string(remote));
+                }
+                let _ = write!(
+                    line,
+                    r#""method":{},"uri":{},"status":{status},"size":{size},"duration_us":{duration_us}}}"#,
+                    json_string(started.method.as_str()),
+                    json_string(&started.uri.to_string()),
+                );
+                line
+            }
+        }
+    }
+}
+
+impl Middleware for Logger {
+    fn before(&self, req: &mut Request<Bytes>) -> Option<Response<Bytes>> {
+        if self.excluded.iter().any(|path| path == req.uri().path()) {
+            return None;
+        }
+
+        let header = |name: HeaderName, headers: &HeaderMap| {
+            let value = headers.get(name)?.to_str().ok()?;
+            Some(value.to_owned())
+        };
+        let started = Started {
+            at: Instant::now(),
+            time: SystemTime::now(),
+            remote: req.extensions().get::<SocketAddr>().copied(),
+            method: req.method().clone(),
+            uri: req.uri().clone(),
+            version: req.version(),
+            referer: header(REFERER, req.headers()),
+            user_agent: header(USER_AGENT, req.headers()),
+        };
+        req.extensions_mut().insert(started);
+        None
+    }
+
+    fn after(&self, res: &mut Response<Bytes>) {
+        // excluded requests were not started
+        let Some(started) = res.extensions().get::<Started>() else {
+            return;
+        };
+        let line = self.line(started, res);
+
+        let subscribed =
+            tracing::dispatcher::get_default(|dispatch| !dispatch.is::<NoSubscriber>());
+        if subscribed {
+            tracing::info!(status = res.status().as_u16(), "{line}");
+        } else {
+            eprintln!("{line}");
+        }
+    }
+}
+
+/// Formats `time` like `10/Oct/2000:13:55:36 +0000`.
+fn clf_time(time: SystemTime) -> String {
+    const MONTHS: [&str; 12] = [
+        "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+    ];
+
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let (days, secs) = (secs / 86400, secs % 86400);
+
+    // civil date from days since the epoch, see
+    // http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let doe = z % 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + u64::from(month <= 2);
+
+    format!(
+        "{day:02}/{}/{year}:{:02}:{:02}:{:02} +0000",
+        MONTHS[month as usize - 1],
+        secs / 3600,
+        secs % 3600 / 60,
+        secs % 60,
+    )
+}
+
+/// Quotes `value` as a JSON string.
+fn json_string(value: &str) -> String {
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => quoted.push_str(r#"\""#),
+            '\\' => quoted.push_str(r"\\"),
+            c if c.is_control() => {
+                let _ = write!(quoted, "\\u{:04x}", c as u32);
+            }
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    fn started() -> Started {
+        Started {
+            at: Instant::now(),
+            time: UNIX_EPOCH + Duration::from_secs(971_186_136),
+            remote: Some(SocketAddr::from(([127, 0, 0, 1], 50000))),
+            method: Method::GET,
+            uri: Uri::from_static("/users?name=%22alice%22"),
+            version: Version::HTTP_11,
+            referer: None,
+            user_agent: Some("curl/8.5.0".to_owned()),
+        }
+    }
+
+    #[test]
+    fn combined_lines_are_apache_style() {
+        let res = Response::new(Bytes::from_static(b"alice"));
+        let line = Logger::new().line(&started(), &res);
+
+        let expected = r#"127.0.0.1 - - [10/Oct/2000:13:55:36 +0000] "GET /users?name=%22alice%22 HTTP/1.1" 200 5 "-" "curl/8.5.0" "#;
+        assert!(line.starts_with(expected), "{line}");
+    }
+
+    #[test]
+    fn json_lines_are_objects() {
+        let mut res = Response::new(Bytes::new());
+        *res.status_mut() = http::StatusCode::NO_CONTENT;
+        let line = Logger::new().format(LogFormat::Json).line(&started(), &res);
+
+        let expected = r#"{"remote":"127.0.0.1","method":"GET","uri":"/users?name=%22alice%22","status":204,"size":0,"duration_us":"#;
+        assert!(line.starts_with(expected), "{line}");
+        assert!(line.ends_with('}'), "{line}");
+    }
+
+    #[test]
+    fn excluded_paths_are_not_logged() {
+        let logger = Logger::new().exclude_paths(&["/health"]);
+
+        let mut req = Request::get("/health").body(Bytes::new()).unwrap();
+        logger.before(&mut req);
+        assert!(req.extensions().get::<Started>().is_none());
+
+        let mut req = Request::get("/users").body(Bytes::new()).unwrap();
+        logger.before(&mut req);
+        assert!(req.extensions().get::<Started>().is_some());
+    }
+
+    #[test]
+    fn strings_are_escaped_for_json() {
+        assert_eq!(json_string("a \"b\" \\ \n"), r#""a \"b\" \\ \u000a""#);
+    }
+}
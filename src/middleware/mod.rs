@@ -0,0 +1,81 @@
+mod auth;
+mod cache;
+#[cfg(feature = "encoding_rs")]
+mod charset;
+#[cfg(feature = "compression")]
+mod compress;
+mod cors;
+mod debug;
+mod logger;
+mod rate_limit;
+mod recover;
+mod request_id;
+mod session;
+mod timeout;
+
+pub use auth::Authenticate;
+pub use cache::LruCache;
+pub(crate) use cache::{CacheKey, CacheLookup};
+#[cfg(feature = "encoding_rs")]
+pub use charset::{CharsetDecode, UnsupportedCharset};
+#[cfg(feature = "compression")]
+pub use compress::Compress;
+pub use cors::Cors;
+pub use debug::DebugDump;
+pub use logger::{LogFormat, Logger};
+pub use rate_limit::RateLimit;
+pub(crate) use recover::recover;
+pub use recover::RecoverPanic;
+pub use request_id::{MissingRequestId, RequestId, RequestIds};
+pub use session::{Session, Sessions};
+pub use timeout::Timeout;
+
+use http::{Request, Response};
+use hyper::body::Bytes;
+
+/// Runs code around the handler of a route.
+///
+/// Middlewares are registered for all routes with [`App::before`] and
+/// [`App::after`], for the routes of a group with [`Group::before`] and
+/// [`Group::after`], or for a single route with [`Route::before`] and
+/// [`Route::after`]. Both hooks do nothing by default.
+///
+/// # Order
+///
+/// Middlewares nest like a stack around the handler:
+///
+/// - `before` middlewares run in the order they were added, those of the
+///   app first, then those of the group, then those of the route.
+/// - `after` middlewares run in reverse order, those of the route first,
+///   then those of the group, then those of the app.
+///
+/// So the middleware added first sees the request first and the response
+/// last. [`MiddlewareOrderRecorder`](crate::testing::MiddlewareOrderRecorder)
+/// helps to test this.
+///
+/// [`App::before`]: crate::App::before
+/// [`App::after`]: crate::App::after
+/// [`Group::before`]: crate::Group#structfield.before
+/// [`Group::after`]: crate::Group#structfield.after
+/// [`Route::before`]: crate::Route::before
+/// [`Route::after`]: crate::Route::after
+pub trait Middleware {
+    /// Called before the handler, with the request it is about to get.
+    ///
+    /// Returning a response short-circuits the request: the remaining
+    /// `before` middlewares and the handler are skipped, and the response
+    /// goes through the `after` middlewares instead, e.g. to reject
+    /// unauthorized requests.
+    fn before(&self, _req: &mut Request<Bytes>) -> Option<Response<Bytes>> {
+        None
+    }
+
+    /// Called after the handler, with the response it returned.
+    ///
+    /// The response carries the extensions of the request as the `before`
+    /// middlewares left them, so a `before` hook can pass data on to the
+    /// `after` hook.
+    fn after(&self, _res: &mut Response<Bytes>) {}
+}
+
+pub type BoxedMiddleware = Box<dyn Middleware + Send + Sync + 'static>;
@@ -0,0 +1,227 @@
+use std::{
+    collections::HashMap,
+    net::{IpAddr, SocketAddr},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use http::{header::RETRY_AFTER, Request, Response, StatusCode};
+use hyper::body::Bytes;
+
+use super::Middleware;
+
+const X_FORWARDED_FOR: &str = "x-forwarded-for";
+
+#[derive(Debug, Clone, Copy)]
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+#[derive(Debug, Default)]
+struct Buckets {
+    by_ip: HashMap<IpAddr, Bucket>,
+    /// When full buckets were last removed.
+    swept: Option<Instant>,
+}
+
+/// Limits how many requests each client can make, answering excess ones
+/// with `429 Too Many Requests`.
+///
+/// Every client IP has a bucket of `requests` tokens, which refills
+/// completely within `per`. A request takes a token, a request that finds
+/// the bucket empty is rejected. Register the limit with
+/// [`App::before`](crate::App::before). Cloning it is cheap, all clones share
+/// the same buckets.
+///
+/// Requests whose client IP is unknown are not limited. Buckets that
+/// refilled completely are removed, so clients that stopped making requests
+/// do not take up memory.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use genuine::{middleware::RateLimit, App};
+///
+/// let mut app = App::new();
+/// app.before(RateLimit::new(10, Duration::from_secs(1)));
+/// ```
+#[derive(Debug, Clone)]
+pub struct RateLimit {
+    requests: u32,
+    per: Duration,
+    behind_proxy: bool,
+    buckets: Arc<Mutex<Buckets>>,
+}
+
+impl RateLimit {
+    /// Allow `requests` requests per client within `per`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `requests` is `0`, since the buckets would never refill.
+    pub fn new(requests: u32, per: Duration) -> Self {
+        assert!(requests > 0, "rate limit must allow at least one request");
+
+        Self {
+            requests,
+            per,
+            behind_proxy: false,
+            buckets: Arc::default(),
+        }
+    }
+
+    /// Take the client IP from the `X-Forwarded-For` header, instead of the
+    /// address of the connection.
+    ///
+    /// Only enable this behind a proxy that sets the header, otherwise
+    /// clients can pick any IP. The last address in the header is used,
+    /// which is the one the proxy added.
+    pub fn behind_proxy(mut self, enabled: bool) -> Self {
+        self.behind_proxy = enabled;
+        self
+    }
+
+    fn client_ip(&self, req: &Request<Bytes>) -> Option<IpAddr> {
+        if self.behind_proxy {
+            return req
+                .headers()
+                .get_all(X_FORWARDED_FOR)
+                .iter()
+                .filter_map(|value| value.to_str().ok())
+                .flat_map(|value| value.split(','))
+                .last()?
+                .trim()
+                .parse()
+                .ok();
+        }
+
+        req.extensions().get::<SocketAddr>().map(SocketAddr::ip)
+    }
+
+    /// Takes a token from the bucket of `ip`, or returns how long to wait
+    /// for the next one.
+    fn take(&self, ip: IpAddr) -> Result<(), Duration> {
+        let capacity = f64::from(self.requests);
+        let refill = capacity / self.per.as_secs_f64();
+        let now = Instant::now();
+        let refilled = |bucket: &Bucket| {
+            let elapsed = now.duration_since(bucket.updated).as_secs_f64();
+            (bucket.tokens + elapsed * refill).min(capacity)
+        };
+
+        let mut buckets = self.buckets.lock().unwrap_or_else(|err| err.into_inner());
+
+        // a full bucket is the same as none, sweep them at most once per
+        // refill period to keep this cheap
+        if buckets.swept.map_or(true, |swept| now - swept >= self.per) {
+            buckets
+                .by_ip
+                .retain(|_, bucket| refilled(bucket) < capacity);
+            buckets.swept = Some(now);
+        }
+
+        let bucket = buckets.by_ip.entry(ip).or_insert(Bucket {
+            tokens: capacity,
+            updated: now,
+        });
+        bucket.tokens = refilled(bucket);
+        bucket.updated = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - bucket.tokens) / refill))
+        }
+    }
+}
+
+impl Middleware for RateLimit {
+    fn before(&self, req: &mut Request<Bytes>) -> Option<Response<Bytes>> {
+        let wait = self.take(self.client_ip(req)?).err()?;
+
+        // `Retry-After` is in whole seconds, round up to not retry too early
+        let seconds = wait.as_secs() + u64::from(wait.subsec_nanos() > 0);
+        let res = Response::builder()
+            .status(StatusCode::TOO_MANY_REQUESTS)
+            .header(RETRY_AFTER, seconds.max(1))
+            .body(Bytes::from_static(b"Too Many Requests"))
+            .unwrap();
+        Some(res)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(ip: [u8; 4]) -> Request<Bytes> {
+        let mut req = Request::new(Bytes::new());
+        req.extensions_mut().insert(SocketAddr::from((ip, 50000)));
+        req
+    }
+
+    #[test]
+    fn requests_over_the_limit_are_rejected() {
+        let limit = RateLimit::new(10, Duration::from_secs(60));
+
+        let rejected: Vec<_> = (0..11)
+            .filter_map(|_| limit.before(&mut request([10, 0, 0, 1])))
+            .collect();
+
+        assert_eq!(rejected.len(), 1);
+        assert_eq!(rejected[0].status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(rejected[0].headers()[RETRY_AFTER], "6");
+
+        assert!(limit.before(&mut request([10, 0, 0, 2])).is_none());
+    }
+
+    #[test]
+    fn forwarded_address_is_used_behind_proxies() {
+        let limit = RateLimit::new(1, Duration::from_secs(60)).behind_proxy(true);
+        let forwarded = |client: &'static str| {
+            let mut req = request([10, 0, 0, 1]);
+            req.headers_mut().insert(
+                X_FORWARDED_FOR,
+                format!("6.6.6.6, {client}").parse().unwrap(),
+            );
+            req
+        };
+
+        assert!(limit.before(&mut forwarded("192.0.2.1")).is_none());
+        assert!(limit.before(&mut forwarded("192.0.2.2")).is_none());
+        assert!(limit.before(&mut forwarded("192.0.2.1")).is_some());
+    }
+
+    #[test]
+    fn refilled_buckets_are_removed() {
+        let limit = RateLimit::new(2, Duration::from_millis(20));
+        limit.before(&mut request([10, 0, 0, 1]));
+        limit.before(&mut request([10, 0, 0, 2]));
+
+        std::thread::sleep(Duration::from_millis(30));
+        limit.before(&mut request([10, 0, 0, 3]));
+
+        let buckets = limit.buckets.lock().unwrap();
+        let ips: Vec<_> = buckets.by_ip.keys().collect();
+        assert_eq!(ips, [&IpAddr::from([10, 0, 0, 3])]);
+    }
+
+    #[test]
+    fn unknown_clients_are_not_limited() {
+        let limit = RateLimit::new(1, Duration::from_secs(60));
+
+        for _ in 0..3 {
+            assert!(limit.before(&mut Request::new(Bytes::new())).is_none());
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "at least one request")]
+    fn zero_requests_are_rejected() {
+        RateLimit::new(0, Duration::from_secs(1));
+    }
+}
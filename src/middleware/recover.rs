@@ -0,0 +1,153 @@
+use std::{
+    any::Any,
+    future::Future,
+    panic::{catch_unwind, AssertUnwindSafe},
+    pin::pin,
+};
+
+use http::{Request, Response, StatusCode};
+use hyper::body::Bytes;
+
+use super::Middleware;
+
+/// Answers `500 Internal Server Error` if the handler panics, instead of
+/// closing the connection.
+///
+/// Register it with [`App::before`](crate::App::before) for all routes, or
+/// with [`Group::before`](crate::Group#structfield.before) for the routes of
+/// a group. The panic is logged at the `ERROR` level.
+///
+/// Recovering from panics can hide bugs, so it can be turned off, e.g. for
+/// debug builds.
+///
+/// # Examples
+///
+/// ```
+/// use genuine::{middleware::RecoverPanic, App};
+///
+/// let mut app = App::new();
+/// app.before(RecoverPanic::new().enabled(!cfg!(debug_assertions)));
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct RecoverPanic {
+    enabled: bool,
+}
+
+impl RecoverPanic {
+    pub fn new() -> Self {
+        Self { enabled: true }
+    }
+
+    pub fn enabled(mut self, enabled: bool) -> Self {
+        self.enabled = enabled;
+        self
+    }
+}
+
+impl Default for RecoverPanic {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Middleware for RecoverPanic {
+    fn before(&self, req: &mut Request<Bytes>) -> Option<Response<Bytes>> {
+        // the router catches panics of the handler if this is set
+        if self.enabled {
+            req.extensions_mut().insert(*self);
+        }
+        None
+    }
+}
+
+/// Awaits `handled`, and answers `500 Internal Server Error` if it panics.
+pub(crate) async fn recover<F>(handled: F) -> F::Output
+where
+    F: Future<Output = Option<Response<Bytes>>>,
+{
+    let mut handled = pin!(handled);
+    let caught = std::future::poll_fn(|cx| {
+        match catch_unwind(AssertUnwindSafe(|| handled.as_mut().poll(cx))) {
+            Ok(poll) => poll.map(Ok),
+            Err(panic) => std::task::Poll::Ready(Err(panic)),
+        }
+    })
+    .await;
+
+    caught.unwrap_or_else(|panic| {
+        tracing::error!(panic = panic_message(&*panic), "handler panicked");
+        let res = Response::builder()
+            .status(StatusCode::INTERNAL_SERVER_ERROR)
+            .body(Bytes::from_static(b"Internal Server Error"))
+            .unwrap();
+        Some(res)
+    })
+}
+
+fn panic_message(panic: &(dyn Any + Send)) -> &str {
+    match panic.downcast_ref::<&str>() {
+        Some(message) => message,
+        None => panic
+            .downcast_ref::<String>()
+            .map_or("unknown panic", String::as_str),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use http::Method;
+
+    use super::*;
+    use crate::{router::Router, testing::TestClient};
+
+    fn boom() -> &'static str {
+        panic!("boom")
+    }
+
+    async fn boom_async(_: crate::Request) -> &'static str {
+        tokio::task::yield_now().await;
+        panic!("boom")
+    }
+
+    fn client(recover: RecoverPanic) -> TestClient {
+        let mut router = Router::new();
+        router.before.push(Box::new(recover));
+        let group = &mut router.groups[0];
+        group.add(Method::GET, "/sync", boom as fn() -> _).unwrap();
+        group.add_async(Method::GET, "/async", boom_async).unwrap();
+        group
+            .add(Method::GET, "/slow", boom as fn() -> _)
+            .unwrap()
+            .timeout(Duration::from_secs(1));
+        TestClient::from_router(router)
+    }
+
+    #[tokio::test]
+    async fn panics_are_internal_server_errors() {
+        let client = client(RecoverPanic::new());
+
+        for uri in ["/sync", "/async", "/slow"] {
+            let res = client.get(uri).send().await;
+            assert_eq!(res.status(), StatusCode::INTERNAL_SERVER_ERROR, "{uri}");
+            assert_eq!(res.text().await, "Internal Server Error");
+        }
+    }
+
+    #[tokio::test]
+    #[should_panic(expected = "boom")]
+    async fn disabled_recovery_lets_panics_through() {
+        client(RecoverPanic::new().enabled(false))
+            .get("/sync")
+            .send()
+            .await;
+    }
+
+    #[test]
+    fn panic_messages_are_extracted() {
+        assert_eq!(panic_message(&"boom"), "boom");
+        assert_eq!(panic_message(&String::from("boom")), "boom");
+        assert_eq!(panic_message(&42), "unknown panic");
+    }
+}
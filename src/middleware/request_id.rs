@@ -0,0 +1,172 @@
+use http::{HeaderName, HeaderValue, Request, Response};
+use hyper::body::Bytes;
+use uuid::Uuid;
+
+use super::Middleware;
+use crate::{FromRequest, Rejection};
+
+const X_REQUEST_ID: HeaderName = HeaderName::from_static("x-request-id");
+
+/// Gives every request a [`RequestId`], and sends it back in the
+/// `X-Request-Id` response header.
+///
+/// Register it with both [`App::before`](crate::App::before), where it
+/// assigns the ID, and [`App::after`](crate::App::after), where it adds the
+/// header.
+///
+/// # Examples
+///
+/// ```
+/// use genuine::{middleware::RequestIds, App};
+///
+/// let ids = RequestIds::new().trust_incoming_id(true);
+///
+/// let mut app = App::new();
+/// app.before(ids).after(ids);
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RequestIds {
+    trust_incoming_id: bool,
+}
+
+impl RequestIds {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reuse the ID of the `X-Request-Id` request header, if it is a UUID,
+    /// instead of generating a new one.
+    ///
+    /// Only enable this if the header is set by a trusted proxy, otherwise
+    /// clients can pick the IDs of their requests.
+    pub fn trust_incoming_id(mut self, enabled: bool) -> Self {
+        self.trust_incoming_id = enabled;
+        self
+    }
+}
+
+impl Middleware for RequestIds {
+    fn before(&self, req: &mut Request<Bytes>) -> Option<Response<Bytes>> {
+        let incoming = req
+            .headers()
+            .get(X_REQUEST_ID)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| Uuid::parse_str(value).ok())
+            .filter(|_| self.trust_incoming_id);
+
+        let id = RequestId(incoming.unwrap_or_else(Uuid::new_v4));
+        req.extensions_mut().insert(id);
+        None
+    }
+
+    fn after(&self, res: &mut Response<Bytes>) {
+        let Some(RequestId(id)) = res.extensions().get::<RequestId>().copied() else {
+            return;
+        };
+
+        // hyphenated UUIDs are valid header values
+        let value = HeaderValue::from_str(&id.to_string()).unwrap();
+        res.headers_mut().entry(X_REQUEST_ID).or_insert(value);
+    }
+}
+
+/// The ID that [`RequestIds`] assigned to a request.
+///
+/// # Examples
+///
+/// ```
+/// use genuine::middleware::RequestId;
+///
+/// fn order(RequestId(id): RequestId) -> String {
+///     format!("placed order for request {id}")
+/// }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RequestId(pub Uuid);
+
+/// The request has no ID, because the [`RequestIds`] middleware does not run
+/// for its route.
+#[derive(Debug, thiserror::Error, PartialEq, Eq)]
+#[error("request has no ID")]
+pub struct MissingRequestId;
+
+impl Rejection for MissingRequestId {}
+
+impl FromRequest for RequestId {
+    type Error = MissingRequestId;
+
+    fn from_request(req: &crate::Request) -> Result<Self, Self::Error> {
+        req.extensions()
+            .get::<RequestId>()
+            .copied()
+            .ok_or(MissingRequestId)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use http::Method;
+    use http_body_util::{BodyExt, Full};
+
+    use super::*;
+    use crate::{router::Router, Handler};
+
+    /// Remembers the ID it got.
+    #[derive(Clone, Default)]
+    struct Seen(Arc<Mutex<Option<RequestId>>>);
+
+    impl Handler for Seen {
+        fn handle_request(&self, req: crate::Request) -> Response<Bytes> {
+            *self.0.lock().unwrap() = RequestId::from_request(&req).ok();
+            Response::new(Bytes::new())
+        }
+    }
+
+    fn router(ids: RequestIds, seen: Seen) -> Router {
+        let mut router = Router::new();
+        router.before.push(Box::new(ids));
+        router.after.push(Box::new(ids));
+        router.groups[0].add(Method::GET, "/", seen).unwrap();
+        router
+    }
+
+    async fn get(router: &Router, incoming: &str) -> Response<()> {
+        let req = Request::get("/")
+            .header(X_REQUEST_ID, incoming)
+            .body(Full::<Bytes>::default())
+            .unwrap();
+        let res = router.respond(req).await.unwrap();
+        let (parts, body) = res.into_parts();
+        body.collect().await.unwrap();
+        Response::from_parts(parts, ())
+    }
+
+    const INCOMING: &str = "67e55044-10b1-426f-9247-bb680e5fe0c8";
+
+    #[tokio::test]
+    async fn response_header_matches_the_extracted_id() {
+        let seen = Seen::default();
+        let router = router(RequestIds::new(), seen.clone());
+
+        let res = get(&router, INCOMING).await;
+        let RequestId(id) = seen.0.lock().unwrap().expect("handler got no ID");
+        assert_eq!(res.headers()[X_REQUEST_ID], id.to_string());
+        assert_ne!(id.to_string(), INCOMING);
+        assert_eq!(id.get_version_num(), 4);
+    }
+
+    #[tokio::test]
+    async fn trusted_incoming_ids_are_reused() {
+        let seen = Seen::default();
+        let router = router(RequestIds::new().trust_incoming_id(true), seen.clone());
+
+        let res = get(&router, INCOMING).await;
+        assert_eq!(res.headers()[X_REQUEST_ID], INCOMING);
+        assert_eq!(seen.0.lock().unwrap().unwrap().0.to_string(), INCOMING);
+
+        let res = get(&router, "not a uuid").await;
+        assert_ne!(res.headers()[X_REQUEST_ID], "not a uuid");
+    }
+}
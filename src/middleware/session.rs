@@ -0,0 +1,303 @@
+use std::{
+    collections::HashMap,
+    fmt::Write,
+    sync::{Arc, Mutex},
+};
+
+use http::{
+    header::{COOKIE, SET_COOKIE},
+    HeaderValue, Request, Response,
+};
+use hyper::body::Bytes;
+
+/// The name of the cookie that holds the session ID.
+const COOKIE_NAME: &str = "session";
+
+type Data = HashMap<String, String>;
+
+/// An in-memory session store.
+///
+/// Sessions are identified by a random ID in the `session` cookie. Cloning
+/// the store is cheap, all clones share the same sessions.
+///
+/// Handlers load the session of a request with [`Sessions::load`] and tell
+/// the client about a new session ID with [`Session::set_cookie`].
+///
+/// # Examples
+///
+/// ```
+/// use genuine::middleware::Sessions;
+/// use http::{Request, Response};
+/// use hyper::body::Bytes;
+///
+/// let sessions = Sessions::new();
+///
+/// let req = Request::new(Bytes::new());
+/// let mut session = sessions.load(&req);
+/// session.insert("user", "ferris");
+///
+/// let mut res = Response::new(Bytes::new());
+/// session.set_cookie(&mut res);
+/// assert!(res.headers().contains_key("set-cookie"));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Sessions {
+    sessions: Arc<Mutex<HashMap<String, Data>>>,
+    secure: bool,
+}
+
+impl Sessions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Mark the session cookie `Secure`, so that clients only send it over
+    /// HTTPS. Enable this whenever the app is served over HTTPS.
+    pub fn secure(mut self, enabled: bool) -> Self {
+        self.secure = enabled;
+        self
+    }
+
+    /// Returns the `Set-Cookie` value for the session `id`, or one that
+    /// deletes the cookie if the session `expired`.
+    fn cookie(&self, id: &str, expired: bool) -> HeaderValue {
+        let mut cookie = format!("{COOKIE_NAME}={id}; Path=/");
+        if expired {
+            cookie.push_str("; Max-Age=0");
+        }
+        cookie.push_str("; HttpOnly; SameSite=Lax");
+        if self.secure {
+            cookie.push_str("; Secure");
+        }
+        // the ID is hex, so the cookie is a valid header value
+        HeaderValue::from_str(&cookie).unwrap()
+    }
+
+    /// Load the session named by the cookie of `req`.
+    ///
+    /// Returns a new, empty session if the request has no session cookie or
+    /// its ID is unknown, e.g. because the session was invalidated.
+    pub fn load(&self, req: &Request<Bytes>) -> Session {
+        let id = session_id(req).filter(|id| self.lock().contains_key(*id));
+
+        Session {
+            store: self.clone(),
+            id: id.map(str::to_owned),
+            cookie: None,
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, Data>> {
+        // a panic while holding the lock cannot leave a map half-updated
+        self.sessions.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+/// The session of a single request, see [`Sessions::load`].
+///
+/// Changes are written to the store immediately. If the session ID changed,
+/// [`Session::set_cookie`] must be called on the response to tell the client.
+#[derive(Debug)]
+pub struct Session {
+    store: Sessions,
+    id: Option<String>,
+    cookie: Option<HeaderValue>,
+}
+
+impl Session {
+    /// The ID of this session, or [`None`] if nothing was stored yet.
+    pub fn id(&self) -> Option<&str> {
+        self.id.as_deref()
+    }
+
+    pub fn get(&self, key: &str) -> Option<String> {
+        let id = self.id.as_ref()?;
+        self.store.lock().get(id)?.get(key).cloned()
+    }
+
+    /// Store `value` under `key`, starting the session if necessary.
+    pub fn insert(&mut self, key: impl Into<String>, value: impl Into<String>) {
+        let id = match &self.id {
+            Some(id) => id.clone(),
+            None => self.start(Data::new()),
+        };
+
+        self.store
+            .lock()
+            .entry(id)
+            .or_default()
+            .insert(key.into(), value.into());
+    }
+
+    pub fn remove(&mut self, key: &str) -> Option<String> {
+        let id = self.id.as_ref()?;
+        self.store.lock().get_mut(id)?.remove(key)
+    }
+
+    /// Delete this session and all its data, and expire the cookie.
+    ///
+    /// This is what logging out should do.
+    pub fn invalidate(&mut self) {
+        if let Some(id) = self.id.take() {
+            self.store.lock().remove(&id);
+        }
+
+        self.cookie = Some(self.store.cookie("", true));
+    }
+
+    /// Move the data of this session to a new ID and forget the old one.
+    ///
+    /// Rotate the session whenever its privileges change, most importantly
+    /// on login. Otherwise an attacker who planted a session ID in the
+    /// victim's browser can use it once the victim is logged in, which is
+    /// known as session fixation.
+    pub fn rotate(&mut self) {
+        let data = match self.id.take() {
+            Some(id) => self.store.lock().remove(&id).unwrap_or_default(),
+            None => Data::new(),
+        };
+
+        self.start(data);
+    }
+
+    /// Add a `Set-Cookie` header to `res` if the session ID changed.
+    pub fn set_cookie(&self, res: &mut Response<Bytes>) {
+        if let Some(cookie) = &self.cookie {
+            res.headers_mut().append(SET_COOKIE, cookie.clone());
+        }
+    }
+
+    fn start(&mut self, data: Data) -> String {
+        let id = new_id();
+        self.store.lock().insert(id.clone(), data);
+
+        self.cookie = Some(self.store.cookie(&id, false));
+        self.id = Some(id.clone());
+        id
+    }
+}
+
+/// Returns the session ID from the `Cookie` headers of `req`.
+fn session_id(req: &Request<Bytes>) -> Option<&str> {
+    req.headers()
+        .get_all(COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|cookies| cookies.split(';'))
+        .filter_map(|cookie| cookie.trim().split_once('='))
+        .find_map(|(name, value)| (name == COOKIE_NAME).then_some(value))
+}
+
+/// Generates a session ID from 128 random bits.
+fn new_id() -> String {
+    let mut bytes = [0; 16];
+    getrandom::fill(&mut bytes).expect("failed to generate a session ID");
+
+    bytes
+        .iter()
+        .fold(String::with_capacity(32), |mut id, byte| {
+            let _ = write!(id, "{byte:02x}");
+            id
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(id: &str) -> Request<Bytes> {
+        Request::get("/")
+            .header(COOKIE, format!("theme=dark; {COOKIE_NAME}={id}"))
+            .body(Bytes::new())
+            .unwrap()
+    }
+
+    fn cookie(session: &Session) -> String {
+        let mut res = Response::new(Bytes::new());
+        session.set_cookie(&mut res);
+        res.headers()[SET_COOKIE].to_str().unwrap().to_owned()
+    }
+
+    fn login(sessions: &Sessions) -> String {
+        let mut session = sessions.load(&Request::new(Bytes::new()));
+        session.insert("cart", "3 apples");
+        session.id().unwrap().to_owned()
+    }
+
+    #[test]
+    fn unknown_ids_start_a_new_session() {
+        let sessions = Sessions::new();
+        let session = sessions.load(&request("forged"));
+        assert_eq!(session.id(), None);
+    }
+
+    #[test]
+    fn data_is_kept_between_requests() {
+        let sessions = Sessions::new();
+        let id = login(&sessions);
+
+        let session = sessions.load(&request(&id));
+        assert_eq!(session.id(), Some(id.as_str()));
+        assert_eq!(session.get("cart").as_deref(), Some("3 apples"));
+    }
+
+    #[test]
+    fn rotation_moves_data_to_a_new_id() {
+        let sessions = Sessions::new();
+        let old = login(&sessions);
+
+        let mut session = sessions.load(&request(&old));
+        session.insert("user", "ferris");
+        session.rotate();
+        let new = session.id().unwrap().to_owned();
+
+        assert_ne!(old, new);
+        assert_eq!(
+            cookie(&session),
+            format!("session={new}; Path=/; HttpOnly; SameSite=Lax")
+        );
+
+        assert_eq!(sessions.load(&request(&old)).id(), None);
+
+        let session = sessions.load(&request(&new));
+        assert_eq!(session.get("user").as_deref(), Some("ferris"));
+        assert_eq!(session.get("cart").as_deref(), Some("3 apples"));
+    }
+
+    #[test]
+    fn invalidation_deletes_the_session_and_expires_the_cookie() {
+        let sessions = Sessions::new();
+        let id = login(&sessions);
+
+        let mut session = sessions.load(&request(&id));
+        session.invalidate();
+
+        assert_eq!(session.id(), None);
+        assert!(cookie(&session).contains("Max-Age=0"));
+        assert_eq!(sessions.load(&request(&id)).id(), None);
+    }
+
+    #[test]
+    fn secure_cookies_are_marked() {
+        let sessions = Sessions::new().secure(true);
+        let mut session = sessions.load(&Request::new(Bytes::new()));
+        session.insert("user", "ferris");
+        assert!(cookie(&session).ends_with("; SameSite=Lax; Secure"));
+
+        session.invalidate();
+        assert_eq!(
+            cookie(&session),
+            "session=; Path=/; Max-Age=0; HttpOnly; SameSite=Lax; Secure"
+        );
+    }
+
+    #[test]
+    fn unchanged_sessions_set_no_cookie() {
+        let sessions = Sessions::new();
+        let id = login(&sessions);
+
+        let mut res = Response::new(Bytes::new());
+        sessions.load(&request(&id)).set_cookie(&mut res);
+        assert!(!res.headers().contains_key(SET_COOKIE));
+    }
+}
@@ -0,0 +1,125 @@
+use std::time::Duration;
+
+use http::{Request, Response};
+use hyper::body::Bytes;
+
+use super::Middleware;
+
+/// Answers `503 Service Unavailable` if the handler does not respond within
+/// the given duration.
+///
+/// Register it with [`App::before`](crate::App::before) for all routes, or
+/// with [`Group::before`](crate::Group#structfield.before) for the routes of
+/// a group. If several timeouts apply to a request, including one set with
+/// [`Route::timeout`](crate::Route::timeout), the shortest one wins.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use genuine::{middleware::Timeout, App};
+///
+/// let mut app = App::new();
+/// app.before(Timeout(Duration::from_secs(10)));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Timeout(pub Duration);
+
+impl Middleware for Timeout {
+    fn before(&self, req: &mut Request<Bytes>) -> Option<Response<Bytes>> {
+        // the router enforces the shortest timeout of the request
+        let shortest = match req.extensions().get::<Timeout>() {
+            Some(Timeout(other)) => self.0.min(*other),
+            None => self.0,
+        };
+        req.extensions_mut().insert(Timeout(shortest));
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use http::{header::CONTENT_TYPE, Method};
+
+    use super::*;
+    use crate::{
+        router::Router,
+        testing::{MiddlewareOrderRecorder, TestClient},
+        Group,
+    };
+
+    fn slow() -> &'static str {
+        std::thread::sleep(Duration::from_millis(200));
+        "slow"
+    }
+
+    fn fast() -> &'static str {
+        "fast"
+    }
+
+    #[tokio::test]
+    async fn slow_handlers_time_out() {
+        let mut router = Router::new();
+        router
+            .before
+            .push(Box::new(Timeout(Duration::from_millis(20))));
+        router.groups[0]
+            .add(Method::GET, "/slow", slow as fn() -> _)
+            .unwrap();
+        router.groups[0]
+            .add(Method::GET, "/fast", fast as fn() -> _)
+            .unwrap();
+        let client = TestClient::from_router(router);
+
+        let res = client.get("/slow").send().await;
+        assert_eq!(res.status(), 503);
+        assert_eq!(res.headers()[CONTENT_TYPE], "application/json");
+        assert_eq!(res.text().await, r#"{"error":"request timeout"}"#);
+
+        assert_eq!(client.get("/fast").send().await.text().await, "fast");
+    }
+
+    #[tokio::test]
+    async fn timed_out_responses_pass_the_after_middlewares() {
+        let recorder = MiddlewareOrderRecorder::new("app");
+        let mut router = Router::new();
+        router
+            .before
+            .push(Box::new(Timeout(Duration::from_millis(20))));
+        router.after.push(Box::new(recorder.clone()));
+        router
+            .headers
+            .insert("x-served-by", http::HeaderValue::from_static("genuine"));
+        router.groups[0]
+            .add(Method::GET, "/slow", slow as fn() -> _)
+            .unwrap();
+        let client = TestClient::from_router(router);
+
+        let res = client.get("/slow").send().await;
+        assert_eq!(res.status(), 503);
+        assert_eq!(res.headers()["x-served-by"], "genuine");
+        assert_eq!(recorder.calls(), ["app"]);
+    }
+
+    #[tokio::test]
+    async fn shortest_timeout_wins() {
+        let mut api = Group::new("/api");
+        api.before
+            .push(Box::new(Timeout(Duration::from_millis(20))));
+        api.add(Method::GET, "/slow", slow as fn() -> _).unwrap();
+
+        let mut router = Router::new();
+        router
+            .before
+            .push(Box::new(Timeout(Duration::from_secs(10))));
+        router.groups[0]
+            .add(Method::GET, "/slow", slow as fn() -> _)
+            .unwrap();
+        router.groups.push(api);
+        let client = TestClient::from_router(router);
+
+        assert_eq!(client.get("/api/slow").send().await.status(), 503);
+        assert_eq!(client.get("/slow").send().await.status(), 200);
+    }
+}
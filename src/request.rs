@@ -1,3 +1,4 @@
+use http::{Extensions, Method};
 use hyper::body::Bytes;
 
 use crate::router::routes::Match;
@@ -18,6 +19,32 @@ impl Request {
     pub fn matches(&self) -> &[Match] {
         self.matches.as_slice()
     }
+
+    /// Returns the extensions of the request mutably.
+    ///
+    /// Middlewares insert values into the extensions before the handler
+    /// runs, and handlers read them with
+    /// [`Extension`](crate::extract::Extension).
+    pub fn extensions_mut(&mut self) -> &mut Extensions {
+        self.request.extensions_mut()
+    }
+
+    pub(crate) fn into_inner(self) -> HttpRequest {
+        self.request
+    }
+
+    /// Returns the host and port a `CONNECT` request asks to tunnel to.
+    ///
+    /// Returns [`None`] for all other methods, or if the request target has
+    /// no port.
+    pub fn tunnel_authority(&self) -> Option<(&str, u16)> {
+        if self.method() != Method::CONNECT {
+            return None;
+        }
+
+        let authority = self.uri().authority()?;
+        Some((authority.host(), authority.port_u16()?))
+    }
 }
 
 impl std::ops::Deref for Request {
@@ -27,3 +54,29 @@ impl std::ops::Deref for Request {
         &self.request
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(method: Method, uri: &'static str) -> Request {
+        let req = http::Request::builder()
+            .method(method)
+            .uri(uri)
+            .body(Bytes::new())
+            .unwrap();
+        Request::new(req, Vec::new())
+    }
+
+    #[test]
+    fn tunnel_authority_of_connect_request() {
+        let req = request(Method::CONNECT, "example.com:443");
+        assert_eq!(req.tunnel_authority(), Some(("example.com", 443)));
+    }
+
+    #[test]
+    fn tunnel_authority_requires_connect() {
+        let req = request(Method::GET, "http://example.com:443/");
+        assert_eq!(req.tunnel_authority(), None);
+    }
+}
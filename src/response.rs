@@ -0,0 +1,549 @@
+//! Helpers for building responses.
+
+use http::{
+    header::{CONTENT_LENGTH, CONTENT_TYPE, LOCATION, VARY},
+    response::Builder,
+    HeaderMap, HeaderName, HeaderValue, Response, StatusCode,
+};
+use hyper::body::Bytes;
+
+use crate::IntoResponse;
+
+/// Extension methods for [`http::response::Builder`].
+pub trait ResponseExt {
+    /// Add `header_name` to the `Vary` header, telling caches that the
+    /// response depends on that request header.
+    ///
+    /// Names already listed are not repeated. An invalid header name turns
+    /// the builder into an error.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use genuine::response::ResponseExt;
+    /// use http::{header::VARY, Response};
+    ///
+    /// let res = Response::builder()
+    ///     .vary("Accept")
+    ///     .vary("Accept-Language")
+    ///     .body(())
+    ///     .unwrap();
+    ///
+    /// assert_eq!(res.headers()[VARY], "accept, accept-language");
+    /// ```
+    fn vary(self, header_name: &str) -> Self;
+
+    /// Finish the response with a plain text body.
+    ///
+    /// If the builder has an error, e.g. an invalid header, the response is
+    /// `500 Internal Server Error` instead.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use genuine::response::ResponseExt;
+    /// use http::{header::CONTENT_TYPE, Response, StatusCode};
+    ///
+    /// let res = Response::builder().status(StatusCode::ACCEPTED).text("queued");
+    ///
+    /// assert_eq!(res.headers()[CONTENT_TYPE], "text/plain; charset=utf-8");
+    /// assert_eq!(res.body(), "queued");
+    /// ```
+    fn text(self, body: &str) -> Response<Bytes>;
+
+    /// Finish the response with `value` as a JSON body.
+    ///
+    /// If the builder has an error or `value` cannot be serialized, the
+    /// response is `500 Internal Server Error` instead.
+    #[cfg(feature = "serde_json")]
+    fn json<T: serde::Serialize>(self, value: T) -> Response<Bytes>;
+}
+
+impl ResponseExt for Builder {
+    fn vary(mut self, header_name: &str) -> Self {
+        let Ok(name) = HeaderName::from_bytes(header_name.as_bytes()) else {
+            // let the builder record the error
+            return self.header(header_name, "");
+        };
+
+        if let Some(headers) = self.headers_mut() {
+            append_vary(headers, &name);
+        }
+        self
+    }
+
+    fn text(self, body: &str) -> Response<Bytes> {
+        let builder = self.header(CONTENT_TYPE, "text/plain; charset=utf-8");
+        finish(builder, Bytes::copy_from_slice(body.as_bytes()))
+    }
+
+    #[cfg(feature = "serde_json")]
+    fn json<T: serde::Serialize>(self, value: T) -> Response<Bytes> {
+        match serde_json::to_vec(&value) {
+            Ok(body) => finish(self.header(CONTENT_TYPE, "application/json"), body.into()),
+            Err(err) => internal_server_error(err.to_string()),
+        }
+    }
+}
+
+/// Builds the response, or answers `500 Internal Server Error` if the builder
+/// has an error.
+fn finish(builder: Builder, body: Bytes) -> Response<Bytes> {
+    builder
+        .body(body)
+        .unwrap_or_else(|err| internal_server_error(err.to_string()))
+}
+
+/// Responds with `200 OK` and `body`.
+pub fn ok(body: impl IntoResponse) -> Response<Bytes> {
+    (StatusCode::OK, body).into_response()
+}
+
+/// Responds with `201 Created` and `body`, e.g. the created resource.
+pub fn created(body: impl IntoResponse) -> Response<Bytes> {
+    (StatusCode::CREATED, body).into_response()
+}
+
+/// Responds with `204 No Content`.
+pub fn no_content() -> Response<Bytes> {
+    let builder = Response::builder().status(StatusCode::NO_CONTENT);
+    finish(builder, Bytes::new())
+}
+
+/// Responds with `302 Found`, redirecting the client to `uri`.
+///
+/// If `uri` is not a valid header value, the response is `500 Internal
+/// Server Error` instead.
+///
+/// # Examples
+///
+/// ```
+/// use genuine::response;
+/// use http::{header::LOCATION, StatusCode};
+///
+/// let res = response::redirect("/login");
+///
+/// assert_eq!(res.status(), StatusCode::FOUND);
+/// assert_eq!(res.headers()[LOCATION], "/login");
+/// ```
+pub fn redirect(uri: &str) -> Response<Bytes> {
+    redirect_with(StatusCode::FOUND, uri)
+}
+
+/// Like [`redirect`], but with `301 Moved Permanently`, so that clients and
+/// caches remember the new location.
+pub fn permanent_redirect(uri: &str) -> Response<Bytes> {
+    redirect_with(StatusCode::MOVED_PERMANENTLY, uri)
+}
+
+fn redirect_with(status: StatusCode, uri: &str) -> Response<Bytes> {
+    let builder = Response::builder().status(status).header(LOCATION, uri);
+    finish(builder, Bytes::new())
+}
+
+/// Responds with `400 Bad Request` and `message`.
+pub fn bad_request(message: impl Into<String>) -> Response<Bytes> {
+    ErrorResponse::new(StatusCode::BAD_REQUEST, message).into_response()
+}
+
+/// Responds with `401 Unauthorized`.
+pub fn unauthorized() -> Response<Bytes> {
+    canonical(StatusCode::UNAUTHORIZED)
+}
+
+/// Responds with `403 Forbidden`.
+pub fn forbidden() -> Response<Bytes> {
+    canonical(StatusCode::FORBIDDEN)
+}
+
+/// Responds with `404 Not Found`.
+pub fn not_found_response() -> Response<Bytes> {
+    canonical(StatusCode::NOT_FOUND)
+}
+
+/// Responds with `500 Internal Server Error` and `message`.
+///
+/// The message is sent to the client, so it should not reveal internals.
+pub fn internal_server_error(message: impl Into<String>) -> Response<Bytes> {
+    ErrorResponse::new(StatusCode::INTERNAL_SERVER_ERROR, message).into_response()
+}
+
+/// Responds with `status` and its reason phrase, e.g. `Forbidden`.
+fn canonical(status: StatusCode) -> Response<Bytes> {
+    let reason = status.canonical_reason().unwrap_or_default();
+    ErrorResponse::new(status, reason).into_response()
+}
+
+/// Adds `name` to the `Vary` header in `headers`, merging all `Vary` values
+/// into a single one.
+pub(crate) fn append_vary(headers: &mut HeaderMap, name: &HeaderName) {
+    let mut names: Vec<String> = headers
+        .get_all(VARY)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(|name| name.trim().to_ascii_lowercase())
+        .filter(|name| !name.is_empty())
+        .collect();
+
+    // `*` already means that the response varies on everything
+    if names
+        .iter()
+        .any(|known| known == name.as_str() || known == "*")
+    {
+        return;
+    }
+
+    names.push(name.as_str().to_owned());
+    let value = HeaderValue::from_str(&names.join(", ")).unwrap();
+    headers.insert(VARY, value);
+}
+
+/// An error that responds with a status code and a plain text message.
+///
+/// Use it as the error type of handlers returning a [`Result`].
+///
+/// # Examples
+///
+/// ```
+/// use genuine::{response::ErrorResponse, IntoResponse};
+/// use http::StatusCode;
+///
+/// fn find_user(id: u32) -> Result<String, ErrorResponse> {
+///     match id {
+///         1 => Ok("ferris".to_owned()),
+///         _ => Err(ErrorResponse::new(StatusCode::NOT_FOUND, "user not found")),
+///     }
+/// }
+///
+/// let res = find_user(2).into_response();
+/// assert_eq!(res.status(), StatusCode::NOT_FOUND);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("{message}")]
+pub struct ErrorResponse {
+    status: StatusCode,
+    message: String,
+}
+
+impl ErrorResponse {
+    pub fn new(status: StatusCode, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            message: message.into(),
+        }
+    }
+
+    pub fn status(&self) -> StatusCode {
+        self.status
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl IntoResponse for ErrorResponse {
+    fn into_response(self) -> Response<Bytes> {
+        (self.status, self.message).into_response()
+    }
+}
+
+/// A step of a [`ResponseChain`].
+///
+/// Closures of type `Fn(Response<Bytes>) -> Response<Bytes>` are response
+/// transforms.
+pub trait ResponseTransform {
+    fn transform(&self, res: Response<Bytes>) -> Response<Bytes>;
+}
+
+impl<F> ResponseTransform for F
+where
+    F: Fn(Response<Bytes>) -> Response<Bytes>,
+{
+    fn transform(&self, res: Response<Bytes>) -> Response<Bytes> {
+        self(res)
+    }
+}
+
+/// Applies [`ResponseTransform`]s to a response, in order.
+///
+/// Start a chain with [`IntoResponse::pipeline`]. This composes transforms
+/// inside a handler, where adding a middleware to the route would be too
+/// coarse.
+///
+/// # Examples
+///
+/// ```
+/// use genuine::{
+///     response::{ContentLength, SetHeader},
+///     IntoResponse,
+/// };
+/// use http::{HeaderName, HeaderValue};
+///
+/// let res = "Hello world!"
+///     .pipeline()
+///     .then(SetHeader(
+///         HeaderName::from_static("x-greeting"),
+///         HeaderValue::from_static("hello"),
+///     ))
+///     .then(ContentLength)
+///     .into_response();
+///
+/// assert_eq!(res.headers()["x-greeting"], "hello");
+/// assert_eq!(res.headers()["content-length"], "12");
+/// ```
+#[derive(Debug)]
+pub struct ResponseChain {
+    response: Response<Bytes>,
+}
+
+impl ResponseChain {
+    pub fn new<R: IntoResponse>(response: R) -> Self {
+        Self {
+            response: response.into_response(),
+        }
+    }
+
+    /// Apply `transform` to the response.
+    pub fn then<T: ResponseTransform>(self, transform: T) -> Self {
+        Self {
+            response: transform.transform(self.response),
+        }
+    }
+}
+
+impl IntoResponse for ResponseChain {
+    fn into_response(self) -> Response<Bytes> {
+        self.response
+    }
+}
+
+/// Sets a header, replacing any previous values.
+#[derive(Debug, Clone)]
+pub struct SetHeader(pub HeaderName, pub HeaderValue);
+
+impl ResponseTransform for SetHeader {
+    fn transform(&self, mut res: Response<Bytes>) -> Response<Bytes> {
+        res.headers_mut().insert(&self.0, self.1.clone());
+        res
+    }
+}
+
+/// Sets `Content-Length` to the length of the body.
+///
+/// Put this last, after every step that changes the body.
+#[derive(Debug, Clone, Copy)]
+pub struct ContentLength;
+
+impl ResponseTransform for ContentLength {
+    fn transform(&self, mut res: Response<Bytes>) -> Response<Bytes> {
+        let len = res.body().len();
+        res.headers_mut().insert(CONTENT_LENGTH, len.into());
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use http::header::ACCEPT;
+
+    use super::*;
+    use crate::{Handler, Request};
+
+    struct Negotiated;
+
+    impl Handler for Negotiated {
+        fn handle_request(&self, req: Request) -> Response<Bytes> {
+            let wants_json = req
+                .headers()
+                .get(ACCEPT)
+                .is_some_and(|accept| accept.as_bytes().starts_with(b"application/json"));
+
+            let (content_type, body) = match wants_json {
+                true => ("application/json", r#"{"greeting":"hello"}"#),
+                false => ("text/plain", "hello"),
+            };
+
+            Response::builder()
+                .header("content-type", content_type)
+                .vary("Accept")
+                .body(Bytes::from_static(body.as_bytes()))
+                .unwrap()
+        }
+    }
+
+    fn request(accept: &'static str) -> Request {
+        let req = http::Request::builder()
+            .header(ACCEPT, accept)
+            .body(Bytes::new())
+            .unwrap();
+        Request::new(req, Vec::new())
+    }
+
+    #[test]
+    fn negotiated_responses_vary_on_accept() {
+        for accept in ["application/json", "text/plain"] {
+            let res = Negotiated.handle_request(request(accept));
+            assert_eq!(res.headers()[VARY], "accept");
+        }
+
+        let res = Negotiated.handle_request(request("application/json"));
+        assert_eq!(res.body(), r#"{"greeting":"hello"}"#);
+    }
+
+    #[test]
+    fn vary_merges_existing_values() {
+        let res = Response::builder()
+            .header(VARY, "Origin")
+            .vary("accept-encoding")
+            .vary("Origin")
+            .body(())
+            .unwrap();
+
+        let values: Vec<_> = res.headers().get_all(VARY).iter().collect();
+        assert_eq!(values, ["origin, accept-encoding"]);
+    }
+
+    #[test]
+    fn vary_star_is_kept() {
+        let res = Response::builder()
+            .header(VARY, "*")
+            .vary("Accept")
+            .body(())
+            .unwrap();
+        assert_eq!(res.headers()[VARY], "*");
+    }
+
+    #[test]
+    fn invalid_vary_name_fails_builder() {
+        let res = Response::builder()
+            .status(StatusCode::OK)
+            .vary("not a header")
+            .body(());
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn chain_applies_transforms_in_order() {
+        let shout = |res: Response<Bytes>| res.map(|body| body.to_ascii_uppercase().into());
+
+        let res = "hello"
+            .pipeline()
+            .then(SetHeader(
+                HeaderName::from_static("x-foo"),
+                HeaderValue::from_static("bar"),
+            ))
+            .then(|res: Response<Bytes>| res.map(|body| [&body[..], b", world"].concat().into()))
+            .then(shout)
+            .then(ContentLength)
+            .into_response();
+
+        assert_eq!(res.headers()["x-foo"], "bar");
+        assert_eq!(res.body(), "HELLO, WORLD");
+        assert_eq!(res.headers()[CONTENT_LENGTH], "12");
+    }
+
+    #[test]
+    fn set_header_replaces_previous_values() {
+        let res = Response::builder()
+            .header("x-foo", "old")
+            .header("x-foo", "older")
+            .body("")
+            .unwrap()
+            .pipeline()
+            .then(SetHeader(
+                HeaderName::from_static("x-foo"),
+                HeaderValue::from_static("new"),
+            ))
+            .into_response();
+
+        let values: Vec<_> = res.headers().get_all("x-foo").iter().collect();
+        assert_eq!(values, ["new"]);
+    }
+
+    fn find_user(id: u32) -> Result<String, ErrorResponse> {
+        match id {
+            1 => Ok("ferris".to_owned()),
+            _ => Err(ErrorResponse::new(StatusCode::NOT_FOUND, "user not found")),
+        }
+    }
+
+    struct ShowUser;
+
+    impl Handler for ShowUser {
+        fn handle_request(&self, req: Request) -> Response<Bytes> {
+            let id = req.uri().path().trim_start_matches('/').parse().unwrap();
+            find_user(id).into_response()
+        }
+    }
+
+    fn get(uri: &str) -> Request {
+        Request::new(
+            http::Request::get(uri).body(Bytes::new()).unwrap(),
+            Vec::new(),
+        )
+    }
+
+    #[test]
+    fn result_responds_with_either_variant() {
+        let res = ShowUser.handle_request(get("/1"));
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.body(), "ferris");
+
+        let res = ShowUser.handle_request(get("/2"));
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+        assert_eq!(res.body(), "user not found");
+    }
+
+    #[test]
+    fn error_response_is_an_error() {
+        let err: Box<dyn std::error::Error> =
+            Box::new(ErrorResponse::new(StatusCode::CONFLICT, "name taken"));
+        assert_eq!(err.to_string(), "name taken");
+    }
+
+    #[test]
+    fn helpers_set_status_and_headers() {
+        let cases = [
+            (ok("hi"), StatusCode::OK, "hi"),
+            (created("made"), StatusCode::CREATED, "made"),
+            (no_content(), StatusCode::NO_CONTENT, ""),
+            (bad_request("no"), StatusCode::BAD_REQUEST, "no"),
+            (unauthorized(), StatusCode::UNAUTHORIZED, "Unauthorized"),
+            (forbidden(), StatusCode::FORBIDDEN, "Forbidden"),
+            (not_found_response(), StatusCode::NOT_FOUND, "Not Found"),
+            (
+                internal_server_error("oops"),
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "oops",
+            ),
+        ];
+        for (res, status, body) in cases {
+            assert_eq!(res.status(), status);
+            assert_eq!(res.body(), body);
+        }
+
+        let res = permanent_redirect("/new");
+        assert_eq!(res.status(), StatusCode::MOVED_PERMANENTLY);
+        assert_eq!(res.headers()[LOCATION], "/new");
+
+        let res = redirect("/new\nlocation");
+        assert_eq!(res.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    #[cfg(feature = "serde_json")]
+    #[test]
+    fn json_shortcut_sets_the_content_type() {
+        let res = Response::builder()
+            .status(StatusCode::CREATED)
+            .json(serde_json::json!({ "id": 1 }));
+
+        assert_eq!(res.status(), StatusCode::CREATED);
+        assert_eq!(res.headers()[CONTENT_TYPE], "application/json");
+        assert_eq!(res.body(), r#"{"id":1}"#);
+
+        let res = Response::builder().header("bad\n", "x").json(1);
+        assert_eq!(res.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    }
+}
@@ -1,30 +1,59 @@
 /// Route group
-use std::collections::HashMap;
+use std::{
+    cmp::Reverse,
+    collections::HashMap,
+    sync::{Arc, OnceLock},
+    time::Duration,
+};
 
-use http::Method;
+use http::{header::IntoHeaderName, HeaderMap, HeaderValue, Method};
 
-use super::middleware::BoxedMiddleware;
-use super::routes::{Handler, Path, Route};
+use super::{
+    routes::{
+        any_method, AsyncHandler, Handler, HandlerFactory, Match, ParseError, Path, Route,
+        RouteInfo,
+    },
+    trie::Trie,
+};
+use crate::{middleware::BoxedMiddleware, GenuineError};
 
 // use hyper::Method;
 
+/// Two routes of a group match the same requests, see
+/// [`Group::try_add_route`].
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+#[error("route `{new}` conflicts with `{existing}`")]
+pub struct RouteConflict {
+    existing: String,
+    new: String,
+}
+
+impl RouteConflict {
+    /// The route that was added first, e.g. `GET /users/{id}`.
+    pub fn existing(&self) -> &str {
+        &self.existing
+    }
+
+    /// The route that could not be added.
+    pub fn new_route(&self) -> &str {
+        &self.new
+    }
+}
+
 /// Route group
 ///
 /// # Examples
 ///
 /// ```
-/// use sincere::App;
-/// use sincere::app::Group;
+/// use genuine::{App, Group};
+/// use http::Method;
 ///
 /// let mut group = Group::new("/app");
 ///
-/// group.get("/", |context| {
-///     context.response.from_text("Hello world!").unwrap();
-/// });
+/// let hello = || "Hello world!";
 ///
-/// group.post("/", |context| {
-///     context.response.from_text("Hello world!").unwrap();
-/// });
+/// group.add(Method::GET, "/", hello as fn() -> _).unwrap();
+/// group.add(Method::POST, "/", hello as fn() -> _).unwrap();
 ///
 /// let mut app = App::new();
 ///
@@ -33,72 +62,816 @@ use super::routes::{Handler, Path, Route};
 /// or
 ///
 /// ```
-/// use sincere::App;
+/// use genuine::App;
+/// use http::Method;
 ///
 /// let mut app = App::new();
 ///
 /// app.mount("/app", |group| {
-///
-///     group.get("/", |context| {
-///         context.response.from_text("Get method!").unwrap();
-///     });
-///
-///     group.post("/", |context| {
-///         context.response.from_text("Post method!").unwrap();
-///     });
-///
+///     group.add(Method::GET, "/", (|| "Get method!") as fn() -> _).unwrap();
+///     group.add(Method::POST, "/", (|| "Post method!") as fn() -> _).unwrap();
 /// });
 /// ```
 pub struct Group {
-    pub routes: HashMap<Method, Vec<Route>>,
+    /// The routes of this group by method, in insertion order, which only
+    /// decides between equally specific routes when matching.
+    ///
+    /// Only changed through methods that reset `index`, so that lookups
+    /// never see a stale index. [`Group::routes`] gives read access.
+    pub(crate) routes: HashMap<Method, Vec<Route>>,
+    index: OnceLock<HashMap<Method, Trie>>,
     prefix: String,
     pub before: Vec<BoxedMiddleware>,
     pub after: Vec<BoxedMiddleware>,
+    pub(crate) headers: HeaderMap,
+    timeout: Option<Duration>,
 }
 
 impl Group {
     /// Create a route group.
     ///
+    /// Repeated slashes in `prefix` are collapsed and trailing ones removed,
+    /// so `"/app/"` and `"//app"` are the same prefix as `"/app"`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `prefix` is not a valid route path, see [`Group::try_new`].
+    ///
     /// # Examples
     ///
     /// ```
-    /// use sincere::app::Group;
+    /// use genuine::Group;
     ///
     /// let group = Group::new("/app");
     /// ```
     ///
     pub fn new(prefix: &str) -> Group {
-        Group {
+        match Group::try_new(prefix) {
+            Ok(group) => group,
+            Err(err) => panic!("invalid group prefix:\n{err}"),
+        }
+    }
+
+    /// Like [`Group::new`], but returns a [`ParseError`] if `prefix` is not
+    /// empty and not a valid route path, e.g. because it does not start with
+    /// a `/`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use genuine::{Group, ParseErrorKind};
+    ///
+    /// let err = Group::try_new("app").err().unwrap();
+    ///
+    /// assert_eq!(err.kind(), &ParseErrorKind::IsNotAbsolute { pos: 0 });
+    /// ```
+    pub fn try_new(prefix: &str) -> Result<Group, ParseError> {
+        let prefix = normalize_prefix(prefix);
+        // the prefix must be a valid start of the paths of its routes
+        Path::new(format!("{prefix}/"))?;
+
+        Ok(Group {
             routes: HashMap::new(),
-            prefix: prefix.to_owned(),
+            index: OnceLock::new(),
+            prefix,
             before: Vec::new(),
             after: Vec::new(),
-        }
+            headers: HeaderMap::new(),
+            timeout: None,
+        })
+    }
+
+    /// Returns the normalized prefix of this group, e.g. `/app` for a group
+    /// created with `"/app/"`. The root group has the empty prefix.
+    pub fn prefix(&self) -> &str {
+        &self.prefix
+    }
+
+    /// Returns the routes of this group, whose paths include the prefix.
+    ///
+    /// Routes of the same method are returned in insertion order, but the
+    /// methods are in no particular order. This is not the order in which
+    /// requests are matched, see [`Group::find`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use genuine::Group;
+    ///
+    /// let mut group = Group::new("/app");
+    /// group.get("/users", (|| "users") as fn() -> _).unwrap();
+    ///
+    /// let paths: Vec<_> = group.routes().map(|route| route.to_string()).collect();
+    /// assert_eq!(paths, ["GET /app/users"]);
+    /// ```
+    pub fn routes(&self) -> impl Iterator<Item = &Route> {
+        self.routes.values().flatten()
+    }
+
+    /// Set a request timeout for all routes added to this group afterwards.
+    ///
+    /// See [`Route::timeout`] for details.
+    pub fn request_timeout(&mut self, timeout: Duration) -> &mut Group {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Set a header on every response from routes in this group.
+    ///
+    /// Headers set by a route, or by its handler, take precedence over group
+    /// headers, which in turn take precedence over the app's default response
+    /// headers.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use genuine::Group;
+    /// use http::HeaderValue;
+    ///
+    /// let mut group = Group::new("/v2");
+    ///
+    /// group.response_header("x-api-version", HeaderValue::from_static("2"));
+    /// ```
+    pub fn response_header<K>(&mut self, name: K, value: HeaderValue) -> &mut Group
+    where
+        K: IntoHeaderName,
+    {
+        self.headers.insert(name, value);
+        self
     }
 
     /// Add route handle to group.
     ///
+    /// The pattern is appended to the group prefix, except for `/`, which
+    /// is the root of the group: with the prefix `/app`, it routes `/app`.
+    ///
+    /// Returns a [`ParseError`] if the group prefix and `pattern` do not form
+    /// a valid route path.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the route conflicts with another one, see
+    /// [`Group::try_add_route`].
+    ///
     /// # Examples
     ///
     /// ```
-    /// use sincere::app::Group;
-    /// use sincere::http::Method;
+    /// use genuine::Group;
+    /// use http::Method;
     ///
     /// let mut group = Group::new("/app");
     ///
-    /// group.add(Method::GET, "/", |context| {
-    ///     context.response.from_text("Get method!").unwrap();
-    /// });
+    /// group.add(Method::GET, "/", (|| "Get method!") as fn() -> _).unwrap();
+    /// ```
+    pub fn add<H>(
+        &mut self,
+        method: Method,
+        pattern: &str,
+        handler: H,
+    ) -> Result<&mut Route, ParseError>
+    where
+        H: Handler,
+    {
+        match self.try_add(method, pattern, handler) {
+            Ok(route) => Ok(route),
+            Err(GenuineError::Parse(err)) => Err(err),
+            Err(err) => panic!("{err}"),
+        }
+    }
+
+    /// Like [`Group::add`], but returns a [`GenuineError::RouteConflict`]
+    /// instead of panicking if the route conflicts with another one.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use genuine::{GenuineError, Group};
+    /// use http::Method;
+    ///
+    /// let mut group = Group::new("/users");
+    /// group.try_add(Method::GET, "/{id}", (|| "User") as fn() -> _).unwrap();
+    ///
+    /// let err = group
+    ///     .try_add(Method::GET, "/{name}", (|| "Name") as fn() -> _)
+    ///     .unwrap_err();
+    /// assert!(matches!(err, GenuineError::RouteConflict(_)));
+    ///
+    /// let err = group
+    ///     .try_add(Method::GET, "/{0id}", (|| "Invalid") as fn() -> _)
+    ///     .unwrap_err();
+    /// assert!(matches!(err, GenuineError::Parse(_)));
+    /// ```
+    pub fn try_add<H>(
+        &mut self,
+        method: Method,
+        pattern: &str,
+        handler: H,
+    ) -> Result<&mut Route, GenuineError>
+    where
+        H: Handler,
+    {
+        let path = Path::new(join_prefix(&self.prefix, pattern))?;
+        let route = Route::new(method, path, Box::new(handler));
+
+        Ok(self.try_add_route(route)?)
+    }
+
+    /// Like [`Group::add`], but returns the group, so that routes can be
+    /// added in a chain.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `pattern` is not a valid route path.
+    ///
+    /// # Examples
+    ///
     /// ```
-    pub fn add<H>(&mut self, method: Method, pattern: &str, handler: H) -> &mut Route
+    /// use genuine::Group;
+    /// use http::Method;
+    ///
+    /// let mut group = Group::new("/users");
+    ///
+    /// group
+    ///     .route(Method::GET, "/{id}", (|| "Show user") as fn() -> _)
+    ///     .route(Method::DELETE, "/{id}", (|| "Delete user") as fn() -> _);
+    /// ```
+    pub fn route<H>(&mut self, method: Method, pattern: &str, handler: H) -> &mut Group
+    where
+        H: Handler,
+    {
+        if let Err(err) = self.add(method, pattern, handler) {
+            panic!("invalid route path:\n{err}");
+        }
+        self
+    }
+
+    /// Add a `GET` route handle to group, see [`Group::add`].
+    pub fn get<H>(&mut self, pattern: &str, handler: H) -> Result<&mut Route, ParseError>
+    where
+        H: Handler,
+    {
+        self.add(Method::GET, pattern, handler)
+    }
+
+    /// Add a `POST` route handle to group, see [`Group::add`].
+    pub fn post<H>(&mut self, pattern: &str, handler: H) -> Result<&mut Route, ParseError>
+    where
+        H: Handler,
+    {
+        self.add(Method::POST, pattern, handler)
+    }
+
+    /// Add a `PUT` route handle to group, see [`Group::add`].
+    pub fn put<H>(&mut self, pattern: &str, handler: H) -> Result<&mut Route, ParseError>
+    where
+        H: Handler,
+    {
+        self.add(Method::PUT, pattern, handler)
+    }
+
+    /// Add a `PATCH` route handle to group, see [`Group::add`].
+    pub fn patch<H>(&mut self, pattern: &str, handler: H) -> Result<&mut Route, ParseError>
+    where
+        H: Handler,
+    {
+        self.add(Method::PATCH, pattern, handler)
+    }
+
+    /// Add a `DELETE` route handle to group, see [`Group::add`].
+    pub fn delete<H>(&mut self, pattern: &str, handler: H) -> Result<&mut Route, ParseError>
+    where
+        H: Handler,
+    {
+        self.add(Method::DELETE, pattern, handler)
+    }
+
+    /// Add a `HEAD` route handle to group, see [`Group::add`].
+    ///
+    /// Without one, `HEAD` requests are answered by the `GET` route of the
+    /// path with the body removed.
+    pub fn head<H>(&mut self, pattern: &str, handler: H) -> Result<&mut Route, ParseError>
+    where
+        H: Handler,
+    {
+        self.add(Method::HEAD, pattern, handler)
+    }
+
+    /// Add a `OPTIONS` route handle to group, see [`Group::add`].
+    pub fn options<H>(&mut self, pattern: &str, handler: H) -> Result<&mut Route, ParseError>
     where
         H: Handler,
     {
-        let path = Path::new(self.prefix.clone() + pattern).unwrap();
-        let route = Route::new(method.clone(), path, Box::new(handler));
+        self.add(Method::OPTIONS, pattern, handler)
+    }
 
-        let routes = self.routes.entry(method).or_default();
+    /// Add a route handle for any method to group, see [`Group::add`].
+    ///
+    /// It answers requests with methods that no other route of the path
+    /// handles, including non-standard ones. Its [`Route::method`] is `ANY`.
+    pub fn any<H>(&mut self, pattern: &str, handler: H) -> Result<&mut Route, ParseError>
+    where
+        H: Handler,
+    {
+        self.add(any_method(), pattern, handler)
+    }
+
+    /// Add an [`AsyncHandler`] to group.
+    ///
+    /// Async handlers are awaited on the runtime instead of blocking it, and
+    /// can be mixed freely with synchronous ones in the same group.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use genuine::{Group, Request};
+    /// use http::Method;
+    ///
+    /// let mut group = Group::new("/app");
+    ///
+    /// group.add(Method::GET, "/", (|| "Get method!") as fn() -> _).unwrap();
+    /// group
+    ///     .add_async(Method::POST, "/", |_: Request| async { "Post method!" })
+    ///     .unwrap();
+    /// ```
+    pub fn add_async<H>(
+        &mut self,
+        method: Method,
+        pattern: &str,
+        handler: H,
+    ) -> Result<&mut Route, ParseError>
+    where
+        H: AsyncHandler,
+    {
+        let path = Path::new(join_prefix(&self.prefix, pattern))?;
+        let route = Route::new_async(method, path, Box::new(handler));
+
+        Ok(self.add_route(route))
+    }
+
+    /// Add a route handle created by `factory` from `state` to group.
+    ///
+    /// The factory is called once, right away, with a clone of `state`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::sync::Arc;
+    ///
+    /// use genuine::{Group, Handler, IntoResponse, Request};
+    /// use http::{Method, Response};
+    /// use hyper::body::Bytes;
+    ///
+    /// struct Config {
+    ///     greeting: &'static str,
+    /// }
+    ///
+    /// struct Greet {
+    ///     config: Arc<Config>,
+    /// }
+    ///
+    /// impl Handler for Greet {
+    ///     fn handle_request(&self, _: Request) -> Response<Bytes> {
+    ///         self.config.greeting.into_response()
+    ///     }
+    /// }
+    ///
+    /// let config = Arc::new(Config { greeting: "Hello world!" });
+    /// let mut group = Group::new("/app");
+    ///
+    /// group
+    ///     .add_factory(Method::GET, "/", &config, |config| Greet { config })
+    ///     .unwrap();
+    /// ```
+    pub fn add_factory<S, F>(
+        &mut self,
+        method: Method,
+        pattern: &str,
+        state: &Arc<S>,
+        factory: F,
+    ) -> Result<&mut Route, ParseError>
+    where
+        F: HandlerFactory<S>,
+    {
+        self.add(method, pattern, factory.create(Arc::clone(state)))
+    }
+
+    /// Add a pre-configured route to group.
+    ///
+    /// The route path is used as is, i.e. the group prefix is *not* prepended
+    /// to it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the route conflicts with another one, see
+    /// [`Group::try_add_route`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use genuine::{route, Group};
+    ///
+    /// let mut group = Group::new("/app");
+    ///
+    /// let route = route!(GET "/" => (|| "Get method!") as fn() -> _).unwrap();
+    /// group.add_route(route);
+    /// ```
+    pub fn add_route(&mut self, route: Route) -> &mut Route {
+        match self.try_add_route(route) {
+            Ok(route) => route,
+            Err(err) => panic!("{err}"),
+        }
+    }
+
+    /// Add a pre-configured route to group, unless it conflicts with another
+    /// one.
+    ///
+    /// Two routes conflict if they have the same method and their paths match
+    /// exactly the same requests, e.g. `/users/{id}` and `/users/{name}`. The
+    /// route added later could never be reached.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use genuine::{route, Group};
+    ///
+    /// let mut group = Group::new("/app");
+    ///
+    /// let user = route!(GET "/users/{id}" => (|| "User") as fn() -> _).unwrap();
+    /// group.try_add_route(user).unwrap();
+    ///
+    /// let name = route!(GET "/users/{name}" => (|| "Name") as fn() -> _).unwrap();
+    /// let err = group.try_add_route(name).unwrap_err();
+    /// assert_eq!(err.existing(), "GET /users/{id}");
+    /// ```
+    pub fn try_add_route(&mut self, route: Route) -> Result<&mut Route, RouteConflict> {
+        let existing = self
+            .routes
+            .get(route.method())
+            .into_iter()
+            .flatten()
+            .find(|existing| existing.path.conflicts_with(&route.path));
+        if let Some(existing) = existing {
+            return Err(RouteConflict {
+                existing: existing.to_string(),
+                new: route.to_string(),
+            });
+        }
+
+        self.index = OnceLock::new();
+
+        let routes = self.routes.entry(route.method().clone()).or_default();
         routes.push(route);
-        routes.last_mut().unwrap()
+
+        let route = routes.last_mut().unwrap();
+        if let Some(timeout) = self.timeout {
+            route.timeout(timeout);
+        }
+        Ok(route)
+    }
+
+    /// Describes the routes of this group, sorted by path and method.
+    pub(crate) fn route_infos(&self) -> Vec<RouteInfo<'_>> {
+        let mut infos: Vec<_> = self
+            .routes
+            .values()
+            .flatten()
+            .map(|route| route.info(&self.prefix))
+            .collect();
+        infos.sort_by(|a, b| (a.path, a.method.as_str()).cmp(&(b.path, b.method.as_str())));
+        infos
+    }
+
+    /// Prepend `prefix` to the prefix of this group and to the paths of all
+    /// its routes, e.g. to nest an app in another one.
+    pub(crate) fn prepend_prefix(&mut self, prefix: &str) -> Result<(), ParseError> {
+        let prefix = normalize_prefix(prefix);
+        Path::new(format!("{prefix}/"))?;
+
+        for route in self.routes.values_mut().flatten() {
+            route.path = Path::new(join_prefix(&prefix, route.path.as_ref()))?;
+        }
+        self.prefix = normalize_prefix(&format!("{prefix}{}", self.prefix));
+        self.index = OnceLock::new();
+        Ok(())
+    }
+
+    /// Returns the first route of `other` that conflicts with a route of this
+    /// group, see [`Group::try_add_route`].
+    pub(crate) fn find_conflict(&self, other: &Group) -> Option<RouteConflict> {
+        other.routes().find_map(|route| {
+            let existing = self
+                .routes
+                .get(route.method())?
+                .iter()
+                .find(|existing| existing.path.conflicts_with(&route.path))?;
+            Some(RouteConflict {
+                existing: existing.to_string(),
+                new: route.to_string(),
+            })
+        })
+    }
+
+    /// Remove a route from the group and return it.
+    ///
+    /// The route is looked up by its method and by the exact pattern it was
+    /// added with, i.e. without the group prefix. Returns [`None`] if no such
+    /// route exists.
+    ///
+    /// Routes can only be removed while the app is being set up, because the
+    /// router is frozen once the server starts.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use genuine::Group;
+    /// use http::Method;
+    ///
+    /// let mut group = Group::new("/app");
+    ///
+    /// group.add(Method::GET, "/", (|| "Get method!") as fn() -> _).unwrap();
+    ///
+    /// assert!(group.remove_route(&Method::GET, "/").is_some());
+    /// assert!(group.remove_route(&Method::GET, "/").is_none());
+    /// ```
+    pub fn remove_route(&mut self, method: &Method, pattern: &str) -> Option<Route> {
+        let path = join_prefix(&self.prefix, pattern);
+
+        let routes = self.routes.get_mut(method)?;
+        let index = routes
+            .iter()
+            .position(|route| route.path.as_ref() == path)?;
+        let route = routes.remove(index);
+
+        if routes.is_empty() {
+            self.routes.remove(method);
+        }
+
+        self.index = OnceLock::new();
+        Some(route)
+    }
+
+    /// Returns the route for `method` that matches `path`.
+    ///
+    /// The most specific matching route wins, the one with the most literal
+    /// bytes in its path, e.g. `/users/me` beats `/users/{id}`. Of equally
+    /// specific routes, the one added first wins. Routes ending in a wildcard
+    /// are only tried if no other route matches.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use genuine::Group;
+    /// use http::Method;
+    ///
+    /// let mut group = Group::new("/users");
+    ///
+    /// group.get("/{*rest}", (|| "Any user page") as fn() -> _).unwrap();
+    /// group.get("/{id}", (|| "User") as fn() -> _).unwrap();
+    /// group.get("/me", (|| "Current user") as fn() -> _).unwrap();
+    ///
+    /// let route = group.find(&Method::GET, "/users/42").unwrap();
+    /// assert_eq!(route.to_string(), "GET /users/{id}");
+    ///
+    /// let route = group.find(&Method::GET, "/users/me").unwrap();
+    /// assert_eq!(route.to_string(), "GET /users/me");
+    /// ```
+    pub fn find(&self, method: &Method, path: &str) -> Option<&Route> {
+        [false, true]
+            .into_iter()
+            .find_map(|wildcard| self.find_match(method, path, wildcard))
+            .map(|(route, _)| route)
+    }
+
+    /// Returns the most specific route for `method` that matches `path` and
+    /// ends in a wildcard or not, with the parameters it matched. Of equally
+    /// specific routes, the first one wins.
+    pub(crate) fn find_match(
+        &self,
+        method: &Method,
+        path: &str,
+        wildcard: bool,
+    ) -> Option<(&Route, Vec<Match>)> {
+        let routes = self.routes.get(method)?;
+        let trie = self.index().get(method)?;
+
+        trie.prefixes_of(path.as_bytes())
+            .into_iter()
+            .filter_map(|index| routes.get(index))
+            .filter(|route| route.path.has_wildcard() == wildcard)
+            .filter_map(|route| route.path.matches(path).map(|matches| (route, matches)))
+            .min_by_key(|(route, _)| Reverse(route.path.specificity()))
+    }
+
+    /// Returns the lookup index of the routes, building it if necessary.
+    fn index(&self) -> &HashMap<Method, Trie> {
+        self.index.get_or_init(|| {
+            self.routes
+                .iter()
+                .map(|(method, routes)| {
+                    let mut trie = Trie::default();
+                    for (index, route) in routes.iter().enumerate() {
+                        trie.insert(route.path.static_prefix(), index);
+                    }
+                    (method.clone(), trie)
+                })
+                .collect()
+        })
+    }
+}
+
+/// Returns the path of a route added with `pattern` to a group with
+/// `prefix`. The pattern `/` is the root of the group, i.e. the prefix
+/// itself, so `/app` and not `/app/`.
+fn join_prefix(prefix: &str, pattern: &str) -> String {
+    match pattern {
+        "/" if !prefix.is_empty() => prefix.to_owned(),
+        pattern => format!("{prefix}{pattern}"),
+    }
+}
+
+/// Collapses repeated slashes in `prefix` and removes trailing ones.
+fn normalize_prefix(prefix: &str) -> String {
+    let mut normalized = String::with_capacity(prefix.len());
+    for c in prefix.chars() {
+        if !(c == '/' && normalized.ends_with('/')) {
+            normalized.push(c);
+        }
+    }
+    normalized.truncate(normalized.trim_end_matches('/').len());
+    normalized
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ParseErrorKind;
+
+    fn hello() -> &'static str {
+        "Hello world!"
+    }
+
+    #[test]
+    fn root_pattern_is_the_prefix() {
+        let mut group = Group::new("/app");
+        group.add(Method::GET, "/", hello as fn() -> _).unwrap();
+        group
+            .add(Method::GET, "/about", hello as fn() -> _)
+            .unwrap();
+
+        let paths: Vec<_> = group.routes().map(|route| route.path.as_ref()).collect();
+        assert_eq!(paths, ["/app", "/app/about"]);
+        assert!(group.remove_route(&Method::GET, "/").is_some());
+
+        let mut root = Group::new("");
+        root.add(Method::GET, "/", hello as fn() -> _).unwrap();
+        assert_eq!(root.routes().next().unwrap().path.as_ref(), "/");
+    }
+
+    #[test]
+    fn add_rejects_invalid_paths() {
+        let mut group = Group::new("/app");
+        let err = group.add(Method::GET, "/users/{id", hello as fn() -> _);
+        assert!(err.is_err());
+        assert!(group.routes.is_empty());
+    }
+
+    #[test]
+    fn invalid_prefixes_are_rejected() {
+        let err = Group::try_new("app").err().unwrap();
+        assert_eq!(err.kind(), &ParseErrorKind::IsNotAbsolute { pos: 0 });
+
+        assert!(Group::try_new("/files/{*path}").is_err());
+        assert!(Group::try_new("/users/{id").is_err());
+        assert!(Group::try_new("/users/{id}").is_ok());
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid group prefix")]
+    fn new_panics_on_invalid_prefixes() {
+        Group::new("app");
+    }
+
+    #[test]
+    fn prefixes_are_normalized() {
+        for prefix in ["/app", "/app/", "//app", "/app//"] {
+            assert_eq!(Group::new(prefix).prefix(), "/app");
+        }
+        assert_eq!(Group::new("/").prefix(), "");
+        assert_eq!(Group::new("").prefix(), "");
+        assert_eq!(Group::new("/api//v1/").prefix(), "/api/v1");
+
+        let mut group = Group::new("/app/");
+        let route = group.get("/users", hello as fn() -> _).unwrap();
+        assert_eq!(route.to_string(), "GET /app/users");
+    }
+
+    #[test]
+    fn routes_can_be_chained() {
+        let mut group = Group::new("/app");
+        group
+            .route(Method::GET, "/users", hello as fn() -> _)
+            .route(Method::POST, "/users", hello as fn() -> _);
+
+        assert!(group.find(&Method::GET, "/app/users").is_some());
+        assert!(group.find(&Method::POST, "/app/users").is_some());
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid route path")]
+    fn chained_routes_must_be_valid() {
+        Group::new("/app").route(Method::GET, "/{0id}", hello as fn() -> _);
+    }
+
+    #[test]
+    fn conflicting_routes_are_rejected() {
+        let mut group = Group::new("/app");
+        let route = || {
+            let path = Path::new("/app/users/{id}".into()).unwrap();
+            Route::new(Method::GET, path, Box::new(hello as fn() -> _))
+        };
+
+        group.try_add_route(route()).unwrap();
+        assert_eq!(
+            group.try_add_route(route()).unwrap_err(),
+            RouteConflict {
+                existing: "GET /app/users/{id}".into(),
+                new: "GET /app/users/{id}".into(),
+            }
+        );
+        assert_eq!(group.routes[&Method::GET].len(), 1);
+
+        group.post("/users/{id}", hello as fn() -> _).unwrap();
+        group.get("/users/{id}/posts", hello as fn() -> _).unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "route `GET /app/users/{name}` conflicts with `GET /app/users/{id}`")]
+    fn add_panics_on_conflicts() {
+        let mut group = Group::new("/app");
+        group.get("/users/{id}", hello as fn() -> _).unwrap();
+        group.get("/users/{name}", hello as fn() -> _).unwrap();
+    }
+
+    #[test]
+    fn remove_route_returns_the_removed_route() {
+        let mut group = Group::new("/app");
+        group
+            .add(Method::GET, "/users", hello as fn() -> _)
+            .unwrap();
+        group
+            .add(Method::GET, "/posts", hello as fn() -> _)
+            .unwrap();
+
+        let route = group.remove_route(&Method::GET, "/users").unwrap();
+        assert_eq!(route.to_string(), "GET /app/users");
+
+        let remaining: Vec<_> = group.routes[&Method::GET]
+            .iter()
+            .map(|route| route.to_string())
+            .collect();
+        assert_eq!(remaining, ["GET /app/posts"]);
+    }
+
+    #[test]
+    fn remove_route_forgets_methods_without_routes() {
+        let mut group = Group::new("/app");
+        group
+            .add(Method::POST, "/users", hello as fn() -> _)
+            .unwrap();
+
+        assert!(group.remove_route(&Method::POST, "/users").is_some());
+        assert!(!group.routes.contains_key(&Method::POST));
+    }
+
+    #[test]
+    fn remove_route_requires_matching_method() {
+        let mut group = Group::new("/app");
+        group
+            .add(Method::GET, "/users", hello as fn() -> _)
+            .unwrap();
+
+        assert!(group.remove_route(&Method::POST, "/users").is_none());
+        assert!(group.remove_route(&Method::GET, "/app/users").is_none());
+        assert_eq!(group.routes[&Method::GET].len(), 1);
+    }
+
+    #[test]
+    fn method_shortcuts_register_routes_with_their_method() {
+        let mut group = Group::new("/app");
+        let hello = hello as fn() -> _;
+
+        group.get("/", hello).unwrap();
+        group.post("/", hello).unwrap();
+        group.put("/", hello).unwrap();
+        group.patch("/", hello).unwrap();
+        group.delete("/", hello).unwrap().name("delete");
+        group.head("/", hello).unwrap();
+        group.options("/", hello).unwrap();
+
+        for method in ["GET", "POST", "PUT", "PATCH", "DELETE", "HEAD", "OPTIONS"] {
+            let method = Method::from_bytes(method.as_bytes()).unwrap();
+            let route = &group.routes[&method][0];
+            assert_eq!(route.to_string(), format!("{method} /app"));
+        }
     }
 }
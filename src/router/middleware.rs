@@ -1,3 +0,0 @@
-pub trait Middleware {}
-
-pub type BoxedMiddleware = Box<dyn Middleware + Send + Sync + 'static>;
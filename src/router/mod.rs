@@ -1,26 +1,100 @@
 pub mod groups;
-pub mod middleware;
 pub mod routes;
+mod trie;
 
+use std::{
+    cmp::Reverse,
+    mem,
+    net::SocketAddr,
+    sync::{Arc, OnceLock},
+    time::Duration,
+};
+
+use crate::{
+    body::{StreamedBody, Streaming, StreamingBody},
+    middleware::{
+        recover, BoxedMiddleware, CacheKey, CacheLookup, Middleware, RecoverPanic, Timeout,
+    },
+    url::{UrlGenerationError, Urls},
+};
 use groups::Group;
-use http::{Method, Request, Response, Uri};
-use http_body_util::{combinators::BoxBody, BodyExt, Full};
+use http::{
+    header::{ALLOW, CONTENT_LENGTH, CONTENT_TYPE, LOCATION},
+    Extensions, HeaderMap, Method, Request, Response, Uri,
+};
+use http_body_util::{combinators::UnsyncBoxBody, BodyExt, Full};
 use hyper::{
     body::{Body, Bytes, Incoming},
     service::Service,
 };
-use middleware::BoxedMiddleware;
-use routes::{Match, Route};
+use routes::{any_method, BoxError, BoxedFuture, ErasedHandler, Handler, Match, Route};
+use tracing::Instrument;
+
+/// The body of responses, either buffered or streamed.
+pub(crate) type ResponseBody = UnsyncBoxBody<Bytes, BoxError>;
+
+/// Correlates the events of a request, if the client or a proxy set it.
+const X_REQUEST_ID: &str = "x-request-id";
 
-type BoxedFuture<T> =
-    std::pin::Pin<Box<dyn std::future::Future<Output = T> + Send + Sync + 'static>>;
+/// The methods that `any` routes are listed with in `Allow` headers.
+const STANDARD_METHODS: [Method; 7] = [
+    Method::GET,
+    Method::POST,
+    Method::PUT,
+    Method::PATCH,
+    Method::DELETE,
+    Method::HEAD,
+    Method::OPTIONS,
+];
 
+/// Requests with larger bodies are rejected, unless configured otherwise.
+const DEFAULT_MAX_BODY_SIZE: u64 = 64 * 1024;
+
+/// Dispatches requests to the handler of the matching route.
+///
+/// Middlewares run like a stack: `before` middlewares of the router, the
+/// group and the route in insertion order, then the handler, then `after`
+/// middlewares of the route, the group and the router in reverse insertion
+/// order. A `before` middleware that responds skips the rest of them and the
+/// handler. See [`Middleware`](crate::middleware::Middleware).
+///
+/// `HEAD` requests are answered by the `GET` route of the path, without the
+/// body, unless there is a `HEAD` route for it. Likewise, `OPTIONS` requests
+/// without an `OPTIONS` route get an empty response listing the allowed
+/// methods, which browsers need for CORS preflight requests. Only the
+/// middlewares of the router run around such responses.
 pub struct Router {
     pub(crate) groups: Vec<Group>,
-    begin: Vec<BoxedMiddleware>,
-    before: Vec<BoxedMiddleware>,
-    after: Vec<BoxedMiddleware>,
-    finish: Vec<BoxedMiddleware>,
+    pub(crate) before: Vec<BoxedMiddleware>,
+    pub(crate) after: Vec<BoxedMiddleware>,
+    pub(crate) headers: HeaderMap,
+    pub(crate) state: Extensions,
+    pub(crate) max_body_size: u64,
+    pub(crate) auto_options: bool,
+    pub(crate) trailing_slash: TrailingSlashPolicy,
+    /// Answers requests to paths without routes, instead of `404 Not Found`.
+    pub(crate) not_found: Option<Arc<dyn Handler>>,
+    /// Answers requests to paths without routes for their method, instead of
+    /// `405 Method Not Allowed`.
+    pub(crate) method_not_allowed: Option<Arc<dyn Handler>>,
+    /// The URLs of named routes, collected on the first request.
+    urls: OnceLock<Urls>,
+}
+
+/// How the router treats trailing slashes in request paths, see
+/// [`App::trailing_slash`](crate::App::trailing_slash).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TrailingSlashPolicy {
+    /// Strip trailing slashes before matching, so `/users/` is routed like
+    /// `/users`, and routes registered with a trailing slash, like
+    /// `/files/`, match with or without it.
+    #[default]
+    Ignore,
+    /// Redirect paths with trailing slashes to the path without them, if a
+    /// route matches that one, with `308 Permanent Redirect`.
+    Redirect,
+    /// Match paths as they are, so `/users/` and `/users` are different.
+    Strict,
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -33,81 +107,433 @@ impl Router {
     pub fn new() -> Self {
         Self {
             groups: vec![Group::new("")],
-            begin: Vec::new(),
             before: Vec::new(),
             after: Vec::new(),
-            finish: Vec::new(),
+            headers: HeaderMap::new(),
+            state: Extensions::new(),
+            max_body_size: DEFAULT_MAX_BODY_SIZE,
+            auto_options: true,
+            trailing_slash: TrailingSlashPolicy::default(),
+            not_found: None,
+            method_not_allowed: None,
+            urls: OnceLock::new(),
         }
     }
 
+    /// Returns the URL of the route called `name`, see [`Urls::url_for`].
+    pub(crate) fn url_for(
+        &self,
+        name: &str,
+        params: &[(&str, &str)],
+    ) -> Result<String, UrlGenerationError> {
+        // routes can still be named, so do not cache the URLs yet
+        Urls::new(self.routes()).url_for(name, params)
+    }
+
+    /// Returns all routes, group by group.
+    fn routes(&self) -> impl Iterator<Item = &Route> {
+        self.groups.iter().flat_map(Group::routes)
+    }
+
     fn route(&self, uri: &Uri, method: &Method) -> Option<(&Group, &Route, Vec<Match>)> {
-        let path = match uri.path() {
-            path @ "/" => path,
-            path => path.trim_end_matches("/"),
-        }
-        .to_owned();
+        let paths = self.routed_paths(uri, method);
+
+        // wildcard routes only match if no other route does, otherwise the
+        // most specific route of all groups wins
+        [false, true].into_iter().find_map(|wildcard| {
+            paths.iter().find_map(|path| {
+                self.groups
+                    .iter()
+                    .filter_map(|g| {
+                        g.find_match(method, path, wildcard)
+                            .map(|(route, matches)| (g, route, matches))
+                    })
+                    .min_by_key(|(_, route, _)| Reverse(route.path.specificity()))
+            })
+        })
+    }
 
-        self.groups
+    /// Returns the methods of all routes matching `uri`, sorted by name.
+    fn allowed_methods(&self, uri: &Uri) -> Vec<Method> {
+        let mut methods: Vec<Method> = self
+            .groups
             .iter()
-            .filter_map(|g| g.routes.get(method).map(|routes| (g, routes)))
-            .find_map(|(g, routes)| {
-                routes
+            .flat_map(|g| g.routes.keys().map(move |method| (g, method)))
+            .filter(|(g, method)| {
+                self.routed_paths(uri, method)
                     .iter()
-                    .find_map(|route| route.path.matches(&path).map(|matches| (g, route, matches)))
+                    .any(|path| g.find(method, path).is_some())
+            })
+            .flat_map(|(_, method)| match method {
+                any if *any == any_method() => STANDARD_METHODS.to_vec(),
+                method => vec![method.clone()],
             })
+            .collect();
+
+        if methods.contains(&Method::GET) {
+            methods.push(Method::HEAD);
+        }
+        if self.auto_options && !methods.is_empty() {
+            methods.push(Method::OPTIONS);
+        }
+        methods.sort_by(|a, b| a.as_str().cmp(b.as_str()));
+        methods.dedup();
+        methods
+    }
+
+    /// Returns the paths that routes for `method` are matched against, in
+    /// order.
+    ///
+    /// When trailing slashes are ignored, a path is matched without them
+    /// first, then with a single one, so routes registered with a trailing
+    /// slash, like `/files/` or the root of a group, match as well.
+    fn routed_paths(&self, uri: &Uri, method: &Method) -> Vec<String> {
+        // CONNECT requests target an authority (`host:port`) instead of a path.
+        // Such routes are registered as a single path segment.
+        if method == Method::CONNECT {
+            return uri
+                .authority()
+                .map(|authority| format!("/{authority}"))
+                .into_iter()
+                .collect();
+        }
+
+        match (self.trailing_slash, uri.path()) {
+            (TrailingSlashPolicy::Ignore, path) if path != "/" => {
+                let path = path.trim_end_matches('/');
+                vec![path.to_owned(), format!("{path}/")]
+            }
+            (_, path) => vec![path.to_owned()],
+        }
+    }
+
+    /// Returns where to redirect requests for `uri` to, if its path has
+    /// trailing slashes and the path without them has routes.
+    fn trailing_slash_redirect(&self, uri: &Uri) -> Option<String> {
+        if self.trailing_slash != TrailingSlashPolicy::Redirect {
+            return None;
+        }
+
+        let path = uri.path().trim_end_matches('/');
+        if path.is_empty() || path == uri.path() {
+            return None;
+        }
+
+        let location = match uri.query() {
+            Some(query) => format!("{path}?{query}"),
+            None => path.to_owned(),
+        };
+        let canonical = location.parse().ok()?;
+        (!self.allowed_methods(&canonical).is_empty()).then_some(location)
+    }
+}
+
+impl Router {
+    /// Moves the groups of `other` into this router, below `prefix`.
+    ///
+    /// The middlewares, default headers and state of `other` keep applying
+    /// to its routes, and to those only.
+    pub(crate) fn nest(&mut self, prefix: &str, other: Router) -> Result<(), NestError> {
+        let middlewares = NestedMiddlewares(Arc::new((other.before, other.after)));
+
+        let mut groups = other.groups;
+        for group in &mut groups {
+            group.prepend_prefix(prefix)?;
+
+            let conflict = self
+                .groups
+                .iter()
+                .find_map(|existing| existing.find_conflict(group));
+            if let Some(conflict) = conflict {
+                return Err(conflict.into());
+            }
+
+            // the middlewares of the nested router wrap those of its groups
+            group.before.insert(0, Box::new(middlewares.clone()));
+            group.after.insert(0, Box::new(middlewares.clone()));
+            merge_headers(&mut group.headers, &other.headers);
+        }
+
+        self.groups.extend(groups);
+        self.state.extend(other.state);
+        Ok(())
     }
 }
 
-impl Service<Request<Incoming>> for Router {
-    type Response = Response<BoxBody<Bytes, hyper::Error>>;
+/// Why an app could not be nested, see [`Router::nest`].
+#[derive(Debug, thiserror::Error)]
+pub(crate) enum NestError {
+    #[error("invalid prefix:\n{0}")]
+    Prefix(#[from] routes::ParseError),
+    #[error(transparent)]
+    Conflict(#[from] groups::RouteConflict),
+}
+
+/// The `before` and `after` middlewares of a nested router, which run as one.
+#[derive(Clone)]
+struct NestedMiddlewares(Arc<(Vec<BoxedMiddleware>, Vec<BoxedMiddleware>)>);
+
+impl Middleware for NestedMiddlewares {
+    fn before(&self, req: &mut Request<Bytes>) -> Option<Response<Bytes>> {
+        let (before, _) = &*self.0;
+        before.iter().find_map(|middleware| middleware.before(req))
+    }
+
+    fn after(&self, res: &mut Response<Bytes>) {
+        let (_, after) = &*self.0;
+        for middleware in after.iter().rev() {
+            middleware.after(res);
+        }
+    }
+}
+
+/// Serves connections with a shared [`Router`].
+///
+/// Response futures must be `'static`, so they cannot borrow the router.
+/// Instead, every future owns a clone of the [`Arc`], which keeps the router
+/// alive until the last response is sent, even if the server is gone.
+///
+/// There is one service per connection. It adds the address of the client,
+/// if known, to the extensions of every request.
+#[derive(Clone)]
+pub(crate) struct RouterService {
+    pub(crate) router: Arc<Router>,
+    pub(crate) remote: Option<SocketAddr>,
+}
+
+impl Service<Request<Incoming>> for RouterService {
+    type Response = Response<ResponseBody>;
     type Error = Error;
     type Future = BoxedFuture<Result<Self::Response, Self::Error>>;
 
-    fn call(&self, req: Request<Incoming>) -> Self::Future {
-        for _begin in &self.begin {}
+    fn call(&self, mut req: Request<Incoming>) -> Self::Future {
+        let router = Arc::clone(&self.router);
+        if let Some(remote) = self.remote {
+            req.extensions_mut().insert(remote);
+        }
+
+        Box::pin(async move { router.respond(req).await })
+    }
+}
+
+impl Router {
+    /// Responds to `req` within a `request` span, which is the parent of all
+    /// events emitted while handling it.
+    pub(crate) async fn respond<B>(&self, req: Request<B>) -> Result<Response<ResponseBody>, Error>
+    where
+        B: Body<Data = Bytes> + Send + 'static,
+        B::Error: Into<BoxError>,
+    {
+        let span = tracing::info_span!(
+            "request",
+            method = %req.method(),
+            path = req.uri().path(),
+            request_id = tracing::field::Empty,
+            status = tracing::field::Empty,
+        );
+        if let Some(id) = req.headers().get(X_REQUEST_ID) {
+            span.record("request_id", id.to_str().unwrap_or_default());
+        }
 
-        // SAFETY: Router must outlive future
-        let router: &'static Router = unsafe { std::mem::transmute(self) };
+        let res = self.dispatch(req).instrument(span.clone()).await;
 
-        let fut: Self::Future = match router.route(req.uri(), req.method()) {
-            Some((group, route, matches)) => Box::pin(async move {
-                let Some(req) = ensure_max_body_size(req) else {
-                    let mut res = Response::new(full("Body too big".into()));
-                    *res.status_mut() = hyper::StatusCode::PAYLOAD_TOO_LARGE;
-                    return Ok(res);
+        span.in_scope(|| match &res {
+            Ok(res) => {
+                let status = res.status().as_u16();
+                tracing::Span::current().record("status", status);
+                tracing::debug!(status, "sent response");
+            }
+            Err(err) => tracing::warn!(error = %err, "failed to respond"),
+        });
+
+        res
+    }
+
+    async fn dispatch<B>(&self, req: Request<B>) -> Result<Response<ResponseBody>, Error>
+    where
+        B: Body<Data = Bytes> + Send + 'static,
+        B::Error: Into<BoxError>,
+    {
+        if let Some(location) = self.trailing_slash_redirect(req.uri()) {
+            return Ok(permanent_redirect(&location));
+        }
+
+        // HEAD requests without a HEAD route are answered by the GET route,
+        // and requests without a route for their method by an `any` route
+        let mut head_from_get = false;
+        let found = self
+            .route(req.uri(), req.method())
+            .or_else(|| {
+                head_from_get = req.method() == Method::HEAD;
+                head_from_get
+                    .then(|| self.route(req.uri(), &Method::GET))
+                    .flatten()
+            })
+            .or_else(|| self.route(req.uri(), &any_method()));
+
+        match found {
+            Some((group, route, matches)) => {
+                let limit = route.max_body_size.unwrap_or(self.max_body_size);
+                let mut req = if route.stream_body {
+                    // streamed bodies are limited while they are read, so only
+                    // reject the ones already known to be too large
+                    if req.body().size_hint().lower() > limit {
+                        return Ok(payload_too_large(limit));
+                    }
+                    StreamedBody::attach(req, limit)
+                } else {
+                    let Some(req) = ensure_max_body_size(req, limit) else {
+                        return Ok(payload_too_large(limit));
+                    };
+                    let req = collect_full_request_body(req).await?;
+                    match transform_body(route, req) {
+                        Ok(req) => req,
+                        Err(err) => return Ok(bad_request(err)),
+                    }
                 };
+                req.extensions_mut().extend(self.state.clone());
+                let urls = self.urls.get_or_init(|| Urls::new(self.routes()));
+                req.extensions_mut().insert(urls.clone());
+                if let Some(key) = route.cache_key {
+                    req.extensions_mut().insert(CacheKey(key));
+                }
+                if let Some(timeout) = route.timeout {
+                    // the route timeout takes the same path as the middleware
+                    Timeout(timeout).before(&mut req);
+                }
+
+                let early = [&self.before, &group.before, &route.before]
+                    .into_iter()
+                    .flatten()
+                    .find_map(|middleware| middleware.before(&mut req))
+                    .or_else(|| {
+                        // only requests all middlewares let through are
+                        // answered from the cache
+                        let lookup = req.extensions().get::<CacheLookup>().cloned()?;
+                        lookup.respond(&mut req)
+                    });
+                let extensions = req.extensions().clone();
 
-                let req = collect_full_request_body(req).await?;
+                let mut resp = match early {
+                    Some(resp) => resp,
+                    None => {
+                        let timeout = req
+                            .extensions()
+                            .get::<Timeout>()
+                            .map(|Timeout(timeout)| *timeout);
 
-                let req = crate::request::Request::new(req, matches);
+                        let recovers = req.extensions().get::<RecoverPanic>().is_some();
+                        let req = crate::request::Request::new(req, matches);
+                        let handled = async {
+                            match timeout {
+                                Some(timeout) => handle_with_timeout(route, req, timeout).await,
+                                None => Some(route.handler.handle_request(req).await),
+                            }
+                        };
+                        let handled = if recovers {
+                            recover(handled).await
+                        } else {
+                            handled.await
+                        };
+
+                        // only timeouts give up on handlers, their response
+                        // still gets the headers and `after` middlewares
+                        handled.unwrap_or_else(timed_out)
+                    }
+                };
+                for headers in [&route.headers, &group.headers, &self.headers] {
+                    merge_headers(resp.headers_mut(), headers);
+                }
+                carry_extensions(&mut resp, extensions);
 
-                for _before in &router.before {}
-                for _before in &group.before {}
+                for after in [&route.after, &group.after, &self.after] {
+                    for middleware in after.iter().rev() {
+                        middleware.after(&mut resp);
+                    }
+                }
 
-                let resp = route.handler.handle_request(req).map(full);
+                if head_from_get {
+                    strip_body(&mut resp);
+                }
 
-                for _after in &group.after {}
-                for _after in &router.after {}
+                Ok(into_body(resp))
+            }
+            None => match self.allowed_methods(req.uri()).as_slice() {
+                [] => match &self.not_found {
+                    Some(handler) => {
+                        self.respond_without_route(req, |req| handler.handle_request(req))
+                            .await
+                    }
+                    None => Ok(not_found()),
+                },
+                allowed if self.auto_options && req.method() == Method::OPTIONS => {
+                    self.respond_without_route(req, |_| options(allowed)).await
+                }
+                allowed => match &self.method_not_allowed {
+                    Some(handler) => {
+                        self.respond_without_route(req, |req| {
+                            let mut resp = handler.handle_request(req);
+                            resp.headers_mut()
+                                .entry(ALLOW)
+                                .or_insert_with(|| allow(allowed).parse().unwrap());
+                            resp
+                        })
+                        .await
+                    }
+                    None => Ok(method_not_allowed(allowed)),
+                },
+            },
+        }
+    }
+}
 
-                Ok(resp)
-            }),
-            None => Box::pin(async move { Ok(not_found()) }),
+impl Router {
+    /// Answers a request without route with `respond`, e.g. an `OPTIONS`
+    /// request to a path without `OPTIONS` route.
+    ///
+    /// Only the middlewares of the router run around it, e.g. to answer CORS
+    /// preflight requests.
+    async fn respond_without_route<B, F>(
+        &self,
+        req: Request<B>,
+        respond: F,
+    ) -> Result<Response<ResponseBody>, Error>
+    where
+        B: Body<Data = Bytes>,
+        F: FnOnce(crate::request::Request) -> Response<Bytes>,
+    {
+        let Some(req) = ensure_max_body_size(req, self.max_body_size) else {
+            return Ok(payload_too_large(self.max_body_size));
         };
+        let mut req = collect_full_request_body(req).await?;
+        req.extensions_mut().extend(self.state.clone());
 
-        for _finish in &self.finish {}
+        let early = self
+            .before
+            .iter()
+            .find_map(|middleware| middleware.before(&mut req));
+        let extensions = req.extensions().clone();
+        let mut resp =
+            early.unwrap_or_else(|| respond(crate::request::Request::new(req, Vec::new())));
+        merge_headers(resp.headers_mut(), &self.headers);
+        carry_extensions(&mut resp, extensions);
+        for middleware in self.after.iter().rev() {
+            middleware.after(&mut resp);
+        }
 
-        fut
+        Ok(into_body(resp))
     }
 }
 
-fn ensure_max_body_size(req: Request<Incoming>) -> Option<Request<Incoming>> {
-    const MAX_BODY_SIZE: u64 = 1024 * 64;
-
+fn ensure_max_body_size<B: Body>(req: Request<B>, limit: u64) -> Option<Request<B>> {
     let upper = req.body().size_hint().upper().unwrap_or(u64::MAX);
-    (upper <= MAX_BODY_SIZE).then_some(req)
+    (upper <= limit).then_some(req)
 }
 
-async fn collect_full_request_body(req: Request<Incoming>) -> Result<Request<Bytes>, Error> {
+async fn collect_full_request_body<B>(req: Request<B>) -> Result<Request<Bytes>, Error>
+where
+    B: Body<Data = Bytes>,
+{
     let (parts, body) = req.into_parts();
     let body = body
         .collect()
@@ -117,13 +543,975 @@ async fn collect_full_request_body(req: Request<Incoming>) -> Result<Request<Byt
     Ok(Request::from_parts(parts, body))
 }
 
-fn not_found() -> Response<BoxBody<Bytes, hyper::Error>> {
+/// Applies the body transforms of `route` in the order they were added.
+fn transform_body(route: &Route, req: Request<Bytes>) -> Result<Request<Bytes>, BoxError> {
+    let (parts, mut body) = req.into_parts();
+    for transform in &route.transforms {
+        body = transform.transform(body)?;
+    }
+    Ok(Request::from_parts(parts, body))
+}
+
+/// Runs the handler of `route`, synchronous ones on the blocking thread pool,
+/// and gives up on it after `timeout`.
+async fn handle_with_timeout(
+    route: &Route,
+    req: crate::request::Request,
+    timeout: Duration,
+) -> Option<Response<Bytes>> {
+    let handler = match &route.handler {
+        ErasedHandler::Sync(handler) => Arc::clone(handler),
+        ErasedHandler::Async(handler) => {
+            return tokio::time::timeout(timeout, handler.handle_request(req))
+                .await
+                .ok();
+        }
+    };
+
+    // keep handler events within the request span
+    let span = tracing::Span::current();
+    let task = tokio::task::spawn_blocking(move || span.in_scope(|| handler.handle_request(req)));
+
+    match tokio::time::timeout(timeout, task).await {
+        Ok(Ok(resp)) => Some(resp),
+        Ok(Err(err)) => std::panic::resume_unwind(err.into_panic()),
+        Err(_) => None,
+    }
+}
+
+/// Empties the body of `resp`, keeping its `Content-Length`.
+fn strip_body(resp: &mut Response<Bytes>) {
+    // the length of a stream is unknown
+    if Streaming::take(resp).is_none() {
+        let len = resp.body().len();
+        resp.headers_mut()
+            .entry(CONTENT_LENGTH)
+            .or_insert(len.into());
+    }
+    *resp.body_mut() = Bytes::new();
+}
+
+/// Adds the request `extensions` to those of `resp`, for the `after`
+/// middlewares. Extensions of the response take precedence.
+fn carry_extensions(resp: &mut Response<Bytes>, mut extensions: Extensions) {
+    extensions.extend(mem::take(resp.extensions_mut()));
+    *resp.extensions_mut() = extensions;
+}
+
+/// Adds all `defaults` to `headers` whose names are not in `headers` yet.
+fn merge_headers(headers: &mut HeaderMap, defaults: &HeaderMap) {
+    for name in defaults.keys() {
+        if !headers.contains_key(name) {
+            for value in defaults.get_all(name) {
+                headers.append(name, value.clone());
+            }
+        }
+    }
+}
+
+fn bad_request(err: BoxError) -> Response<ResponseBody> {
+    Response::builder()
+        .status(400)
+        .body(full(err.to_string().into()))
+        .unwrap()
+}
+
+fn payload_too_large(limit: u64) -> Response<ResponseBody> {
+    let body = format!(r#"{{"error":"payload too large","limit":{limit}}}"#);
+    Response::builder()
+        .status(413)
+        .header(CONTENT_TYPE, "application/json")
+        .body(full(body.into()))
+        .unwrap()
+}
+
+fn timed_out() -> Response<Bytes> {
+    Response::builder()
+        .status(503)
+        .header(CONTENT_TYPE, "application/json")
+        .body(Bytes::from_static(br#"{"error":"request timeout"}"#))
+        .unwrap()
+}
+
+fn not_found() -> Response<ResponseBody> {
     Response::builder()
         .status(404)
         .body(full("Not Found".into()))
         .unwrap()
 }
 
-fn full(bytes: Bytes) -> BoxBody<Bytes, hyper::Error> {
-    Full::new(bytes).map_err(|never| match never {}).boxed()
+fn method_not_allowed(allowed: &[Method]) -> Response<ResponseBody> {
+    Response::builder()
+        .status(405)
+        .header(ALLOW, allow(allowed))
+        .body(full("Method Not Allowed".into()))
+        .unwrap()
+}
+
+fn permanent_redirect(location: &str) -> Response<ResponseBody> {
+    Response::builder()
+        .status(308)
+        .header(LOCATION, location)
+        .body(full(Bytes::new()))
+        .unwrap()
+}
+
+fn options(allowed: &[Method]) -> Response<Bytes> {
+    Response::builder()
+        .status(204)
+        .header(ALLOW, allow(allowed))
+        .body(Bytes::new())
+        .unwrap()
+}
+
+fn allow(methods: &[Method]) -> String {
+    methods
+        .iter()
+        .map(Method::as_str)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Sends the stream that the handler responded with, if any, or the body
+/// of `resp`.
+fn into_body(mut resp: Response<Bytes>) -> Response<ResponseBody> {
+    match Streaming::take(&mut resp) {
+        Some(stream) => resp.map(|_| StreamingBody(stream).boxed_unsync()),
+        None => resp.map(full),
+    }
+}
+
+fn full(bytes: Bytes) -> ResponseBody {
+    Full::new(bytes)
+        .map_err(|never| match never {})
+        .boxed_unsync()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        middleware::{Logger, Middleware},
+        testing::MiddlewareOrderRecorder,
+    };
+
+    fn tunnel() -> &'static str {
+        "tunnel"
+    }
+
+    struct Echo;
+
+    impl routes::Handler for Echo {
+        fn handle_request(&self, req: crate::Request) -> Response<Bytes> {
+            Response::new(req.body().clone())
+        }
+    }
+
+    async fn send(router: &Router, req: Request<Full<Bytes>>) -> Response<Bytes> {
+        let res = router.respond(req).await.unwrap();
+        let (parts, body) = res.into_parts();
+        let body = body.collect().await.unwrap().to_bytes();
+        Response::from_parts(parts, body)
+    }
+
+    fn post(uri: &str, body: &'static str) -> Request<Full<Bytes>> {
+        Request::post(uri)
+            .body(Full::new(Bytes::from_static(body.as_bytes())))
+            .unwrap()
+    }
+
+    fn base64_decode(bytes: Bytes) -> Result<Bytes, BoxError> {
+        const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+        let mut decoded = Vec::new();
+        let (mut buffer, mut bits) = (0u32, 0);
+        for &byte in bytes.iter().filter(|&&byte| byte != b'=') {
+            let value = ALPHABET
+                .iter()
+                .position(|&x| x == byte)
+                .ok_or("invalid base64")?;
+            buffer = (buffer << 6) | value as u32;
+            bits += 6;
+            if bits >= 8 {
+                bits -= 8;
+                decoded.push((buffer >> bits) as u8);
+            }
+        }
+        Ok(decoded.into())
+    }
+
+    fn versioned() -> Response<Bytes> {
+        Response::builder()
+            .header("x-api-version", "0")
+            .body(Bytes::new())
+            .unwrap()
+    }
+
+    fn get(uri: &str) -> Request<Full<Bytes>> {
+        Request::get(uri).body(Full::default()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn more_specific_response_headers_win() {
+        let version = |v| http::HeaderValue::from_static(v);
+
+        let mut router = Router::new();
+        router.headers.insert("x-api-version", version("1"));
+        router.headers.insert("x-powered-by", version("genuine"));
+        router.groups[0]
+            .add(Method::GET, "/v1", tunnel as fn() -> _)
+            .unwrap();
+
+        let mut v2 = Group::new("/v2");
+        v2.response_header("x-api-version", version("2"));
+        v2.add(Method::GET, "/stable", tunnel as fn() -> _).unwrap();
+        v2.add(Method::GET, "/beta", tunnel as fn() -> _)
+            .unwrap()
+            .response_header("x-api-version", version("3"));
+        v2.add(Method::GET, "/own", versioned as fn() -> _).unwrap();
+        router.groups.push(v2);
+
+        for (uri, expected) in [
+            ("/v1", "1"),
+            ("/v2/stable", "2"),
+            ("/v2/beta", "3"),
+            ("/v2/own", "0"),
+        ] {
+            let res = send(&router, get(uri)).await;
+            assert_eq!(res.headers()["x-api-version"], expected, "{uri}");
+            assert_eq!(res.headers()["x-powered-by"], "genuine", "{uri}");
+        }
+    }
+
+    fn slow() -> &'static str {
+        std::thread::sleep(Duration::from_millis(200));
+        "slow"
+    }
+
+    #[tokio::test]
+    async fn group_timeout_applies_to_its_routes() {
+        let mut api = Group::new("/api");
+        api.request_timeout(Duration::from_millis(20));
+        api.add(Method::GET, "/slow", slow as fn() -> _).unwrap();
+        api.add(Method::GET, "/fast", tunnel as fn() -> _).unwrap();
+
+        let mut router = Router::new();
+        router.groups[0]
+            .add(Method::GET, "/slow", slow as fn() -> _)
+            .unwrap();
+        router.groups.push(api);
+
+        let res = send(&router, get("/api/slow")).await;
+        assert_eq!(res.status(), 503);
+        assert_eq!(res.body(), r#"{"error":"request timeout"}"#);
+        assert_eq!(send(&router, get("/api/fast")).await.status(), 200);
+        assert_eq!(send(&router, get("/slow")).await.status(), 200);
+    }
+
+    #[tokio::test]
+    async fn shortest_timeout_wins() {
+        let mut api = Group::new("/api");
+        api.request_timeout(Duration::from_secs(10));
+        api.add(Method::GET, "/slow", slow as fn() -> _)
+            .unwrap()
+            .timeout(Duration::from_millis(20));
+
+        let mut router = Router::new();
+        router.groups.push(api);
+
+        assert_eq!(send(&router, get("/api/slow")).await.status(), 503);
+    }
+
+    async fn sleepy(_: crate::Request) -> &'static str {
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        "sleepy"
+    }
+
+    async fn echo_async(req: crate::Request) -> Response<Bytes> {
+        tokio::task::yield_now().await;
+        Response::new(req.body().clone())
+    }
+
+    #[tokio::test]
+    async fn wrong_method_is_not_allowed() {
+        let mut router = Router::new();
+        router.groups[0]
+            .add(Method::GET, "/", tunnel as fn() -> _)
+            .unwrap();
+        router.groups[0]
+            .add(Method::POST, "/", tunnel as fn() -> _)
+            .unwrap();
+
+        let req = Request::delete("/").body(Full::default()).unwrap();
+        let res = send(&router, req).await;
+        assert_eq!(res.status(), 405);
+        assert_eq!(res.headers()[ALLOW], "GET, HEAD, OPTIONS, POST");
+    }
+
+    struct EchoMatches;
+
+    impl Handler for EchoMatches {
+        fn handle_request(&self, req: crate::Request) -> Response<Bytes> {
+            let values: Vec<_> = req.matches().iter().map(|m| m.value.as_str()).collect();
+            Response::new(values.join(",").into())
+        }
+    }
+
+    #[tokio::test]
+    async fn query_strings_are_not_matched() {
+        let mut router = Router::new();
+        router.groups[0]
+            .add(Method::GET, "/files/{name}", EchoMatches)
+            .unwrap();
+
+        let res = send(&router, get("/files/hello%20world?limit=10")).await;
+        assert_eq!(res.status(), 200);
+        assert_eq!(res.body(), "hello world");
+    }
+
+    /// Answers with the pattern of its route.
+    struct Pattern(&'static str);
+
+    impl Handler for Pattern {
+        fn handle_request(&self, _: crate::Request) -> Response<Bytes> {
+            Response::new(self.0.into())
+        }
+    }
+
+    #[tokio::test]
+    async fn most_specific_route_wins() {
+        let routes = [
+            ("", "/{*rest}"),
+            ("", "/{page}"),
+            ("/api", "/{id}"),
+            ("/api", "/me"),
+        ];
+
+        // register the groups and routes forwards and backwards
+        for reverse in [false, true] {
+            let mut router = Router::new();
+            router.groups.push(Group::new("/api"));
+            let mut ordered = routes.to_vec();
+            if reverse {
+                router.groups.reverse();
+                ordered.reverse();
+            }
+            for (prefix, pattern) in ordered {
+                let api = (prefix == "/api") != reverse;
+                router.groups[usize::from(api)]
+                    .add(Method::GET, pattern, Pattern(pattern))
+                    .unwrap();
+            }
+
+            for (uri, expected) in [
+                ("/api/me", "/me"),
+                ("/api/123", "/{id}"),
+                ("/about", "/{page}"),
+                ("/docs/intro", "/{*rest}"),
+            ] {
+                let res = send(&router, get(uri)).await;
+                assert_eq!(res.body(), expected, "{uri}, reversed: {reverse}");
+            }
+        }
+    }
+
+    fn missing(method: Method, uri: Uri) -> (http::StatusCode, String) {
+        (
+            http::StatusCode::NOT_FOUND,
+            format!("nothing at {method} {}", uri.path()),
+        )
+    }
+
+    #[tokio::test]
+    async fn fallback_handlers_answer_requests_without_route() {
+        let mut router = Router::new();
+        router.groups[0]
+            .add(Method::GET, "/tunnel", tunnel as fn() -> _)
+            .unwrap();
+        router.not_found = Some(Arc::new(missing as fn(_, _) -> _));
+        router.method_not_allowed = Some(Arc::new(missing as fn(_, _) -> _));
+        router.headers.insert("x-app", "genuine".parse().unwrap());
+
+        let res = send(&router, get("/nowhere")).await;
+        assert_eq!(res.status(), 404);
+        assert_eq!(res.body(), "nothing at GET /nowhere");
+        assert_eq!(res.headers()["x-app"], "genuine");
+
+        let req = Request::delete("/tunnel").body(Full::default()).unwrap();
+        let res = send(&router, req).await;
+        assert_eq!(res.status(), 404);
+        assert_eq!(res.body(), "nothing at DELETE /tunnel");
+        assert_eq!(res.headers()[ALLOW], "GET, HEAD, OPTIONS");
+    }
+
+    fn explicit_head() -> Response<Bytes> {
+        Response::builder()
+            .header("x-head", "explicit")
+            .body(Bytes::new())
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn head_is_answered_by_get_routes() {
+        let mut router = Router::new();
+        router.groups[0]
+            .add(Method::GET, "/tunnel", tunnel as fn() -> _)
+            .unwrap();
+        router.groups[0]
+            .add(Method::GET, "/own", tunnel as fn() -> _)
+            .unwrap();
+        router.groups[0]
+            .head("/own", explicit_head as fn() -> _)
+            .unwrap();
+
+        let body = send(&router, get("/tunnel")).await.into_body();
+
+        let req = Request::head("/tunnel").body(Full::default()).unwrap();
+        let res = send(&router, req).await;
+        assert_eq!(res.status(), 200);
+        assert_eq!(res.headers()[CONTENT_LENGTH], body.len().to_string());
+        assert!(res.body().is_empty());
+
+        let req = Request::head("/own").body(Full::default()).unwrap();
+        let res = send(&router, req).await;
+        assert_eq!(res.headers()["x-head"], "explicit");
+    }
+
+    fn countdown() -> Response<crate::body::BodyStream> {
+        let chunks = ["3 ", "2 ", "1"].map(|chunk| Ok(Bytes::from(chunk)));
+        Response::new(crate::body::BodyStream::new(futures::stream::iter(chunks)))
+    }
+
+    #[tokio::test]
+    async fn streamed_bodies_are_sent_chunk_by_chunk() {
+        let mut router = Router::new();
+        router.groups[0]
+            .add(Method::GET, "/countdown", countdown as fn() -> _)
+            .unwrap();
+
+        let res = router.respond(get("/countdown")).await.unwrap();
+        let mut body = res.into_body();
+        let mut chunks = Vec::new();
+        while let Some(frame) = body.frame().await {
+            chunks.push(frame.unwrap().into_data().unwrap());
+        }
+        assert_eq!(chunks, ["3 ", "2 ", "1"]);
+
+        let req = Request::head("/countdown").body(Full::default()).unwrap();
+        let res = send(&router, req).await;
+        assert_eq!(res.status(), 200);
+        assert!(!res.headers().contains_key(CONTENT_LENGTH));
+        assert!(res.body().is_empty());
+    }
+
+    #[tokio::test]
+    async fn options_are_answered_automatically() {
+        let mut router = Router::new();
+        router.groups[0]
+            .add(Method::GET, "/api/users", tunnel as fn() -> _)
+            .unwrap();
+
+        let options = || {
+            Request::options("/api/users")
+                .body(Full::default())
+                .unwrap()
+        };
+
+        let res = send(&router, options()).await;
+        assert_eq!(res.status(), 204);
+        assert_eq!(res.headers()[ALLOW], "GET, HEAD, OPTIONS");
+
+        router.auto_options = false;
+        let res = send(&router, options()).await;
+        assert_eq!(res.status(), 405);
+        assert_eq!(res.headers()[ALLOW], "GET, HEAD");
+    }
+
+    fn slash_router(policy: TrailingSlashPolicy) -> Router {
+        let mut router = Router::new();
+        router.trailing_slash = policy;
+        router.groups[0]
+            .add(Method::GET, "/users", tunnel as fn() -> _)
+            .unwrap();
+        router.groups[0]
+            .add(Method::GET, "/files/", tunnel as fn() -> _)
+            .unwrap();
+        router
+    }
+
+    #[tokio::test]
+    async fn trailing_slashes_are_ignored() {
+        let router = slash_router(TrailingSlashPolicy::Ignore);
+
+        assert_eq!(send(&router, get("/users/")).await.status(), 200);
+        assert_eq!(send(&router, get("/users")).await.status(), 200);
+        assert_eq!(send(&router, get("/files/")).await.status(), 200);
+        assert_eq!(send(&router, get("/files")).await.status(), 200);
+    }
+
+    #[tokio::test]
+    async fn group_roots_match_with_and_without_slash() {
+        let mut router = Router::new();
+        router.groups.push(Group::new("/app"));
+        router.groups[1]
+            .add(Method::GET, "/", tunnel as fn() -> _)
+            .unwrap();
+
+        assert_eq!(send(&router, get("/app")).await.status(), 200);
+        assert_eq!(send(&router, get("/app/")).await.status(), 200);
+        assert_eq!(send(&router, get("/")).await.status(), 404);
+    }
+
+    #[tokio::test]
+    async fn group_root_is_the_prefix() {
+        let mut router = Router::new();
+        router.trailing_slash = TrailingSlashPolicy::Strict;
+        router.groups.push(Group::new("/app"));
+        router.groups[1]
+            .add(Method::GET, "/", tunnel as fn() -> _)
+            .unwrap();
+
+        assert_eq!(send(&router, get("/app")).await.status(), 200);
+        assert_eq!(send(&router, get("/app/")).await.status(), 404);
+    }
+
+    #[tokio::test]
+    async fn trailing_slashes_are_redirected() {
+        let router = slash_router(TrailingSlashPolicy::Redirect);
+
+        let res = send(&router, get("/users/?page=2")).await;
+        assert_eq!(res.status(), 308);
+        assert_eq!(res.headers()[LOCATION], "/users?page=2");
+
+        assert_eq!(send(&router, get("/users")).await.status(), 200);
+        assert_eq!(send(&router, get("/files/")).await.status(), 200);
+        assert_eq!(send(&router, get("/")).await.status(), 404);
+    }
+
+    #[tokio::test]
+    async fn trailing_slashes_are_strict() {
+        let router = slash_router(TrailingSlashPolicy::Strict);
+
+        assert_eq!(send(&router, get("/files/")).await.status(), 200);
+        assert_eq!(send(&router, get("/files")).await.status(), 404);
+        assert_eq!(send(&router, get("/users")).await.status(), 200);
+    }
+
+    #[tokio::test]
+    async fn async_handlers_are_awaited() {
+        let mut router = Router::new();
+        router.groups[0]
+            .add_async(Method::POST, "/echo", echo_async)
+            .unwrap();
+        router.groups[0]
+            .add(Method::GET, "/tunnel", tunnel as fn() -> _)
+            .unwrap();
+
+        let res = send(&router, post("/echo", "hello")).await;
+        assert_eq!(res.body(), "hello");
+
+        let res = send(&router, get("/tunnel")).await;
+        assert_eq!(res.body(), "tunnel");
+    }
+
+    #[tokio::test]
+    async fn async_handlers_time_out() {
+        let mut router = Router::new();
+        router.groups[0]
+            .add_async(Method::GET, "/sleepy", sleepy)
+            .unwrap()
+            .timeout(Duration::from_millis(20));
+
+        assert_eq!(send(&router, get("/sleepy")).await.status(), 503);
+    }
+
+    fn show() -> &'static str {
+        "show"
+    }
+
+    fn catch_all() -> &'static str {
+        "catch-all"
+    }
+
+    #[test]
+    fn wildcard_routes_have_lowest_priority() {
+        let mut router = Router::new();
+        router.groups[0]
+            .add(Method::GET, "/prefix/{*rest}", catch_all as fn() -> _)
+            .unwrap();
+        router.groups[0]
+            .add(Method::GET, "/prefix/{id}", show as fn() -> _)
+            .unwrap();
+
+        let uri = Uri::from_static("/prefix/42");
+        let (_, route, matches) = router.route(&uri, &Method::GET).unwrap();
+        assert_eq!(route.path.as_ref(), "/prefix/{id}");
+        assert_eq!(matches[0].value, "42");
+
+        let router = {
+            let mut router = Router::new();
+            router.groups[0]
+                .add(Method::GET, "/prefix/{*rest}", catch_all as fn() -> _)
+                .unwrap();
+            router
+        };
+        let uri = Uri::from_static("/prefix/a/b");
+        let (_, _, matches) = router.route(&uri, &Method::GET).unwrap();
+        assert_eq!(
+            matches,
+            [Match {
+                name: "rest".into(),
+                value: "a/b".into()
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn body_size_limit_can_be_configured_per_route() {
+        let mut router = Router::new();
+        router.max_body_size = 4;
+        router.groups[0].add(Method::POST, "/echo", Echo).unwrap();
+        router.groups[0]
+            .add(Method::POST, "/upload", Echo)
+            .unwrap()
+            .max_body_size(8);
+
+        let res = send(&router, post("/echo", "hello")).await;
+        assert_eq!(res.status(), 413);
+        assert_eq!(res.headers()[CONTENT_TYPE], "application/json");
+        assert_eq!(res.body(), r#"{"error":"payload too large","limit":4}"#);
+
+        let res = send(&router, post("/upload", "hello")).await;
+        assert_eq!(res.status(), 200);
+
+        let res = send(&router, post("/upload", "hello world")).await;
+        assert_eq!(res.body(), r#"{"error":"payload too large","limit":8}"#);
+    }
+
+    #[tokio::test]
+    async fn body_transform_runs_before_handler() {
+        let mut router = Router::new();
+        router.groups[0]
+            .add(Method::POST, "/echo", Echo)
+            .unwrap()
+            .transform_body(base64_decode);
+
+        let res = send(&router, post("/echo", "aGVsbG8gd29ybGQ=")).await;
+        assert_eq!(res.body(), "hello world");
+    }
+
+    #[tokio::test]
+    async fn body_transforms_compose_in_order() {
+        let mut router = Router::new();
+        router.groups[0]
+            .add(Method::POST, "/echo", Echo)
+            .unwrap()
+            .transform_body(base64_decode)
+            .transform_body(|bytes: Bytes| -> Result<Bytes, BoxError> {
+                Ok(bytes.to_ascii_uppercase().into())
+            });
+
+        let res = send(&router, post("/echo", "aGVsbG8=")).await;
+        assert_eq!(res.body(), "HELLO");
+    }
+
+    #[tokio::test]
+    async fn failing_body_transform_is_a_bad_request() {
+        let mut router = Router::new();
+        router.groups[0]
+            .add(Method::POST, "/echo", Echo)
+            .unwrap()
+            .transform_body(base64_decode);
+
+        let res = send(&router, post("/echo", "not base64!")).await;
+        assert_eq!(res.status(), 400);
+        assert_eq!(res.body(), "invalid base64");
+    }
+
+    fn router_with_connect_route(pattern: &str) -> Router {
+        let mut router = Router::new();
+        router.groups[0]
+            .add(Method::CONNECT, &format!("/{pattern}"), tunnel as fn() -> _)
+            .unwrap();
+        router
+    }
+
+    #[test]
+    fn connect_is_routed_by_authority() {
+        let router = router_with_connect_route("example.com:443");
+
+        let uri = Uri::from_static("example.com:443");
+        assert!(router.route(&uri, &Method::CONNECT).is_some());
+
+        let uri = Uri::from_static("example.org:443");
+        assert!(router.route(&uri, &Method::CONNECT).is_none());
+    }
+
+    #[test]
+    fn connect_authority_can_be_captured() {
+        let router = router_with_connect_route("{authority}");
+
+        let uri = Uri::from_static("example.com:8080");
+        let (_, _, matches) = router.route(&uri, &Method::CONNECT).unwrap();
+
+        assert_eq!(
+            matches,
+            [Match {
+                name: "authority".into(),
+                value: "example.com:8080".into()
+            }]
+        );
+    }
+
+    fn traced() -> &'static str {
+        tracing::info!("inside handler");
+        "traced"
+    }
+
+    #[tokio::test]
+    #[tracing_test::traced_test]
+    async fn handler_events_are_within_the_request_span() {
+        let mut router = Router::new();
+        router.groups[0]
+            .add(Method::GET, "/traced", traced as fn() -> _)
+            .unwrap();
+
+        let req = Request::get("/traced")
+            .header(X_REQUEST_ID, "abc123")
+            .body(Full::default())
+            .unwrap();
+        send(&router, req).await;
+
+        assert!(logs_contain(
+            r#"request{method=GET path="/traced" request_id="abc123"}: genuine::router::tests: inside handler"#
+        ));
+        assert!(logs_contain("sent response status=200"));
+    }
+
+    #[tokio::test]
+    #[tracing_test::traced_test]
+    async fn handler_events_stay_within_the_span_with_a_timeout() {
+        let mut router = Router::new();
+        router.groups[0]
+            .add(Method::GET, "/traced", traced as fn() -> _)
+            .unwrap()
+            .timeout(Duration::from_secs(1));
+
+        send(&router, get("/traced")).await;
+
+        assert!(logs_contain(
+            r#"request{method=GET path="/traced"}: genuine::router::tests: inside handler"#
+        ));
+    }
+
+    struct Recorded(Arc<std::sync::Mutex<Vec<String>>>);
+
+    impl routes::Handler for Recorded {
+        fn handle_request(&self, _: crate::Request) -> Response<Bytes> {
+            self.0.lock().unwrap().push("handler".into());
+            Response::new(Bytes::new())
+        }
+    }
+
+    #[tokio::test]
+    async fn middlewares_nest_around_the_handler() {
+        let outer = MiddlewareOrderRecorder::new("outer");
+        let first = outer.named("first");
+        let second = outer.named("second");
+
+        let mut router = Router::new();
+        let group = &mut router.groups[0];
+        group.before.push(Box::new(outer.clone()));
+        group.after.push(Box::new(outer.clone()));
+        group
+            .add(Method::GET, "/", Recorded(outer.shared()))
+            .unwrap()
+            .before(first.clone())
+            .before(second.clone())
+            .after(first)
+            .after(second);
+
+        send(&router, get("/")).await;
+
+        assert_eq!(
+            outer.calls(),
+            ["outer", "first", "second", "handler", "second", "first", "outer"]
+        );
+    }
+
+    #[tokio::test]
+    async fn route_middleware_wraps_only_its_route() {
+        let recorder = MiddlewareOrderRecorder::new("recorder");
+
+        let mut router = Router::new();
+        let group = &mut router.groups[0];
+        group
+            .add(Method::GET, "/admin", Recorded(recorder.shared()))
+            .unwrap()
+            .middleware(recorder.clone());
+        group
+            .add(Method::GET, "/public", Recorded(recorder.shared()))
+            .unwrap();
+
+        send(&router, get("/admin")).await;
+        send(&router, get("/public")).await;
+
+        assert_eq!(
+            recorder.calls(),
+            ["recorder", "handler", "recorder", "handler"]
+        );
+    }
+
+    #[tokio::test]
+    async fn nested_routers_keep_their_middlewares_and_headers() {
+        let recorder = MiddlewareOrderRecorder::new("nested");
+
+        let mut v1 = Router::new();
+        v1.before.push(Box::new(recorder.clone()));
+        v1.after.push(Box::new(recorder.clone()));
+        v1.headers
+            .insert("x-api-version", http::HeaderValue::from_static("1"));
+        v1.groups[0]
+            .add(Method::GET, "/users", Recorded(recorder.shared()))
+            .unwrap();
+        let mut admin = Group::new("/admin");
+        admin
+            .add(Method::GET, "/stats", Recorded(recorder.shared()))
+            .unwrap()
+            .before(recorder.named("route"));
+        v1.groups.push(admin);
+
+        let mut router = Router::new();
+        router.groups[0]
+            .add(Method::GET, "/users", Recorded(recorder.shared()))
+            .unwrap();
+        router.nest("/v1/", v1).unwrap();
+
+        let res = send(&router, get("/v1/users")).await;
+        assert_eq!(res.headers()["x-api-version"], "1");
+        assert_eq!(recorder.calls(), ["nested", "handler", "nested"]);
+
+        send(&router, get("/v1/admin/stats")).await;
+        assert_eq!(
+            recorder.calls()[3..],
+            ["nested", "route", "handler", "nested"]
+        );
+
+        let res = send(&router, get("/users")).await;
+        assert!(!res.headers().contains_key("x-api-version"));
+        assert_eq!(recorder.calls()[7..], ["handler"]);
+    }
+
+    #[tokio::test]
+    async fn nested_root_is_the_prefix() {
+        let mut v1 = Router::new();
+        v1.groups[0]
+            .add(Method::GET, "/", tunnel as fn() -> _)
+            .unwrap();
+
+        let mut router = Router::new();
+        router.nest("/v1", v1).unwrap();
+
+        assert_eq!(router.routes().next().unwrap().path.as_ref(), "/v1");
+        assert_eq!(send(&router, get("/v1")).await.status(), 200);
+        assert_eq!(send(&router, get("/v1/")).await.status(), 200);
+    }
+
+    #[test]
+    fn nested_routes_must_not_conflict() {
+        let mut router = Router::new();
+        router.groups.push(Group::new("/v1"));
+        router.groups[1]
+            .add(Method::GET, "/users/{id}", (|| "user") as fn() -> _)
+            .unwrap();
+
+        let mut v1 = Router::new();
+        v1.groups[0]
+            .add(Method::GET, "/users/{name}", (|| "user") as fn() -> _)
+            .unwrap();
+
+        let err = router.nest("/v1", v1).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "route `GET /v1/users/{name}` conflicts with `GET /v1/users/{id}`"
+        );
+        assert!(router.nest("v1", Router::new()).is_err());
+    }
+
+    struct EchoMethod;
+
+    impl routes::Handler for EchoMethod {
+        fn handle_request(&self, req: crate::Request) -> Response<Bytes> {
+            Response::new(req.method().as_str().to_owned().into())
+        }
+    }
+
+    #[tokio::test]
+    async fn any_routes_handle_all_methods() {
+        let mut router = Router::new();
+        router.groups[0].any("/echo", EchoMethod).unwrap();
+        router.groups[0]
+            .add(Method::GET, "/echo", (|| "get route") as fn() -> _)
+            .unwrap();
+
+        for method in ["PATCH", "DELETE", "PURGE"] {
+            let req = Request::builder()
+                .method(method)
+                .uri("/echo")
+                .body(Full::default())
+                .unwrap();
+            let res = send(&router, req).await;
+            assert_eq!(res.status(), 200);
+            assert_eq!(res.body(), method);
+        }
+
+        assert_eq!(send(&router, get("/echo")).await.body(), "get route");
+    }
+
+    struct Deny;
+
+    impl Middleware for Deny {
+        fn before(&self, req: &mut Request<Bytes>) -> Option<Response<Bytes>> {
+            let authorized = req.headers().contains_key("authorization");
+            (!authorized).then(|| {
+                Response::builder()
+                    .status(401)
+                    .body(Bytes::from_static(b"Unauthorized"))
+                    .unwrap()
+            })
+        }
+    }
+
+    #[tokio::test]
+    #[tracing_test::traced_test]
+    async fn before_middleware_can_respond_early() {
+        let recorder = MiddlewareOrderRecorder::new("recorder");
+
+        let mut router = Router::new();
+        router.before.push(Box::new(Logger::new()));
+        router.after.push(Box::new(Logger::new()));
+        router.groups[0]
+            .add(Method::GET, "/", Recorded(recorder.shared()))
+            .unwrap()
+            .before(Deny)
+            .before(recorder.clone())
+            .after(recorder.clone());
+
+        let res = send(&router, get("/")).await;
+        assert_eq!(res.status(), 401);
+        assert_eq!(recorder.calls(), ["recorder"]);
+        assert!(logs_contain(r#""GET / HTTP/1.1" 401 12 "-" "-""#));
+        assert!(logs_contain("status=401"));
+
+        let req = Request::get("/")
+            .header("authorization", "Bearer secret")
+            .body(Full::default())
+            .unwrap();
+        let res = send(&router, req).await;
+        assert_eq!(res.status(), 200);
+        assert_eq!(
+            recorder.calls(),
+            ["recorder", "recorder", "handler", "recorder"]
+        );
+    }
 }
@@ -1,12 +1,24 @@
-use std::fmt;
+use std::{fmt, future::Future, pin::Pin, sync::Arc};
 
-use http::Response;
+use http::{HeaderMap, Response, StatusCode};
 use hyper::body::Bytes;
 
-use crate::request::Request;
+use crate::{
+    body::{BodyStream, Streaming},
+    request::Request,
+    response::ResponseChain,
+};
 
 pub trait IntoResponse {
     fn into_response(self) -> Response<Bytes>;
+
+    /// Start a [`ResponseChain`] to transform the response step by step.
+    fn pipeline(self) -> ResponseChain
+    where
+        Self: Sized,
+    {
+        ResponseChain::new(self)
+    }
 }
 
 impl<B: Into<Bytes>> IntoResponse for Response<B> {
@@ -15,6 +27,16 @@ impl<B: Into<Bytes>> IntoResponse for Response<B> {
     }
 }
 
+/// Streams the body chunk by chunk, see [`BodyStream`].
+impl IntoResponse for Response<BodyStream> {
+    fn into_response(self) -> Response<Bytes> {
+        let (parts, stream) = self.into_parts();
+        let mut res = Response::from_parts(parts, Bytes::new());
+        Streaming::attach(&mut res, stream);
+        res
+    }
+}
+
 impl IntoResponse for String {
     fn into_response(self) -> Response<Bytes> {
         let bytes = Bytes::from(self);
@@ -29,36 +51,631 @@ impl IntoResponse for &'static str {
     }
 }
 
+/// Responds with `T` on success and with `E` on failure, see [`Handler`].
+impl<T, E> IntoResponse for Result<T, E>
+where
+    T: IntoResponse,
+    E: IntoResponse,
+{
+    fn into_response(self) -> Response<Bytes> {
+        match self {
+            Ok(res) => res.into_response(),
+            Err(err) => err.into_response(),
+        }
+    }
+}
+
+/// Sets the status of the response.
+impl<T: IntoResponse> IntoResponse for (StatusCode, T) {
+    fn into_response(self) -> Response<Bytes> {
+        let (status, res) = self;
+        let mut res = res.into_response();
+        *res.status_mut() = status;
+        res
+    }
+}
+
+/// Sets the status of the response, and the headers, replacing headers of the
+/// same name that `T` set.
+impl<T: IntoResponse> IntoResponse for (StatusCode, HeaderMap, T) {
+    fn into_response(self) -> Response<Bytes> {
+        let (status, headers, res) = self;
+        let mut res = (status, res).into_response();
+        res.headers_mut().extend(headers);
+        res
+    }
+}
+
+/// Turns a request into a response.
+///
+/// Handlers are stored as boxed trait objects inside the router, which is
+/// shared by every connection the server accepts. That is what the bounds are
+/// for:
+///
+/// - `Sync`, because all connection tasks call the handler through a shared
+///   reference to the same router.
+/// - `Send`, because the router is moved to the Tokio runtime, whose worker
+///   threads run the connection tasks.
+/// - `'static`, because the router must outlive every spawned task, so it
+///   cannot borrow anything with a shorter lifetime.
+///
+/// # Fallible handlers
+///
+/// Functions can return a [`Result`] of two [`IntoResponse`] types, and use
+/// `?` to bail out. The request is answered with whichever of the two the
+/// handler returned. An `Err` response sets its own status, e.g. `400` or
+/// `500`, while an `Ok` response is `200 OK` unless it sets another one, e.g.
+/// with a `(StatusCode, T)` tuple.
+///
+/// ```
+/// use genuine::App;
+/// use http::StatusCode;
+///
+/// fn create_order(quantity: String) -> Result<(StatusCode, String), (StatusCode, String)> {
+///     let quantity: u32 = quantity
+///         .trim()
+///         .parse()
+///         .map_err(|err| (StatusCode::BAD_REQUEST, format!("invalid quantity: {err}")))?;
+///     Ok((StatusCode::CREATED, format!("ordered {quantity} items")))
+/// }
+///
+/// let mut app = App::new();
+/// app.post("/orders", create_order as fn(_) -> _).unwrap();
+/// ```
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` is not a handler",
+    label = "the trait `Handler` is not implemented for `{Self}`",
+    note = "Handler requires Send + Sync because it is shared across Tokio tasks",
+    note = "functions must be cast to function pointers, e.g. `handler as fn(_) -> _`"
+)]
 pub trait Handler: Send + Sync + 'static {
     fn handle_request(&self, req: Request) -> Response<Bytes>;
 }
 
-pub trait FromRequest: Sized {
-    type Error: fmt::Debug;
+pub type BoxedFuture<T> = Pin<Box<dyn Future<Output = T> + Send + 'static>>;
 
-    fn from_request(req: &Request) -> Result<Self, Self::Error>;
+/// Asynchronous counterpart to [`Handler`], for handlers that need to do
+/// I/O, e.g. to query a database, without blocking a worker thread.
+///
+/// Async functions and closures taking a [`Request`] are async handlers:
+///
+/// ```
+/// use genuine::{Group, Request};
+/// use http::Method;
+///
+/// async fn hello(_: Request) -> &'static str {
+///     "Hello world!"
+/// }
+///
+/// let mut group = Group::new("/app");
+///
+/// group.add_async(Method::GET, "/", hello).unwrap();
+/// ```
+pub trait AsyncHandler: Send + Sync + 'static {
+    fn handle_request(&self, req: Request) -> BoxedFuture<Response<Bytes>>;
 }
 
-impl<R> Handler for fn() -> R
+impl<F, Fut, R> AsyncHandler for F
 where
-    Self: Send + 'static,
+    F: Fn(Request) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = R> + Send + 'static,
     R: IntoResponse,
 {
-    fn handle_request(&self, _: Request) -> Response<Bytes> {
-        self().into_response()
+    fn handle_request(&self, req: Request) -> BoxedFuture<Response<Bytes>> {
+        let res = self(req);
+        Box::pin(async move { res.await.into_response() })
+    }
+}
+
+/// A [`Handler`] or an [`AsyncHandler`].
+#[derive(Clone)]
+pub(crate) enum ErasedHandler {
+    Sync(Arc<dyn Handler>),
+    Async(Arc<dyn AsyncHandler>),
+}
+
+impl ErasedHandler {
+    pub(crate) async fn handle_request(&self, req: Request) -> Response<Bytes> {
+        match self {
+            Self::Sync(handler) => handler.handle_request(req),
+            Self::Async(handler) => handler.handle_request(req).await,
+        }
     }
 }
 
-impl<P1, P2, R> Handler for fn(P1, P2) -> R
+/// Creates a [`Handler`] from injected dependencies.
+///
+/// Factories are called once, when the route is registered with
+/// [`Group::add_factory`](crate::Group::add_factory). This is useful for
+/// handlers that need resources, like a database pool, which are not
+/// available from the request itself.
+pub trait HandlerFactory<S> {
+    fn create(&self, state: Arc<S>) -> impl Handler;
+}
+
+impl<S, F, H> HandlerFactory<S> for F
 where
-    Self: Send + 'static,
-    R: IntoResponse,
-    P1: FromRequest,
-    P2: FromRequest,
+    F: Fn(Arc<S>) -> H,
+    H: Handler,
 {
-    fn handle_request(&self, req: Request) -> Response<Bytes> {
-        let p1 = P1::from_request(&req).unwrap();
-        let p2 = P2::from_request(&req).unwrap();
-        self(p1, p2).into_response()
+    fn create(&self, state: Arc<S>) -> impl Handler {
+        self(state)
+    }
+}
+
+/// The error of an extractor, which answers the request instead of the
+/// handler.
+///
+/// By default, rejections answer `422 Unprocessable Entity` and are only
+/// logged, since their `Debug` output may show internals that clients should
+/// not see. Rejections that tell clients what went wrong override
+/// [`Rejection::respond`], e.g. `JsonRejection` answers `415 Unsupported
+/// Media Type` for a body that is not JSON.
+///
+/// # Examples
+///
+/// ```
+/// use genuine::{FromRequest, Rejection, Request};
+/// use http::{Response, StatusCode};
+/// use hyper::body::Bytes;
+///
+/// struct ApiKey(String);
+///
+/// #[derive(Debug)]
+/// struct MissingApiKey;
+///
+/// impl Rejection for MissingApiKey {
+///     fn respond(self) -> Response<Bytes> {
+///         Response::builder()
+///             .status(StatusCode::UNAUTHORIZED)
+///             .body(Bytes::from_static(b"missing API key"))
+///             .unwrap()
+///     }
+/// }
+///
+/// impl FromRequest for ApiKey {
+///     type Error = MissingApiKey;
+///
+///     fn from_request(req: &Request) -> Result<Self, Self::Error> {
+///         let key = req.headers().get("x-api-key").ok_or(MissingApiKey)?;
+///         let key = key.to_str().map_err(|_| MissingApiKey)?;
+///         Ok(ApiKey(key.to_owned()))
+///     }
+/// }
+/// ```
+pub trait Rejection: fmt::Debug {
+    /// Answers the request whose handler parameter could not be extracted.
+    fn respond(self) -> Response<Bytes>
+    where
+        Self: Sized,
+    {
+        tracing::debug!(error = ?self, "failed to extract handler parameter");
+
+        Response::builder()
+            .status(StatusCode::UNPROCESSABLE_ENTITY)
+            .body(Bytes::from_static(b"Unprocessable Entity"))
+            .unwrap()
+    }
+}
+
+impl Rejection for std::convert::Infallible {}
+
+pub trait FromRequest: Sized {
+    type Error: Rejection;
+
+    /// The number of path parameters this extractor takes by position, like
+    /// [`Param`](crate::Param) does.
+    const PATH_PARAMS: usize = 0;
+
+    fn from_request(req: &Request) -> Result<Self, Self::Error>;
+
+    /// Extracts a handler argument, after the arguments before it took the
+    /// first `taken` path parameters.
+    ///
+    /// Only extractors with [`PATH_PARAMS`](FromRequest::PATH_PARAMS) need
+    /// this, the default calls [`FromRequest::from_request`].
+    fn from_request_at(req: &Request, taken: usize) -> Result<Self, Self::Error> {
+        let _ = taken;
+        Self::from_request(req)
+    }
+}
+
+/// Asynchronous counterpart to [`FromRequest`], for extractors that need to
+/// do I/O, e.g. to validate a token against a database.
+///
+/// Every [`FromRequest`] extractor is also an [`AsyncFromRequest`] extractor
+/// whose future completes immediately.
+///
+/// Async functions whose parameters are [`AsyncFromRequest`] extractors are
+/// [`AsyncHandler`]s once cast to function pointers:
+///
+/// ```
+/// use genuine::{Group, Param};
+/// use http::Method;
+///
+/// async fn show_user(id: Param<u32>) -> String {
+///     format!("user #{}", *id)
+/// }
+///
+/// let mut group = Group::new("/users");
+///
+/// group
+///     .add_async(Method::GET, "/{id}", show_user as fn(_) -> _)
+///     .unwrap();
+/// ```
+pub trait AsyncFromRequest: Sized {
+    type Error: Rejection;
+
+    /// See [`FromRequest::PATH_PARAMS`].
+    const PATH_PARAMS: usize = 0;
+
+    fn from_request(req: &Request) -> impl Future<Output = Result<Self, Self::Error>> + Send;
+
+    /// See [`FromRequest::from_request_at`].
+    fn from_request_at(
+        req: &Request,
+        taken: usize,
+    ) -> impl Future<Output = Result<Self, Self::Error>> + Send {
+        let _ = taken;
+        Self::from_request(req)
+    }
+}
+
+impl<T> AsyncFromRequest for T
+where
+    T: FromRequest + Send,
+    T::Error: Send,
+{
+    type Error = T::Error;
+
+    const PATH_PARAMS: usize = T::PATH_PARAMS;
+
+    fn from_request(req: &Request) -> impl Future<Output = Result<Self, Self::Error>> + Send {
+        std::future::ready(<T as FromRequest>::from_request(req))
+    }
+
+    fn from_request_at(
+        req: &Request,
+        taken: usize,
+    ) -> impl Future<Output = Result<Self, Self::Error>> + Send {
+        std::future::ready(<T as FromRequest>::from_request_at(req, taken))
+    }
+}
+
+/// Implements [`Handler`] for functions with the given parameters, which are
+/// extracted from the request in order, and [`AsyncHandler`] for async
+/// functions with the given [`AsyncFromRequest`] parameters.
+///
+/// If a parameter cannot be extracted, the handler is not called and the
+/// request is answered by the [`Rejection`] of the extractor.
+macro_rules! impl_handler {
+    ($($param:ident),*) => {
+        impl<$($param,)* R> Handler for fn($($param),*) -> R
+        where
+            Self: Send + 'static,
+            R: IntoResponse,
+            $($param: FromRequest,)*
+        {
+            #[allow(non_snake_case, unused_variables)]
+            fn handle_request(&self, req: Request) -> Response<Bytes> {
+                let taken = 0;
+                $(
+                    let $param = match $param::from_request_at(&req, taken) {
+                        Ok(param) => param,
+                        Err(err) => return err.respond(),
+                    };
+                    let taken = taken + <$param as FromRequest>::PATH_PARAMS;
+                )*
+                self($($param),*).into_response()
+            }
+        }
+
+        impl<$($param,)* Fut> AsyncHandler for fn($($param),*) -> Fut
+        where
+            Self: Send + Sync + 'static,
+            Fut: Future + Send + 'static,
+            Fut::Output: IntoResponse,
+            $($param: AsyncFromRequest + Send,)*
+        {
+            #[allow(non_snake_case, unused_variables)]
+            fn handle_request(&self, req: Request) -> BoxedFuture<Response<Bytes>> {
+                let handler = *self;
+                Box::pin(async move {
+                    let taken = 0;
+                    $(
+                        let $param = match $param::from_request_at(&req, taken).await {
+                            Ok(param) => param,
+                            Err(err) => return err.respond(),
+                        };
+                        let taken = taken + <$param as AsyncFromRequest>::PATH_PARAMS;
+                    )*
+                    handler($($param),*).await.into_response()
+                })
+            }
+        }
+    };
+}
+
+impl_handler!();
+impl_handler!(P1);
+impl_handler!(P1, P2);
+impl_handler!(P1, P2, P3);
+impl_handler!(P1, P2, P3, P4);
+impl_handler!(P1, P2, P3, P4, P5);
+impl_handler!(P1, P2, P3, P4, P5, P6);
+impl_handler!(P1, P2, P3, P4, P5, P6, P7);
+impl_handler!(P1, P2, P3, P4, P5, P6, P7, P8);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Db {
+        users: Vec<&'static str>,
+    }
+
+    struct ListUsers {
+        db: Arc<Db>,
+    }
+
+    impl Handler for ListUsers {
+        fn handle_request(&self, _: Request) -> Response<Bytes> {
+            self.db.users.join(", ").into_response()
+        }
+    }
+
+    fn empty_request() -> Request {
+        Request::new(http::Request::new(Bytes::new()), Vec::new())
+    }
+
+    fn request_with_token(token: &'static str) -> Request {
+        let req = http::Request::builder()
+            .header("x-token", token)
+            .body(Bytes::new())
+            .unwrap();
+        Request::new(req, Vec::new())
+    }
+
+    #[derive(Debug, PartialEq)]
+    struct InvalidToken(&'static str);
+
+    impl Rejection for InvalidToken {}
+
+    #[derive(Debug, PartialEq)]
+    struct Token(String);
+
+    impl FromRequest for Token {
+        type Error = InvalidToken;
+
+        fn from_request(req: &Request) -> Result<Self, Self::Error> {
+            let token = req.headers().get("x-token");
+            let token = token.ok_or(InvalidToken("missing token"))?;
+            let token = token.to_str().map_err(|_| InvalidToken("invalid token"))?;
+            Ok(Token(token.to_owned()))
+        }
+    }
+
+    #[derive(Debug, PartialEq)]
+    struct User(&'static str);
+
+    impl AsyncFromRequest for User {
+        type Error = InvalidToken;
+
+        async fn from_request(req: &Request) -> Result<Self, Self::Error> {
+            let Token(token) = <Token as FromRequest>::from_request(req)?;
+
+            // pretend to look the token up in a database
+            tokio::task::yield_now().await;
+
+            match token.as_str() {
+                "secret" => Ok(User("alice")),
+                _ => Err(InvalidToken("unknown token")),
+            }
+        }
+    }
+
+    #[derive(Debug)]
+    struct Anything;
+
+    impl FromRequest for Anything {
+        type Error = std::convert::Infallible;
+
+        fn from_request(_: &Request) -> Result<Self, Self::Error> {
+            Ok(Anything)
+        }
+    }
+
+    #[test]
+    fn functions_with_up_to_eight_params_are_handlers() {
+        fn handler(_: &dyn Handler) {}
+
+        type A = Anything;
+        handler(&((|| "") as fn() -> _));
+        handler(&((|_: A| "") as fn(_) -> _));
+        handler(&((|_: A, _: A, _: A| "") as fn(_, _, _) -> _));
+        handler(&((|_: A, _: A, _: A, _: A, _: A| "") as fn(_, _, _, _, _) -> _));
+        handler(
+            &((|_: A, _: A, _: A, _: A, _: A, _: A, _: A, _: A| "")
+                as fn(_, _, _, _, _, _, _, _) -> _),
+        );
+    }
+
+    #[test]
+    fn failed_extraction_is_rejected() {
+        let handler = (|Token(token): Token, _: Anything| token) as fn(_, _) -> _;
+
+        let res = handler.handle_request(request_with_token("secret"));
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.body(), "secret");
+
+        let res = handler.handle_request(empty_request());
+        assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(res.body(), "Unprocessable Entity");
+    }
+
+    #[derive(Debug)]
+    struct NoCoffee;
+
+    impl Rejection for NoCoffee {
+        fn respond(self) -> Response<Bytes> {
+            (StatusCode::IM_A_TEAPOT, "no coffee").into_response()
+        }
+    }
+
+    struct Coffee;
+
+    impl FromRequest for Coffee {
+        type Error = NoCoffee;
+
+        fn from_request(_: &Request) -> Result<Self, Self::Error> {
+            Err(NoCoffee)
+        }
+    }
+
+    #[tokio::test]
+    async fn rejections_answer_with_their_own_response() {
+        let handler = (|_: Coffee| "brewed") as fn(_) -> _;
+        let res = Handler::handle_request(&handler, empty_request());
+        assert_eq!(res.status(), StatusCode::IM_A_TEAPOT);
+        assert_eq!(res.body(), "no coffee");
+
+        let handler = (|_: Coffee| async { "brewed" }) as fn(_) -> _;
+        let res = AsyncHandler::handle_request(&handler, empty_request()).await;
+        assert_eq!(res.status(), StatusCode::IM_A_TEAPOT);
+    }
+
+    fn divide(Token(token): Token) -> Result<(StatusCode, String), (StatusCode, &'static str)> {
+        let divisor: u32 = token
+            .parse()
+            .map_err(|_| (StatusCode::BAD_REQUEST, "not a number"))?;
+        match 100u32.checked_div(divisor) {
+            Some(1) => Ok((StatusCode::CREATED, "1".to_owned())),
+            Some(quotient) => Ok((StatusCode::OK, quotient.to_string())),
+            None => Err((StatusCode::INTERNAL_SERVER_ERROR, "division by zero")),
+        }
+    }
+
+    #[test]
+    fn fallible_handlers_respond_with_either_branch() {
+        let handler = divide as fn(_) -> _;
+        let respond = |token| Handler::handle_request(&handler, request_with_token(token));
+
+        let res = respond("4");
+        assert_eq!(
+            (res.status(), res.body().as_ref()),
+            (StatusCode::OK, &b"25"[..])
+        );
+        let res = respond("100");
+        assert_eq!(
+            (res.status(), res.body().as_ref()),
+            (StatusCode::CREATED, &b"1"[..])
+        );
+
+        let res = respond("four");
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(res.body(), "not a number");
+        let res = respond("0");
+        assert_eq!(res.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(res.body(), "division by zero");
+    }
+
+    #[test]
+    fn status_tuple_sets_status() {
+        let res = (StatusCode::CREATED, "created").into_response();
+        assert_eq!(res.status(), StatusCode::CREATED);
+        assert_eq!(res.body(), "created");
+    }
+
+    #[test]
+    fn status_headers_tuple_composes() {
+        let inner = Response::builder()
+            .status(StatusCode::OK)
+            .header("content-type", "text/plain")
+            .header("x-inner", "kept")
+            .body("{}")
+            .unwrap();
+        let mut headers = HeaderMap::new();
+        headers.insert("content-type", "application/json".parse().unwrap());
+        headers.insert("location", "/users/7".parse().unwrap());
+
+        let res = (StatusCode::CREATED, headers, inner).into_response();
+
+        assert_eq!(res.status(), StatusCode::CREATED);
+        assert_eq!(res.headers()["content-type"], "application/json");
+        assert_eq!(res.headers().get_all("content-type").iter().count(), 1);
+        assert_eq!(res.headers()["location"], "/users/7");
+        assert_eq!(res.headers()["x-inner"], "kept");
+        assert_eq!(res.body(), "{}");
+    }
+
+    #[tokio::test]
+    async fn sync_extractors_are_async_extractors() {
+        let req = request_with_token("secret");
+        let token = <Token as AsyncFromRequest>::from_request(&req).await;
+        assert_eq!(token, Ok(Token("secret".into())));
+    }
+
+    async fn greet(User(name): User, id: crate::Param<u32>) -> String {
+        format!("{name} #{}", *id)
+    }
+
+    #[tokio::test]
+    async fn async_handlers_await_their_extractors() {
+        let handler = greet as fn(_, _) -> _;
+        let request = |token| {
+            let id = crate::router::routes::Match {
+                name: "id".into(),
+                value: "7".into(),
+            };
+            Request::new(request_with_token(token).into_inner(), vec![id])
+        };
+
+        let res = AsyncHandler::handle_request(&handler, request("secret")).await;
+        assert_eq!(res.body(), "alice #7");
+
+        let res = AsyncHandler::handle_request(&handler, request("guess")).await;
+        assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    }
+
+    #[tokio::test]
+    async fn async_extractor_can_await() {
+        let req = request_with_token("secret");
+        assert_eq!(User::from_request(&req).await, Ok(User("alice")));
+
+        let req = request_with_token("guess");
+        assert_eq!(
+            User::from_request(&req).await,
+            Err(InvalidToken("unknown token"))
+        );
+    }
+
+    #[test]
+    fn factory_injects_state_into_handler() {
+        let db = Arc::new(Db {
+            users: vec!["alice", "bob"],
+        });
+
+        let factory = |db| ListUsers { db };
+        let handler = factory.create(Arc::clone(&db));
+
+        let res = handler.handle_request(empty_request());
+        assert_eq!(res.body(), "alice, bob");
+        assert_eq!(Arc::strong_count(&db), 2);
+    }
+
+    #[tokio::test]
+    async fn group_calls_factory_at_registration() {
+        let db = Arc::new(Db {
+            users: vec!["carol"],
+        });
+        let mut group = crate::Group::new("/api");
+
+        let route = group
+            .add_factory(http::Method::GET, "/users", &db, |db| ListUsers { db })
+            .unwrap();
+
+        assert_eq!(Arc::strong_count(&db), 2);
+        let res = route.handler.handle_request(empty_request()).await;
+        assert_eq!(res.body(), "carol");
     }
 }
@@ -1,24 +1,69 @@
 mod handler;
 mod paths;
+mod transform;
 
-use std::fmt;
+use std::{fmt, sync::Arc, time::Duration};
 
-use http::Method;
+use http::{header::IntoHeaderName, HeaderMap, HeaderValue, Method};
 
-use super::middleware::BoxedMiddleware;
+use crate::{
+    middleware::{BoxedMiddleware, Middleware},
+    Request,
+};
 
+pub(crate) use self::handler::ErasedHandler;
 pub use self::{
-    handler::{FromRequest, Handler, IntoResponse},
-    params::Param,
-    paths::{Match, Path},
+    handler::{
+        AsyncFromRequest, AsyncHandler, BoxedFuture, FromRequest, Handler, HandlerFactory,
+        IntoResponse, Rejection,
+    },
+    params::{Named, NamedParamError, Param, ParamExtractError, ParamName},
+    paths::{Match, ParseError, ParseErrorKind, Part, Path},
+    transform::{BodyTransform, BoxError},
 };
 
+/// The method of routes that handle all methods, see
+/// [`Group::any`](crate::Group::any).
+pub(crate) fn any_method() -> Method {
+    Method::from_bytes(b"ANY").unwrap()
+}
+
 pub struct Route {
-    pub(super) path: Path,
+    pub(crate) path: Path,
     method: Method,
-    pub(crate) handler: Box<dyn Handler>,
+    pub(crate) handler: ErasedHandler,
     pub(crate) before: Vec<BoxedMiddleware>,
     pub(crate) after: Vec<BoxedMiddleware>,
+    pub(crate) transforms: Vec<Box<dyn BodyTransform>>,
+    pub(crate) headers: HeaderMap,
+    pub(crate) timeout: Option<Duration>,
+    pub(crate) cache_key: Option<fn(&Request) -> String>,
+    pub(crate) max_body_size: Option<u64>,
+    pub(crate) stream_body: bool,
+    pub(crate) name: Option<String>,
+    pub(crate) description: Option<String>,
+}
+
+/// Describes a registered route, see [`App::routes`](crate::App::routes).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RouteInfo<'a> {
+    pub method: &'a Method,
+    /// The full path of the route, including the group prefix.
+    pub path: &'a str,
+    pub group_prefix: &'a str,
+    pub name: Option<&'a str>,
+}
+
+/// Formats the route as a row of a routing table, e.g.
+/// `GET     /users/{id}  (user)`.
+impl fmt::Display for RouteInfo<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:<7} {}", self.method.as_str(), self.path)?;
+        if let Some(name) = self.name {
+            write!(f, "  ({name})")?;
+        }
+        Ok(())
+    }
 }
 
 impl fmt::Display for Route {
@@ -32,32 +77,215 @@ impl fmt::Debug for Route {
         fmt::Display::fmt(self, f)?;
         write!(
             f,
-            " {{ handler: ..., before: [...; {}], after: [...; {}] }}",
+            " {{ handler: ..., before: [...; {}], after: [...; {}], name: {:?}, description: {:?} }}",
             self.before.len(),
-            self.after.len()
+            self.after.len(),
+            self.name,
+            self.description,
         )
     }
 }
 
 impl Route {
     pub fn new(method: Method, path: Path, handler: Box<dyn Handler>) -> Self {
+        Self::with_handler(method, path, ErasedHandler::Sync(Arc::from(handler)))
+    }
+
+    /// Like [`Route::new`], but with an [`AsyncHandler`].
+    pub fn new_async(method: Method, path: Path, handler: Box<dyn AsyncHandler>) -> Self {
+        Self::with_handler(method, path, ErasedHandler::Async(Arc::from(handler)))
+    }
+
+    fn with_handler(method: Method, path: Path, handler: ErasedHandler) -> Self {
         Self {
             path,
             method,
             handler,
             before: Vec::new(),
             after: Vec::new(),
+            transforms: Vec::new(),
+            headers: HeaderMap::new(),
+            timeout: None,
+            cache_key: None,
+            max_body_size: None,
+            stream_body: false,
+            name: None,
+            description: None,
         }
     }
 
     pub fn method(&self) -> &Method {
         &self.method
     }
+
+    /// Describes this route, as a route of a group with `group_prefix`.
+    pub(crate) fn info<'a>(&'a self, group_prefix: &'a str) -> RouteInfo<'a> {
+        RouteInfo {
+            method: &self.method,
+            path: self.path.as_ref(),
+            group_prefix,
+            name: self.name.as_deref(),
+        }
+    }
+
+    /// Add a middleware that runs before the handler of this route.
+    pub fn before<M>(&mut self, middleware: M) -> &mut Route
+    where
+        M: Middleware + Send + Sync + 'static,
+    {
+        self.before.push(Box::new(middleware));
+        self
+    }
+
+    /// Add a middleware that runs after the handler of this route.
+    pub fn after<M>(&mut self, middleware: M) -> &mut Route
+    where
+        M: Middleware + Send + Sync + 'static,
+    {
+        self.after.push(Box::new(middleware));
+        self
+    }
+
+    /// Add a middleware that runs both before and after the handler of this
+    /// route, like registering it with [`Route::before`] and
+    /// [`Route::after`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use genuine::{middleware::RequestIds, App};
+    ///
+    /// let mut app = App::new();
+    ///
+    /// app.get("/orders", (|| "orders") as fn() -> _)
+    ///     .unwrap()
+    ///     .middleware(RequestIds::new());
+    /// ```
+    pub fn middleware<M>(&mut self, middleware: M) -> &mut Route
+    where
+        M: Middleware + Clone + Send + Sync + 'static,
+    {
+        self.before(middleware.clone()).after(middleware)
+    }
+
+    /// Add a transform that preprocesses the request body before the handler
+    /// sees it, e.g. to decompress or decode it.
+    ///
+    /// Transforms are applied in the order they were added. If one fails, the
+    /// request is answered with `400 Bad Request`.
+    pub fn transform_body<T>(&mut self, transform: T) -> &mut Route
+    where
+        T: BodyTransform,
+    {
+        self.transforms.push(Box::new(transform));
+        self
+    }
+
+    /// Set a header on every response from this route, unless the handler
+    /// sets it itself.
+    pub fn response_header<K>(&mut self, name: K, value: HeaderValue) -> &mut Route
+    where
+        K: IntoHeaderName,
+    {
+        self.headers.insert(name, value);
+        self
+    }
+
+    /// Answer with `503 Service Unavailable` if the handler takes longer than
+    /// `timeout`, like the [`Timeout`](crate::middleware::Timeout)
+    /// middleware does.
+    ///
+    /// If a timeout is set more than once, e.g. by the route and its group, the
+    /// shortest one wins.
+    pub fn timeout(&mut self, timeout: Duration) -> &mut Route {
+        self.timeout = Some(match self.timeout {
+            Some(current) => current.min(timeout),
+            None => timeout,
+        });
+        self
+    }
+
+    /// Cache responses of this route under the key returned by `key`,
+    /// instead of the full request URI.
+    ///
+    /// This lets requests that differ only in irrelevant parts, e.g. a
+    /// pagination parameter, share a response in an
+    /// [`LruCache`](crate::middleware::LruCache).
+    pub fn cache_key(&mut self, key: fn(&Request) -> String) -> &mut Route {
+        self.cache_key = Some(key);
+        self
+    }
+
+    /// Answer requests with bodies larger than `bytes` with `413 Payload Too
+    /// Large`, instead of using the limit of the app.
+    ///
+    /// See [`App::max_body_size`](crate::App::max_body_size).
+    pub fn max_body_size(&mut self, bytes: u64) -> &mut Route {
+        self.max_body_size = Some(bytes);
+        self
+    }
+
+    /// Hand the request body to the handler as it arrives, instead of
+    /// buffering it first.
+    ///
+    /// The handler reads it with the [`BodyReader`](crate::body::BodyReader)
+    /// extractor, middlewares and body transforms see an empty body. Reading
+    /// more than the [maximum body size](Route::max_body_size) fails.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use genuine::{body::BodyReader, App};
+    /// use http::Method;
+    /// use tokio::io::AsyncReadExt;
+    ///
+    /// async fn upload(mut body: BodyReader) -> String {
+    ///     let mut received = Vec::new();
+    ///     match body.read_to_end(&mut received).await {
+    ///         Ok(len) => format!("received {len} bytes"),
+    ///         Err(err) => format!("upload failed: {err}"),
+    ///     }
+    /// }
+    ///
+    /// let mut app = App::new();
+    /// app.add_async(Method::POST, "/upload", upload as fn(_) -> _)
+    ///     .unwrap()
+    ///     .stream_body();
+    /// ```
+    pub fn stream_body(&mut self) -> &mut Route {
+        self.stream_body = true;
+        self
+    }
+
+    /// Give this route a name.
+    pub fn name(&mut self, name: impl Into<String>) -> &mut Route {
+        self.name = Some(name.into());
+        self
+    }
+
+    /// Attach a human-readable description to this route.
+    pub fn description(&mut self, description: impl Into<String>) -> &mut Route {
+        self.description = Some(description.into());
+        self
+    }
 }
 
 mod params {
-    use std::ops::{Deref, DerefMut};
+    use std::{
+        fmt,
+        marker::PhantomData,
+        ops::{Deref, DerefMut},
+        str::FromStr,
+    };
+
+    use crate::{FromRequest, Rejection, Request};
 
+    /// Extracts a path parameter and parses it into `T`.
+    ///
+    /// Parameters are extracted by position: the first `Param` of a handler
+    /// gets the first parameter of the route path, the second one the
+    /// second, and so on.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
     pub struct Param<T>(T);
 
     impl<T> Param<T> {
@@ -83,4 +311,284 @@ mod params {
             &mut self.0
         }
     }
+
+    impl<T> From<T> for Param<T> {
+        fn from(t: T) -> Self {
+            Self(t)
+        }
+    }
+
+    impl<T: fmt::Display> fmt::Display for Param<T> {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            self.0.fmt(f)
+        }
+    }
+
+    /// A [`Param`] could not be extracted.
+    #[derive(Debug, thiserror::Error, PartialEq)]
+    pub enum ParamExtractError {
+        #[error("the route has fewer path parameters than the handler")]
+        Missing,
+        #[error("invalid path parameter `{name}`: {reason}")]
+        Invalid { name: String, reason: String },
+    }
+
+    impl Rejection for ParamExtractError {}
+
+    impl<T> FromRequest for Param<T>
+    where
+        T: FromStr,
+        T::Err: fmt::Display,
+    {
+        type Error = ParamExtractError;
+
+        const PATH_PARAMS: usize = 1;
+
+        /// Extracts the first path parameter.
+        fn from_request(req: &Request) -> Result<Self, Self::Error> {
+            Self::from_request_at(req, 0)
+        }
+
+        fn from_request_at(req: &Request, taken: usize) -> Result<Self, Self::Error> {
+            let param = req.matches().get(taken).ok_or(ParamExtractError::Missing)?;
+
+            param
+                .value
+                .parse()
+                .map(Param)
+                .map_err(|err: T::Err| ParamExtractError::Invalid {
+                    name: param.name.clone(),
+                    reason: err.to_string(),
+                })
+        }
+    }
+
+    /// Names the path parameter a [`Named`] extractor looks up.
+    pub trait ParamName {
+        const NAME: &'static str;
+    }
+
+    /// Extracts the path parameter called [`N::NAME`](ParamName::NAME) and
+    /// parses it into `T`.
+    ///
+    /// Unlike [`Param`], this does not depend on the position of the
+    /// parameter in the route path.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use genuine::{App, Named, ParamName};
+    ///
+    /// struct User;
+    ///
+    /// impl ParamName for User {
+    ///     const NAME: &'static str = "user";
+    /// }
+    ///
+    /// struct Post;
+    ///
+    /// impl ParamName for Post {
+    ///     const NAME: &'static str = "post";
+    /// }
+    ///
+    /// fn show_post(post: Named<Post, u32>, user: Named<User, String>) -> String {
+    ///     format!("post #{} by {}", *post, *user)
+    /// }
+    ///
+    /// let mut app = App::new();
+    /// app.get("/users/{user}/posts/{post}", show_post as fn(_, _) -> _)
+    ///     .unwrap();
+    /// ```
+    pub struct Named<N, T> {
+        value: T,
+        name: PhantomData<N>,
+    }
+
+    impl<N, T> Named<N, T> {
+        pub fn into_inner(self) -> T {
+            self.value
+        }
+    }
+
+    impl<N, T: fmt::Debug> fmt::Debug for Named<N, T> {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.debug_tuple("Named").field(&self.value).finish()
+        }
+    }
+
+    impl<N, T> Deref for Named<N, T> {
+        type Target = T;
+
+        fn deref(&self) -> &Self::Target {
+            &self.value
+        }
+    }
+
+    impl<N, T> DerefMut for Named<N, T> {
+        fn deref_mut(&mut self) -> &mut Self::Target {
+            &mut self.value
+        }
+    }
+
+    /// A [`Named`] parameter could not be extracted.
+    #[derive(Debug, thiserror::Error, PartialEq)]
+    pub enum NamedParamError {
+        #[error("no path parameter named `{0}`")]
+        MissingParam(&'static str),
+        #[error("invalid path parameter `{name}`: {reason}")]
+        ParseParam { name: &'static str, reason: String },
+    }
+
+    impl Rejection for NamedParamError {}
+
+    impl<N, T> FromRequest for Named<N, T>
+    where
+        N: ParamName,
+        T: FromStr,
+        T::Err: fmt::Display,
+    {
+        type Error = NamedParamError;
+
+        fn from_request(req: &Request) -> Result<Self, Self::Error> {
+            let param = req
+                .matches()
+                .iter()
+                .find(|param| param.name == N::NAME)
+                .ok_or(NamedParamError::MissingParam(N::NAME))?;
+
+            match param.value.parse() {
+                Ok(value) => Ok(Named {
+                    value,
+                    name: PhantomData,
+                }),
+                Err(err) => Err(NamedParamError::ParseParam {
+                    name: N::NAME,
+                    reason: err.to_string(),
+                }),
+            }
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use std::net::IpAddr;
+
+        use hyper::body::Bytes;
+
+        use super::*;
+        use crate::router::routes::Match;
+
+        fn request(params: &[(&str, &str)]) -> Request {
+            let matches = params
+                .iter()
+                .map(|(name, value)| Match {
+                    name: (*name).into(),
+                    value: (*value).into(),
+                })
+                .collect();
+            Request::new(http::Request::new(Bytes::new()), matches)
+        }
+
+        #[test]
+        fn params_compare_and_display_like_their_value() {
+            let req = request(&[("id", "42")]);
+            let id = Param::<u32>::from_request(&req).unwrap();
+
+            assert_eq!(id, Param::from(42));
+            assert_eq!(id.to_string(), "42");
+            let ids: std::collections::HashSet<_> = [id, id, Param::new(7)].into();
+            assert_eq!(ids.len(), 2);
+        }
+
+        #[test]
+        fn params_are_extracted_by_position() {
+            let req = request(&[("id", "42"), ("ip", "127.0.0.1")]);
+
+            let id = Param::<u32>::from_request(&req).unwrap();
+            let ip = Param::<IpAddr>::from_request_at(&req, 1).unwrap();
+
+            assert_eq!(*id, 42);
+            assert_eq!(ip.into_inner(), IpAddr::from([127, 0, 0, 1]));
+            assert_eq!(Param::<u32>::from_request(&req), Ok(id));
+            assert_eq!(
+                Param::<u32>::from_request_at(&req, 2).unwrap_err(),
+                ParamExtractError::Missing
+            );
+        }
+
+        #[test]
+        fn uuid_params_are_parsed() {
+            let req = request(&[("id", "67e55044-10b1-426f-9247-bb680e5fe0c8")]);
+
+            let id = Param::<uuid::Uuid>::from_request(&req).unwrap();
+            assert_eq!(id.to_string(), "67e55044-10b1-426f-9247-bb680e5fe0c8");
+        }
+
+        fn show(method: http::Method, id: Param<u32>, name: Param<String>) -> String {
+            format!("{method} {} #{}", *name, *id)
+        }
+
+        #[test]
+        fn handlers_get_params_by_position() {
+            let handler = show as fn(_, _, _) -> _;
+            let req = request(&[("id", "7"), ("name", "ferris")]);
+
+            let res = crate::Handler::handle_request(&handler, req);
+            assert_eq!(res.body(), "GET ferris #7");
+        }
+
+        struct Id;
+
+        impl ParamName for Id {
+            const NAME: &'static str = "id";
+        }
+
+        struct UserName;
+
+        impl ParamName for UserName {
+            const NAME: &'static str = "name";
+        }
+
+        fn greet(name: Named<UserName, String>, id: Named<Id, u32>) -> String {
+            format!("{} #{}", *name, *id)
+        }
+
+        #[test]
+        fn named_params_are_found_regardless_of_position() {
+            let handler = greet as fn(_, _) -> _;
+            let req = request(&[("id", "7"), ("name", "ferris")]);
+
+            let res = crate::Handler::handle_request(&handler, req);
+            assert_eq!(res.body(), "ferris #7");
+        }
+
+        #[test]
+        fn named_param_errors() {
+            let req = request(&[("name", "ferris")]);
+            assert_eq!(
+                Named::<Id, u32>::from_request(&req).unwrap_err(),
+                NamedParamError::MissingParam("id")
+            );
+
+            let req = request(&[("id", "ferris")]);
+            assert_eq!(
+                Named::<Id, u32>::from_request(&req).unwrap_err(),
+                NamedParamError::ParseParam {
+                    name: "id",
+                    reason: "invalid digit found in string".into()
+                }
+            );
+        }
+
+        #[test]
+        fn unparsable_params_name_the_param() {
+            let req = request(&[("id", "forty-two")]);
+
+            let err = Param::<u32>::from_request(&req).unwrap_err();
+            assert_eq!(
+                err.to_string(),
+                "invalid path parameter `id`: invalid digit found in string"
+            );
+        }
+    }
 }
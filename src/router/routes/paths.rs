@@ -1,4 +1,7 @@
-#[derive(Debug)]
+use std::fmt;
+
+/// A parsed route path, e.g. `/users/{id}`.
+#[derive(Debug, Clone)]
 pub struct Path {
     path: String,
     parts: Vec<Part>,
@@ -15,12 +18,42 @@ impl Path {
     pub fn new(path: String) -> Result<Self, ParseError> {
         let parts = {
             let mut parser = parser::Parser::new(path.as_bytes());
-            parser.parse()?
+            match parser.parse() {
+                Ok(parts) => parts,
+                Err(kind) => return Err(ParseError { path, kind }),
+            }
         };
 
         Ok(Self { path, parts })
     }
 
+    /// Returns the literal and parameter parts this path consists of.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use genuine::{Part, Path};
+    ///
+    /// let path = Path::new("/users/{id}".to_owned()).unwrap();
+    ///
+    /// assert_eq!(
+    ///     path.parts(),
+    ///     [
+    ///         Part::Literal(b"/users/".to_vec()),
+    ///         Part::Param { name: "id".to_owned() },
+    ///     ]
+    /// );
+    /// ```
+    pub fn parts(&self) -> &[Part] {
+        &self.parts
+    }
+
+    /// Matches the path of a request, without its query string, against
+    /// this path.
+    ///
+    /// The values of parameters and wildcards are percent-decoded, so
+    /// `/files/hello%20world` matches `/files/{name}` with `name` being
+    /// `hello world`.
     pub fn matches(&self, path: &str) -> Option<Vec<Match>> {
         let mut matches = Vec::new();
 
@@ -33,21 +66,75 @@ impl Path {
                 },
                 Part::Param { name } => {
                     let mut parser = parser::Parser::new(bytes);
-                    let parameter = {
-                        let segment = parser.segment().to_vec();
-                        String::from_utf8(segment).unwrap()
-                    };
-                    bytes = &bytes[parameter.len()..];
+                    let segment = parser.segment();
+                    bytes = &bytes[segment.len()..];
+                    matches.push(Match {
+                        name: name.clone(),
+                        value: percent_decode(segment),
+                    });
+                }
+                Part::Wildcard { name } => {
+                    let rest = percent_decode(bytes);
+                    bytes = &[];
                     matches.push(Match {
                         name: name.clone(),
-                        value: parameter,
+                        value: rest,
                     });
                 }
             }
         }
 
+        // the path must be matched completely, wildcards consume the rest
+        if !bytes.is_empty() {
+            return None;
+        }
+
         Some(matches)
     }
+
+    /// Returns the leading literal of this path, which all paths it matches
+    /// start with.
+    pub(crate) fn static_prefix(&self) -> &[u8] {
+        match self.parts.first() {
+            Some(Part::Literal(literal)) => literal,
+            _ => &[],
+        }
+    }
+
+    /// Returns whether this path matches exactly the same paths as `other`,
+    /// i.e. they only differ in the names of their parameters.
+    pub(crate) fn conflicts_with(&self, other: &Path) -> bool {
+        self.parts.len() == other.parts.len()
+            && self
+                .parts
+                .iter()
+                .zip(&other.parts)
+                .all(|parts| match parts {
+                    (Part::Literal(a), Part::Literal(b)) => a == b,
+                    (Part::Param { .. }, Part::Param { .. }) => true,
+                    (Part::Wildcard { .. }, Part::Wildcard { .. }) => true,
+                    _ => false,
+                })
+    }
+
+    /// Returns how specific this path is, the number of literal bytes.
+    ///
+    /// Of the paths that match a request, the most specific one has the
+    /// most bytes of it in literals, e.g. `/users/me` beats `/users/{id}`.
+    pub(crate) fn specificity(&self) -> usize {
+        self.parts
+            .iter()
+            .map(|part| match part {
+                Part::Literal(literal) => literal.len(),
+                Part::Param { .. } | Part::Wildcard { .. } => 0,
+            })
+            .sum()
+    }
+
+    /// Returns whether this path ends in a [`Part::Wildcard`].
+    pub(crate) fn has_wildcard(&self) -> bool {
+        matches!(self.parts.last(), Some(Part::Wildcard { .. }))
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -56,13 +143,63 @@ pub struct Match {
     pub value: String,
 }
 
-#[derive(Debug, PartialEq)]
+/// A part of a [`Path`].
+#[derive(Debug, Clone, PartialEq)]
 pub enum Part {
+    /// Bytes that must appear verbatim.
     Literal(Vec<u8>),
+    /// A named parameter matching a single path segment.
     Param { name: String },
+    /// A named parameter matching the rest of the path, including slashes.
+    /// It is always the last part.
+    Wildcard { name: String },
 }
 
-pub use parser::ParseError;
+pub use parser::ParseErrorKind;
+
+/// An invalid route path, together with the reason it was rejected.
+///
+/// The [`Display`](fmt::Display) output points at the offending character:
+///
+/// ```text
+/// /users/{id
+///           ^ unexpected end of path (pos 10)
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    path: String,
+    kind: ParseErrorKind,
+}
+
+impl ParseError {
+    /// Returns the path that failed to parse.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    pub fn kind(&self) -> &ParseErrorKind {
+        &self.kind
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // count characters, not bytes, so that the caret lines up
+        let pos = self.kind.pos().min(self.path.len());
+        let column = String::from_utf8_lossy(&self.path.as_bytes()[..pos])
+            .chars()
+            .count();
+
+        writeln!(f, "{}", self.path)?;
+        write!(f, "{:column$}^ {}", "", self.kind)
+    }
+}
+
+impl std::error::Error for ParseError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.kind)
+    }
+}
 
 /// Parse route paths with optional named parameters.
 ///
@@ -85,33 +222,65 @@ pub use parser::ParseError;
 ///                | "~"
 /// pct-encoded    : '%' HEXDIG HEXDIG
 /// sub-delims     : '!' | '$' | '&' | '\'' | '(' | ')' | '*' | '+' | ',' | ';' | '='
-/// param          : '{' SPACE* name SPACE* '}'
+/// param          : '{' SPACE* '*'? name SPACE* '}'
 /// name           : ALPHA ( ALPHA | DIGIT )*
 /// ```
+///
+/// A param whose name starts with `*` is a wildcard, which matches the rest of
+/// the path. It must be the last segment.
 mod parser {
+    use std::fmt;
+
     use super::Part;
 
-    #[derive(Debug, thiserror::Error, PartialEq)]
-    pub enum ParseError {
-        #[error("encountered byte 0x{actual:x} at position {pos}, but expected 0x{expected:x}")]
+    /// What went wrong while parsing a route path.
+    #[derive(Debug, Clone, thiserror::Error, PartialEq)]
+    pub enum ParseErrorKind {
+        #[error("expected {}, found {} (pos {pos})", Byte(*expected), Byte(*actual))]
         ExpectedExact {
             expected: u8,
             actual: u8,
             pos: usize,
         },
-        #[error("encountered byte 0x{actual:x} at position {pos}, but expected {expected}")]
+        #[error("expected {expected}, found {} (pos {pos})", Byte(*actual))]
         Expected {
             expected: &'static str,
             actual: u8,
             pos: usize,
         },
-        #[error("encountered unexpected end of stream at position {pos}")]
+        #[error("unexpected end of path (pos {pos})")]
         EndOfStream { pos: usize },
-        #[error("route paths must start with a '/'")]
-        IsNotAbsolute,
+        #[error("parameters must have a name (pos {pos})")]
+        EmptyParamName { pos: usize },
+        #[error("route paths must start with a single '/' (pos {pos})")]
+        IsNotAbsolute { pos: usize },
+    }
+
+    impl ParseErrorKind {
+        /// Returns the byte offset into the path at which parsing failed.
+        pub fn pos(&self) -> usize {
+            match *self {
+                Self::ExpectedExact { pos, .. } | Self::Expected { pos, .. } => pos,
+                Self::EndOfStream { pos }
+                | Self::EmptyParamName { pos }
+                | Self::IsNotAbsolute { pos } => pos,
+            }
+        }
+    }
+
+    /// Displays a byte as a quoted character if it is printable.
+    struct Byte(u8);
+
+    impl fmt::Display for Byte {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self.0 {
+                x if x.is_ascii_graphic() || x == b' ' => write!(f, "'{}'", x as char),
+                x => write!(f, "byte 0x{x:02x}"),
+            }
+        }
     }
 
-    type Result<T> = std::result::Result<T, ParseError>;
+    type Result<T> = std::result::Result<T, ParseErrorKind>;
 
     pub struct Parser<'b> {
         /// Route path to parse.
@@ -134,7 +303,8 @@ mod parser {
 
         /// Parse a route path.
         pub fn parse(&'b mut self) -> Result<Vec<Part>> {
-            self.consume(b'/').map_err(|_| ParseError::IsNotAbsolute)?;
+            self.consume(b'/')
+                .map_err(|_| ParseErrorKind::IsNotAbsolute { pos: 0 })?;
 
             // According to the [specification][spec], the first path segment
             // must not exist or be of non-zero length.
@@ -142,12 +312,12 @@ mod parser {
             // [spec]: https://www.rfc-editor.org/rfc/rfc3986#section-3.3
             {
                 if self.consume(b'/').is_ok() {
-                    return Err(ParseError::IsNotAbsolute);
+                    return Err(ParseErrorKind::IsNotAbsolute { pos: 1 });
                 }
             }
 
             let mut parts = Vec::new();
-            while self.consume(b'/').is_ok() {
+            loop {
                 match self.peek() {
                     Some(b'{') => {
                         let literal = self.bytes[self.anchor..self.cursor].to_vec();
@@ -157,18 +327,34 @@ mod parser {
                         self.cursor += 1;
 
                         self.ws();
+                        let wildcard = self.consume(b'*').is_ok();
                         let name = self.parameter_name()?;
 
                         self.ws();
                         self.consume(b'}')?;
 
                         self.anchor = self.cursor;
+                        if wildcard {
+                            parts.push(Part::Wildcard { name });
+                            if let Some(x) = self.peek() {
+                                return Err(self.expected("the end of the path", x));
+                            }
+                            return Ok(parts);
+                        }
                         parts.push(Part::Param { name });
                     }
                     _ => {
                         let _ = self.segment();
                     }
                 }
+
+                if self.consume(b'/').is_err() {
+                    break;
+                }
+            }
+
+            if let Some(x) = self.peek() {
+                return Err(self.expected("a path character", x));
             }
 
             let tail = &self.bytes[self.anchor..self.cursor];
@@ -183,9 +369,17 @@ mod parser {
 
         /// Parses a parameter name.
         fn parameter_name(&mut self) -> Result<String> {
+            match self.peek() {
+                Some(b'}') => return Err(ParseErrorKind::EmptyParamName { pos: self.cursor }),
+                Some(x) if !x.is_ascii_alphabetic() => {
+                    return Err(self.expected("an alphabetic character", x));
+                }
+                _ => {}
+            }
+
             let name = {
                 let (name_bytes, ()) = self.capture(|parser| {
-                    assert!(parser.any()?.is_ascii_alphabetic());
+                    parser.any()?;
                     parser.skip_while(move |x| x.is_ascii_alphanumeric());
                     Ok(())
                 })?;
@@ -204,13 +398,10 @@ mod parser {
         pub fn segment(&mut self) -> &[u8] {
             self.capture(|parser| {
                 loop {
-                    match parser.pchar() {
-                        Ok(_) => (),
-                        Err(ParseError::EndOfStream { .. }) => break,
-                        Err(_) => {
-                            parser.cursor -= 1;
-                            break;
-                        }
+                    let checkpoint = parser.cursor;
+                    if parser.pchar().is_err() {
+                        parser.cursor = checkpoint;
+                        break;
                     }
                 }
 
@@ -226,9 +417,11 @@ mod parser {
         fn pchar(&mut self) -> Result<u8> {
             if let Some(x) = self.unreserved() {
                 Ok(x)
-            } else {
+            } else if let Some(b'%') = self.peek() {
                 self.percent_encoded()
-                    .or_else(|_| self.sub_delimiter())
+            } else {
+                self.sub_delimiter()
+                    .or_else(|_| self.consume(b':'))
                     .or_else(|_| self.consume(b'@'))
             }
         }
@@ -255,7 +448,7 @@ mod parser {
         }
 
         /// Parses a single hexadecimal digit and returns its numeric value, or
-        /// a [`ParseError`] if the byte is no valid hexadecimal digit.
+        /// a [`ParseErrorKind`] if the byte is no valid hexadecimal digit.
         fn hex_digit(&mut self) -> Result<u8> {
             Ok(match self.any()? {
                 x @ b'0'..=b'9' => x - b'0',
@@ -269,12 +462,16 @@ mod parser {
         ///
         /// [rfc]: https://www.rfc-editor.org/rfc/rfc3986#section-2.2
         fn sub_delimiter(&mut self) -> Result<u8> {
-            match self.any() {
-                ok @ Ok(
-                    b'!' | b'$' | b'&' | b'\'' | b'(' | b')' | b'*' | b'+' | b',' | b';' | b'=',
-                ) => ok,
-                Ok(x) => Err(self.expected("a sub delimiter", x)),
-                Err(err) => Err(err),
+            match self.peek() {
+                Some(
+                    x @ (b'!' | b'$' | b'&' | b'\'' | b'(' | b')' | b'*' | b'+' | b',' | b';'
+                    | b'='),
+                ) => {
+                    self.cursor += 1;
+                    Ok(x)
+                }
+                Some(x) => Err(self.expected("a sub delimiter", x)),
+                None => Err(ParseErrorKind::EndOfStream { pos: self.cursor }),
             }
         }
 
@@ -284,8 +481,8 @@ mod parser {
             }
         }
 
-        fn expected(&self, what: &'static str, actual: u8) -> ParseError {
-            ParseError::Expected {
+        fn expected(&self, what: &'static str, actual: u8) -> ParseErrorKind {
+            ParseErrorKind::Expected {
                 expected: what,
                 actual,
                 pos: self.cursor,
@@ -312,7 +509,7 @@ mod parser {
         }
 
         /// Consumes the next byte if it matches the expected value. Advances
-        /// the cursor if successful, or returns a [`ParseError`] if the byte
+        /// the cursor if successful, or returns a [`ParseErrorKind`] if the byte
         /// does not match or if the input ends unexpectedly.
         fn consume(&mut self, expected: u8) -> Result<u8> {
             match self.peek() {
@@ -320,24 +517,24 @@ mod parser {
                     self.cursor += 1;
                     Ok(x)
                 }
-                Some(x) => Err(ParseError::ExpectedExact {
+                Some(x) => Err(ParseErrorKind::ExpectedExact {
                     expected,
                     actual: x,
                     pos: self.cursor,
                 }),
-                None => Err(ParseError::EndOfStream { pos: self.cursor }),
+                None => Err(ParseErrorKind::EndOfStream { pos: self.cursor }),
             }
         }
 
         /// Consumes and returns the next byte, advancing the cursor or returns
-        /// a [`ParseError`] if the end of input is reached.
+        /// a [`ParseErrorKind`] if the end of input is reached.
         fn any(&mut self) -> Result<u8> {
             match self.bytes.get(self.cursor).copied() {
                 Some(x) => {
                     self.cursor += 1;
                     Ok(x)
                 }
-                None => Err(ParseError::EndOfStream { pos: self.cursor }),
+                None => Err(ParseErrorKind::EndOfStream { pos: self.cursor }),
             }
         }
 
@@ -404,7 +601,10 @@ mod parser {
         #[test]
         fn parse_literal_url_path_without_leading_slash() {
             let mut parser = Parser::new(b"url/path/to/parse/");
-            assert_eq!(parser.parse(), Err(ParseError::IsNotAbsolute));
+            assert_eq!(
+                parser.parse(),
+                Err(ParseErrorKind::IsNotAbsolute { pos: 0 })
+            );
         }
 
         #[test]
@@ -420,10 +620,222 @@ mod parser {
             parse_path_and_compare(&format!("{}{{{}}}", PREFIX, PARAM), expected);
         }
 
+        #[test]
+        fn parse_root_wildcard() {
+            let expected = &[
+                Part::Literal(b"/".into()),
+                Part::Wildcard {
+                    name: "rest".into(),
+                },
+            ];
+            parse_path_and_compare("/{*rest}", expected);
+        }
+
+        #[test]
+        fn parse_wildcard_after_prefix() {
+            let expected = &[
+                Part::Literal(b"/prefix/".into()),
+                Part::Wildcard {
+                    name: "rest".into(),
+                },
+            ];
+            parse_path_and_compare("/prefix/{ *rest }", expected);
+        }
+
+        #[test]
+        fn wildcard_must_be_last() {
+            let mut parser = Parser::new(b"/{*rest}/tail");
+            assert_eq!(
+                parser.parse(),
+                Err(ParseErrorKind::Expected {
+                    expected: "the end of the path",
+                    actual: b'/',
+                    pos: 8
+                })
+            );
+        }
+
+        #[test]
+        fn parse_url_path_with_empty_first_segment() {
+            let mut parser = Parser::new(b"//users");
+            assert_eq!(
+                parser.parse(),
+                Err(ParseErrorKind::IsNotAbsolute { pos: 1 })
+            );
+        }
+
+        #[test]
+        fn param_name_must_start_with_a_letter() {
+            let mut parser = Parser::new(b"/users/{0id}");
+            assert_eq!(
+                parser.parse(),
+                Err(ParseErrorKind::Expected {
+                    expected: "an alphabetic character",
+                    actual: b'0',
+                    pos: 8
+                })
+            );
+        }
+
+        #[test]
+        fn param_name_must_not_be_empty() {
+            let mut parser = Parser::new(b"/users/{ }");
+            assert_eq!(
+                parser.parse(),
+                Err(ParseErrorKind::EmptyParamName { pos: 9 })
+            );
+
+            let mut parser = Parser::new(b"/files/{*}");
+            assert_eq!(
+                parser.parse(),
+                Err(ParseErrorKind::EmptyParamName { pos: 9 })
+            );
+        }
+
         #[test]
         fn root_path_is_valid() {
             let mut parser = Parser::new(b"/");
-            assert_eq!(parser.parse(), Err(ParseError::IsNotAbsolute));
+            assert_eq!(parser.parse(), Ok(vec![Part::Literal(b"/".into())]));
         }
     }
 }
+
+/// Decodes the `%XX` sequences of `bytes`. Malformed sequences are kept as
+/// they are, and invalid UTF-8 is replaced.
+fn percent_decode(bytes: &[u8]) -> String {
+    let hex = |byte: u8| (byte as char).to_digit(16);
+
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = match bytes[i..] {
+            [b'%', high, low, ..] => hex(high).zip(hex(low)),
+            _ => None,
+        };
+        match escaped {
+            Some((high, low)) => {
+                decoded.push((high * 16 + low) as u8);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn parts_of_path_with_parameters() {
+        let path = Path::new("/users/{id}/posts/{post}".into()).unwrap();
+
+        assert_eq!(
+            path.parts(),
+            [
+                Part::Literal(b"/users/".to_vec()),
+                Part::Param { name: "id".into() },
+                Part::Literal(b"/posts/".to_vec()),
+                Part::Param {
+                    name: "post".into()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn parts_of_literal_path() {
+        let path = Path::new("/about".into()).unwrap();
+        assert_eq!(path.parts(), [Part::Literal(b"/about".to_vec())]);
+    }
+
+    #[test]
+    fn error_points_at_the_offending_character() {
+        let err = Path::new("/users/{id x}".into()).unwrap_err();
+
+        assert_eq!(err.path(), "/users/{id x}");
+        assert_eq!(
+            err.to_string(),
+            "/users/{id x}\n           ^ expected '}', found 'x' (pos 11)"
+        );
+    }
+
+    #[test]
+    fn error_at_end_of_path_points_past_it() {
+        let err = Path::new("/users/{id".into()).unwrap_err();
+
+        assert_eq!(err.kind(), &ParseErrorKind::EndOfStream { pos: 10 });
+        assert_eq!(
+            err.to_string(),
+            "/users/{id\n          ^ unexpected end of path (pos 10)"
+        );
+    }
+
+    #[test]
+    fn unprintable_bytes_are_shown_as_hex() {
+        let err = Path::new("/caf\u{e9}".into()).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "/caf\u{e9}\n    ^ expected a path character, found byte 0xc3 (pos 4)"
+        );
+    }
+
+    #[test]
+    fn wildcard_matches_rest_of_path() {
+        let path = Path::new("/files/{*path}".into()).unwrap();
+
+        assert_eq!(
+            path.matches("/files/css/site.css"),
+            Some(vec![Match {
+                name: "path".into(),
+                value: "css/site.css".into()
+            }])
+        );
+        assert_eq!(path.matches("/images/logo.png"), None);
+    }
+
+    #[test]
+    fn paths_match_exactly() {
+        let path = Path::new("/users/{id}".into()).unwrap();
+
+        assert_eq!(
+            path.matches("/users/42"),
+            Some(vec![Match {
+                name: "id".into(),
+                value: "42".into()
+            }])
+        );
+        assert_eq!(path.matches("/users/42/comments"), None);
+        assert_eq!(path.matches("/users/42/"), None);
+
+        let root = Path::new("/".into()).unwrap();
+        assert_eq!(root.matches("/"), Some(vec![]));
+        assert_eq!(root.matches("/users"), None);
+    }
+
+    #[test]
+    fn matched_values_are_percent_decoded() {
+        let path = Path::new("/files/{name}".into()).unwrap();
+
+        assert_eq!(
+            path.matches("/files/hello%20world"),
+            Some(vec![Match {
+                name: "name".into(),
+                value: "hello world".into()
+            }])
+        );
+
+        let path = Path::new("/files/{*path}".into()).unwrap();
+        assert_eq!(
+            path.matches("/files/caf%C3%A9/100%/a%2Fb")
+                .map(|matches| matches[0].value.clone()),
+            Some("caf\u{e9}/100%/a/b".into())
+        );
+    }
+}
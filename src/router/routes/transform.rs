@@ -0,0 +1,20 @@
+use hyper::body::Bytes;
+
+pub type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Preprocesses a request body before it reaches the handler.
+///
+/// Closures of type `Fn(Bytes) -> Result<Bytes, BoxError>` are body
+/// transforms.
+pub trait BodyTransform: Send + Sync + 'static {
+    fn transform(&self, bytes: Bytes) -> Result<Bytes, BoxError>;
+}
+
+impl<F> BodyTransform for F
+where
+    F: Fn(Bytes) -> Result<Bytes, BoxError> + Send + Sync + 'static,
+{
+    fn transform(&self, bytes: Bytes) -> Result<Bytes, BoxError> {
+        self(bytes)
+    }
+}
@@ -0,0 +1,118 @@
+//! A radix trie of routes by the static prefix of their path.
+//!
+//! Looking up a request path yields the few routes whose static prefix it
+//! starts with, so only those have to be matched against it.
+
+use std::mem;
+
+#[derive(Debug, Default)]
+pub(crate) struct Trie {
+    root: Node,
+}
+
+#[derive(Debug, Default)]
+struct Node {
+    prefix: Vec<u8>,
+    values: Vec<usize>,
+    children: Vec<Node>,
+}
+
+impl Trie {
+    pub(crate) fn insert(&mut self, key: &[u8], value: usize) {
+        self.root.insert(key, value);
+    }
+
+    /// Returns the values of all keys that `path` starts with, in ascending
+    /// order.
+    pub(crate) fn prefixes_of(&self, mut path: &[u8]) -> Vec<usize> {
+        let mut values = Vec::new();
+
+        let mut node = &self.root;
+        loop {
+            values.extend_from_slice(&node.values);
+
+            let Some(child) = path
+                .first()
+                .and_then(|first| node.children.iter().find(|c| c.prefix[0] == *first))
+            else {
+                break;
+            };
+            match path.strip_prefix(child.prefix.as_slice()) {
+                Some(rest) => (node, path) = (child, rest),
+                None => break,
+            }
+        }
+
+        values.sort_unstable();
+        values
+    }
+}
+
+impl Node {
+    /// Insert `value` under `key`, which is relative to the prefix of this
+    /// node.
+    fn insert(&mut self, key: &[u8], value: usize) {
+        let Some(&first) = key.first() else {
+            self.values.push(value);
+            return;
+        };
+
+        let Some(child) = self.children.iter_mut().find(|c| c.prefix[0] == first) else {
+            self.children.push(Node {
+                prefix: key.to_vec(),
+                values: vec![value],
+                children: Vec::new(),
+            });
+            return;
+        };
+
+        let common = child
+            .prefix
+            .iter()
+            .zip(key)
+            .take_while(|(a, b)| a == b)
+            .count();
+        if common < child.prefix.len() {
+            let tail = Node {
+                prefix: child.prefix.split_off(common),
+                values: mem::take(&mut child.values),
+                children: mem::take(&mut child.children),
+            };
+            child.children.push(tail);
+        }
+
+        child.insert(&key[common..], value);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn all_prefixes_are_found() {
+        let mut trie = Trie::default();
+        for (value, key) in ["/users/", "/users", "/", "/user", "/teams/", "/users/me"]
+            .into_iter()
+            .enumerate()
+        {
+            trie.insert(key.as_bytes(), value);
+        }
+
+        assert_eq!(trie.prefixes_of(b"/users/42"), [0, 1, 2, 3]);
+        assert_eq!(trie.prefixes_of(b"/users/me"), [0, 1, 2, 3, 5]);
+        assert_eq!(trie.prefixes_of(b"/teams"), [2]);
+        assert_eq!(trie.prefixes_of(b"/teams/rust"), [2, 4]);
+        assert!(trie.prefixes_of(b"users").is_empty());
+    }
+
+    #[test]
+    fn empty_keys_match_everything() {
+        let mut trie = Trie::default();
+        trie.insert(b"", 0);
+        trie.insert(b"/a", 1);
+
+        assert_eq!(trie.prefixes_of(b"/b"), [0]);
+        assert_eq!(trie.prefixes_of(b"/a"), [0, 1]);
+    }
+}
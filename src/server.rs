@@ -1,44 +1,891 @@
-use std::{io, net::SocketAddr, sync::Arc};
+use std::{
+    future::Future,
+    io,
+    net::SocketAddr,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    task::Poll,
+    time::Duration,
+};
 
-use hyper::server::conn::http1;
-use hyper_util::rt::TokioIo;
-use tokio::net::TcpListener;
+use hyper_util::{
+    rt::{TokioExecutor, TokioIo, TokioTimer},
+    server::{
+        conn::auto,
+        graceful::{GracefulShutdown, Watcher},
+    },
+};
+use socket2::{SockRef, TcpKeepalive};
+use tokio::{
+    io::{AsyncRead, AsyncWrite},
+    net::{TcpListener, TcpSocket, TcpStream},
+    sync::Semaphore,
+};
 
-use crate::router::Router;
+use crate::{
+    router::{Router, RouterService},
+    App, BoxedFuture,
+};
+
+/// Configures the sockets of a server.
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::time::Duration;
+///
+/// use genuine::{App, ServerBuilder};
+///
+/// let app = App::new();
+///
+/// let server = ServerBuilder::new()
+///     .tcp_nodelay(true)
+///     .tcp_keepalive(Some(Duration::from_secs(60)))
+///     .tcp_reuseport(true);
+///
+/// app.run_with(([127, 0, 0, 1], 3000), server).unwrap();
+/// ```
+#[derive(Debug, Clone)]
+pub struct ServerBuilder {
+    tcp_nodelay: bool,
+    tcp_keepalive: Option<Duration>,
+    tcp_reuseport: bool,
+    drain_timeout: Duration,
+    http2: bool,
+    max_connections: Option<usize>,
+    http1_keep_alive: bool,
+    http1_header_read_timeout: Option<Duration>,
+}
+
+impl Default for ServerBuilder {
+    fn default() -> Self {
+        Self {
+            tcp_nodelay: false,
+            tcp_keepalive: None,
+            tcp_reuseport: false,
+            drain_timeout: Duration::from_secs(30),
+            http2: false,
+            max_connections: None,
+            http1_keep_alive: true,
+            http1_header_read_timeout: None,
+        }
+    }
+}
+
+impl ServerBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Serve HTTP/2 besides HTTP/1.
+    ///
+    /// Over TLS, clients choose HTTP/2 with ALPN. Without TLS, they must
+    /// start the connection with the HTTP/2 preface, which is known as h2c
+    /// with prior knowledge. This uses the `http2` features of `hyper` and
+    /// `hyper-util`, which are always enabled.
+    pub fn http2(mut self, enabled: bool) -> Self {
+        self.http2 = enabled;
+        self
+    }
+
+    /// Serve at most `max` connections at once. Further connections wait in
+    /// the backlog of the listening socket until one is closed. By default,
+    /// there is no limit.
+    pub fn max_connections(mut self, max: usize) -> Self {
+        self.max_connections = Some(max);
+        self
+    }
+
+    /// Keep HTTP/1 connections open for further requests. This is enabled
+    /// by default.
+    pub fn http1_keep_alive(mut self, enabled: bool) -> Self {
+        self.http1_keep_alive = enabled;
+        self
+    }
+
+    /// Close HTTP/1 connections whose request headers are not received
+    /// within `timeout`, e.g. of slow or idle clients.
+    pub fn http1_header_read_timeout(mut self, timeout: Duration) -> Self {
+        self.http1_header_read_timeout = Some(timeout);
+        self
+    }
+
+    /// Set how long to wait for in-flight requests once the server shuts
+    /// down. Connections still open afterwards are dropped. The default is
+    /// 30 seconds.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::time::Duration;
+    ///
+    /// use genuine::{App, Server};
+    ///
+    /// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+    /// let app = App::new();
+    ///
+    /// Server::builder()
+    ///     .drain_timeout(Duration::from_secs(5))
+    ///     .build(([127, 0, 0, 1], 3000).into(), app)
+    ///     .with_shutdown(async {
+    ///         tokio::signal::ctrl_c().await.ok();
+    ///     })
+    ///     .bind()
+    ///     .await
+    ///     .unwrap();
+    /// # });
+    /// ```
+    pub fn drain_timeout(mut self, timeout: Duration) -> Self {
+        self.drain_timeout = timeout;
+        self
+    }
+
+    /// Set `TCP_NODELAY` on every accepted connection, disabling Nagle's
+    /// algorithm.
+    pub fn tcp_nodelay(mut self, enabled: bool) -> Self {
+        self.tcp_nodelay = enabled;
+        self
+    }
+
+    /// Set `SO_KEEPALIVE` on every accepted connection, probing idle
+    /// connections after the given duration.
+    pub fn tcp_keepalive(mut self, idle: Option<Duration>) -> Self {
+        self.tcp_keepalive = idle;
+        self
+    }
+
+    /// Set `SO_REUSEPORT` on the listening socket, so several servers can
+    /// bind the same address, e.g. for zero-downtime deploys.
+    ///
+    /// This is only supported on Linux and ignored with a warning elsewhere.
+    pub fn tcp_reuseport(mut self, enabled: bool) -> Self {
+        self.tcp_reuseport = enabled;
+        self
+    }
+
+    /// Create a server for `app` on `addr` with this configuration.
+    pub fn build(self, addr: SocketAddr, app: App) -> Server {
+        Server {
+            addr: Some(addr),
+            ..self.build_without_addr(app)
+        }
+    }
+
+    /// Create a server for `app` without an address, for
+    /// [`Server::bind_unix`] and [`Server::bind_all`], which are given
+    /// their own.
+    pub fn build_without_addr(self, app: App) -> Server {
+        Server {
+            addr: None,
+            router: app.into_router(),
+            config: self,
+            shutdown: None,
+            #[cfg(feature = "tls")]
+            tls: None,
+        }
+    }
+
+    fn listen(&self, addr: SocketAddr) -> io::Result<TcpListener> {
+        self.listen_with(addr, false)
+    }
+
+    /// Like [`ServerBuilder::listen`], but with `only_v6`, IPv6 sockets do
+    /// not accept IPv4 connections, so another socket can listen on the
+    /// same port for those.
+    fn listen_with(&self, addr: SocketAddr, only_v6: bool) -> io::Result<TcpListener> {
+        let socket = match addr {
+            SocketAddr::V4(_) => TcpSocket::new_v4()?,
+            SocketAddr::V6(_) => TcpSocket::new_v6()?,
+        };
+
+        // Same as `TcpListener::bind`, which allows to rebind while
+        // connections of a previous server are still in `TIME_WAIT`.
+        #[cfg(not(windows))]
+        socket.set_reuseaddr(true)?;
+
+        if self.tcp_reuseport {
+            #[cfg(target_os = "linux")]
+            socket.set_reuseport(true)?;
+
+            #[cfg(not(target_os = "linux"))]
+            tracing::warn!("SO_REUSEPORT is only supported on Linux, ignoring it");
+        }
+
+        if only_v6 && addr.is_ipv6() {
+            SockRef::from(&socket).set_only_v6(true)?;
+        }
+
+        socket.bind(addr)?;
+        socket.listen(1024)
+    }
+
+    /// Returns a connection builder with the HTTP settings of this
+    /// configuration.
+    fn connection_builder(&self) -> auto::Builder<TokioExecutor> {
+        let mut builder = auto::Builder::new(TokioExecutor::new());
+        if !self.http2 {
+            builder = builder.http1_only();
+        }
+
+        let mut http1 = builder.http1();
+        http1.keep_alive(self.http1_keep_alive);
+        if let Some(timeout) = self.http1_header_read_timeout {
+            http1.timer(TokioTimer::new()).header_read_timeout(timeout);
+        }
+        builder
+    }
+
+    fn configure(&self, stream: &TcpStream) -> io::Result<()> {
+        stream.set_nodelay(self.tcp_nodelay)?;
+
+        if let Some(idle) = self.tcp_keepalive {
+            let keepalive = TcpKeepalive::new().with_time(idle);
+            SockRef::from(stream).set_tcp_keepalive(&keepalive)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Serves connections until the server shuts down, see
+/// [`Server::bind_with_addr`].
+pub type BindFuture = Pin<Box<dyn Future<Output = io::Result<()>> + Send>>;
 
 pub struct Server {
-    addr: SocketAddr,
+    addr: Option<SocketAddr>,
     router: Router,
+    config: ServerBuilder,
+    shutdown: Option<BoxedFuture<()>>,
+    #[cfg(feature = "tls")]
+    pub(crate) tls: Option<Arc<tokio_rustls::rustls::ServerConfig>>,
 }
 
 impl Server {
-    pub fn new(addr: SocketAddr, router: Router) -> Self {
-        Self { addr, router }
+    /// Create a server for `app` on `addr` with the default configuration,
+    /// see [`ServerBuilder::build`].
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use genuine::{App, Server};
+    ///
+    /// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+    /// let mut app = App::new();
+    /// app.get("/", (|| "Hello world!") as fn() -> _).unwrap();
+    ///
+    /// Server::new(([127, 0, 0, 1], 3000).into(), app)
+    ///     .bind()
+    ///     .await
+    ///     .unwrap();
+    /// # });
+    /// ```
+    pub fn new(addr: SocketAddr, app: App) -> Self {
+        ServerBuilder::new().build(addr, app)
+    }
+
+    /// Create a server without an address, see
+    /// [`ServerBuilder::build_without_addr`].
+    pub fn from_app(app: App) -> Self {
+        ServerBuilder::new().build_without_addr(app)
+    }
+
+    /// Configure a server, see [`ServerBuilder`].
+    pub fn builder() -> ServerBuilder {
+        ServerBuilder::new()
+    }
+
+    /// Shut down once `signal` completes, e.g. `tokio::signal::ctrl_c()`.
+    ///
+    /// The server stops accepting connections and waits for in-flight
+    /// requests for up to the [drain
+    /// timeout](ServerBuilder::drain_timeout), then [`Server::bind`] returns.
+    pub fn with_shutdown<F>(mut self, signal: F) -> Self
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        self.shutdown = Some(Box::pin(signal));
+        self
     }
 
     pub async fn bind(self) -> io::Result<()> {
-        let listener = TcpListener::bind(self.addr).await?;
+        let (_, serving) = self.bind_with_addr()?;
+        serving.await
+    }
+
+    /// Like [`Server::bind`], but binds right away and returns the address
+    /// the server listens on, with a future that serves connections until
+    /// the server shuts down.
+    ///
+    /// This reveals the port the OS picked for port `0`, e.g. to connect to
+    /// the server in tests, see [`App::bind_with_addr`](crate::App::bind_with_addr).
+    /// It must be called within a tokio runtime.
+    ///
+    /// Returns an [`InvalidInput`](io::ErrorKind::InvalidInput) error if the
+    /// server has no address, see [`Server::from_app`].
+    pub fn bind_with_addr(self) -> io::Result<(SocketAddr, BindFuture)> {
+        let Some(addr) = self.addr else {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "no address to listen on",
+            ));
+        };
+        let listener = self.config.listen(addr)?;
+        let addr = listener.local_addr()?;
+        Ok((addr, Box::pin(self.serve(listener))))
+    }
+
+    /// Like [`Server::bind`], but listens on all of `addrs` instead of the
+    /// server address, e.g. on `0.0.0.0:80` and `[::]:80` for both IPv4 and
+    /// IPv6.
+    ///
+    /// IPv6 addresses only accept IPv6 connections then, so they do not
+    /// clash with IPv4 addresses on the same port.
+    pub async fn bind_all(self, addrs: impl IntoIterator<Item = SocketAddr>) -> io::Result<()> {
+        let listeners = addrs
+            .into_iter()
+            .map(|addr| self.config.listen_with(addr, true))
+            .collect::<io::Result<Vec<_>>>()?;
+        if listeners.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "no address to listen on",
+            ));
+        }
+
+        self.serve(Listeners {
+            listeners,
+            next: AtomicUsize::new(0),
+        })
+        .await
+    }
+
+    /// Like [`Server::bind`], but listens on the Unix domain socket at
+    /// `path` instead of the server address.
+    ///
+    /// The socket file is removed once the server stops.
+    #[cfg(unix)]
+    pub async fn bind_unix(self, path: impl AsRef<std::path::Path>) -> io::Result<()> {
+        let path = path.as_ref();
+        let listener = tokio::net::UnixListener::bind(path)?;
+        let _socket = SocketFile(path.to_owned());
+
+        self.serve(listener).await
+    }
+
+    pub(crate) async fn serve<L: Listener>(self, listener: L) -> io::Result<()> {
         let router = Arc::new(self.router);
+        let mut shutdown = self
+            .shutdown
+            .unwrap_or_else(|| Box::pin(std::future::pending()));
+        let graceful = GracefulShutdown::new();
+        let connections = self
+            .config
+            .max_connections
+            .map(|max| Arc::new(Semaphore::new(max)));
+
+        #[cfg(feature = "tls")]
+        let tls = self.tls.map(|config| {
+            let mut config = Arc::unwrap_or_clone(config);
+            if self.config.http2 && config.alpn_protocols.is_empty() {
+                config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+            }
+            tokio_rustls::TlsAcceptor::from(Arc::new(config))
+        });
 
         loop {
-            let (stream, _) = listener.accept().await?;
-            let router_handle = Arc::clone(&router);
+            // wait for a free connection slot before accepting another one
+            let permit = match &connections {
+                Some(connections) => tokio::select! {
+                    permit = Arc::clone(connections).acquire_owned() => permit.ok(),
+                    () = &mut shutdown => break,
+                },
+                None => None,
+            };
+            let (stream, remote) = tokio::select! {
+                accepted = listener.accept() => accepted?,
+                () = &mut shutdown => break,
+            };
+            let service = RouterService {
+                router: Arc::clone(&router),
+                remote,
+            };
+
+            if let Err(err) = L::configure(&self.config, &stream) {
+                tracing::error!(error = ?err, "failed to configure connection");
+                continue;
+            }
 
-            // Use an adapter to access something implementing `tokio::io` traits as if they implement
-            // `hyper::rt` IO traits.
-            let io = TokioIo::new(stream);
+            let watcher = graceful.watcher();
+            let builder = self.config.connection_builder();
+
+            #[cfg(feature = "tls")]
+            if let Some(acceptor) = &tls {
+                let acceptor = acceptor.clone();
+                tokio::task::spawn(async move {
+                    match acceptor.accept(stream).await {
+                        Ok(stream) => serve_connection(stream, service, watcher, builder).await,
+                        Err(err) => tracing::warn!(error = ?err, "TLS handshake failed"),
+                    }
+                    drop(permit);
+                });
+                continue;
+            }
 
             // Spawn a tokio task to serve multiple connections concurrently
             tokio::task::spawn(async move {
-                // Finally, we bind the incoming connection to our `hello` service
-                if let Err(err) = http1::Builder::new()
-                    // `service_fn` converts our function in a `Service`
-                    .serve_connection(io, router_handle)
-                    .await
-                {
-                    eprintln!("Error serving connection: {:?}", err);
+                serve_connection(stream, service, watcher, builder).await;
+                drop(permit);
+            });
+        }
+
+        drop(listener);
+        tracing::info!(connections = graceful.count(), "shutting down");
+
+        let drained = tokio::time::timeout(self.config.drain_timeout, graceful.shutdown()).await;
+        if drained.is_err() {
+            tracing::warn!("dropping connections still open after the drain timeout");
+        }
+
+        Ok(())
+    }
+}
+
+/// A socket the server accepts connections on.
+pub(crate) trait Listener {
+    type Stream: AsyncRead + AsyncWrite + Unpin + Send + 'static;
+
+    /// Accepts a connection, and returns it with the address of the client if
+    /// there is one.
+    fn accept(&self)
+        -> impl Future<Output = io::Result<(Self::Stream, Option<SocketAddr>)>> + Send;
+
+    /// Applies the socket options of `config` to an accepted stream.
+    fn configure(_config: &ServerBuilder, _stream: &Self::Stream) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Listener for TcpListener {
+    type Stream = TcpStream;
+
+    async fn accept(&self) -> io::Result<(TcpStream, Option<SocketAddr>)> {
+        let (stream, remote) = TcpListener::accept(self).await?;
+        Ok((stream, Some(remote)))
+    }
+
+    fn configure(config: &ServerBuilder, stream: &TcpStream) -> io::Result<()> {
+        config.configure(stream)
+    }
+}
+
+/// Several TCP listeners, which accept connections in turn.
+struct Listeners {
+    listeners: Vec<TcpListener>,
+    /// The listener to try first, so that no listener starves the others.
+    next: AtomicUsize,
+}
+
+impl Listener for Listeners {
+    type Stream = TcpStream;
+
+    async fn accept(&self) -> io::Result<(TcpStream, Option<SocketAddr>)> {
+        let start = self.next.fetch_add(1, Ordering::Relaxed);
+        let count = self.listeners.len();
+
+        std::future::poll_fn(|cx| {
+            for i in 0..count {
+                let listener = &self.listeners[(start + i) % count];
+                if let Poll::Ready(accepted) = listener.poll_accept(cx) {
+                    return Poll::Ready(accepted.map(|(stream, remote)| (stream, Some(remote))));
+                }
+            }
+            Poll::Pending
+        })
+        .await
+    }
+
+    fn configure(config: &ServerBuilder, stream: &TcpStream) -> io::Result<()> {
+        config.configure(stream)
+    }
+}
+
+#[cfg(unix)]
+impl Listener for tokio::net::UnixListener {
+    type Stream = tokio::net::UnixStream;
+
+    async fn accept(&self) -> io::Result<(tokio::net::UnixStream, Option<SocketAddr>)> {
+        let (stream, _) = tokio::net::UnixListener::accept(self).await?;
+        Ok((stream, None))
+    }
+}
+
+/// Removes the file of a Unix domain socket when dropped.
+#[cfg(unix)]
+struct SocketFile(std::path::PathBuf);
+
+#[cfg(unix)]
+impl Drop for SocketFile {
+    fn drop(&mut self) {
+        if let Err(err) = std::fs::remove_file(&self.0) {
+            tracing::warn!(error = ?err, path = ?self.0, "failed to remove socket file");
+        }
+    }
+}
+
+/// Serves the requests of a connection until it is closed by the client or,
+/// on shutdown, by `watcher`.
+///
+/// If `builder` allows HTTP/2, the protocol is detected from the first bytes
+/// the client sends.
+async fn serve_connection<I>(
+    io: I,
+    service: RouterService,
+    watcher: Watcher,
+    builder: auto::Builder<TokioExecutor>,
+) where
+    I: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    // Use an adapter to access something implementing `tokio::io` traits as if they implement
+    // `hyper::rt` IO traits.
+    let io = TokioIo::new(io);
+
+    // Finally, we bind the incoming connection to our service. Only the auto
+    // builder supports graceful shutdown of connections that can be upgraded,
+    // e.g. to WebSockets.
+    let conn = builder.serve_connection_with_upgrades(io, service);
+    if let Err(err) = watcher.watch(conn).await {
+        tracing::error!(error = ?err, "failed to serve connection");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use http::Method;
+    use http_body_util::{BodyExt, Full};
+    use hyper::body::Bytes;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        sync::{oneshot, Notify},
+    };
+
+    use super::*;
+
+    async fn connect(config: &ServerBuilder) -> (TcpStream, TcpListener) {
+        let listener = config.listen(([127, 0, 0, 1], 0).into()).unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let (client, accepted) = tokio::join!(TcpStream::connect(addr), listener.accept());
+        drop(client);
+
+        (accepted.unwrap().0, listener)
+    }
+
+    #[tokio::test]
+    async fn nodelay_and_keepalive_are_applied_to_accepted_streams() {
+        let config = ServerBuilder::new()
+            .tcp_nodelay(true)
+            .tcp_keepalive(Some(Duration::from_secs(42)));
+
+        let (stream, _listener) = connect(&config).await;
+        config.configure(&stream).unwrap();
+
+        assert!(stream.nodelay().unwrap());
+        let socket = SockRef::from(&stream);
+        assert!(socket.keepalive().unwrap());
+        #[cfg(target_os = "linux")]
+        assert_eq!(
+            socket.tcp_keepalive_time().unwrap(),
+            Duration::from_secs(42)
+        );
+    }
+
+    #[tokio::test]
+    async fn defaults_leave_nodelay_off() {
+        let config = ServerBuilder::new();
+
+        let (stream, _listener) = connect(&config).await;
+        config.configure(&stream).unwrap();
+
+        assert!(!stream.nodelay().unwrap());
+        assert!(!SockRef::from(&stream).keepalive().unwrap());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn reuseport_allows_binding_twice() {
+        let config = ServerBuilder::new().tcp_reuseport(true);
+
+        let first = config.listen(([127, 0, 0, 1], 0).into()).unwrap();
+        let addr = first.local_addr().unwrap();
+
+        assert!(config.listen(addr).is_ok());
+        assert!(ServerBuilder::new().listen(addr).is_err());
+    }
+
+    #[tokio::test]
+    async fn shutdown_waits_for_in_flight_requests() {
+        let started = Arc::new(Notify::new());
+        let mut app = App::new();
+        app.add_async(Method::GET, "/slow", {
+            let started = Arc::clone(&started);
+            move |_| {
+                let started = Arc::clone(&started);
+                async move {
+                    started.notify_one();
+                    tokio::time::sleep(Duration::from_millis(100)).await;
+                    "done"
                 }
+            }
+        })
+        .unwrap();
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (stop, stopped) = oneshot::channel();
+        let server = Server::new(addr, app).with_shutdown(async {
+            stopped.await.ok();
+        });
+        let server = tokio::spawn(server.serve(listener));
+
+        let mut client = TcpStream::connect(addr).await.unwrap();
+        client
+            .write_all(b"GET /slow HTTP/1.1\r\nhost: localhost\r\n\r\n")
+            .await
+            .unwrap();
+        started.notified().await;
+        stop.send(()).unwrap();
+
+        let mut response = String::new();
+        client.read_to_string(&mut response).await.unwrap();
+        assert!(response.starts_with("HTTP/1.1 200 OK"), "{response}");
+        assert!(response.ends_with("done"), "{response}");
+
+        server.await.unwrap().unwrap();
+        assert!(TcpStream::connect(addr).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn shutdown_gives_up_after_the_drain_timeout() {
+        let started = Arc::new(Notify::new());
+        let mut app = App::new();
+        app.add_async(Method::GET, "/stuck", {
+            let started = Arc::clone(&started);
+            move |_| {
+                let started = Arc::clone(&started);
+                async move {
+                    started.notify_one();
+                    std::future::pending::<()>().await;
+                    "never"
+                }
+            }
+        })
+        .unwrap();
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (stop, stopped) = oneshot::channel();
+        let server = Server::builder()
+            .drain_timeout(Duration::from_millis(50))
+            .build(addr, app)
+            .with_shutdown(async {
+                stopped.await.ok();
             });
+        let server = tokio::spawn(server.serve(listener));
+
+        let mut client = TcpStream::connect(addr).await.unwrap();
+        client
+            .write_all(b"GET /stuck HTTP/1.1\r\nhost: localhost\r\n\r\n")
+            .await
+            .unwrap();
+        started.notified().await;
+        stop.send(()).unwrap();
+
+        let stopped = tokio::time::timeout(Duration::from_secs(5), server).await;
+        stopped.unwrap().unwrap().unwrap();
+    }
+
+    fn hello_app() -> App {
+        let mut app = App::new();
+        app.add(Method::GET, "/", (|| "hello") as fn() -> _)
+            .unwrap();
+        app
+    }
+
+    async fn spawn(config: ServerBuilder) -> SocketAddr {
+        let server = config.build(([127, 0, 0, 1], 0).into(), hello_app());
+        let (addr, serving) = server.bind_with_addr().unwrap();
+        tokio::spawn(serving);
+        addr
+    }
+
+    const GET: &[u8] = b"GET / HTTP/1.1\r\nhost: localhost\r\n\r\n";
+
+    #[tokio::test]
+    async fn connections_over_the_limit_wait() {
+        let addr = spawn(Server::builder().max_connections(1)).await;
+
+        let mut first = TcpStream::connect(addr).await.unwrap();
+        first.write_all(GET).await.unwrap();
+        let mut buf = [0; 1024];
+        assert!(first.read(&mut buf).await.unwrap() > 0);
+
+        let mut second = TcpStream::connect(addr).await.unwrap();
+        second.write_all(GET).await.unwrap();
+        let waited = tokio::time::timeout(Duration::from_millis(100), second.read(&mut buf)).await;
+        assert!(waited.is_err(), "second connection was served");
+
+        drop(first);
+        assert!(second.read(&mut buf).await.unwrap() > 0);
+        assert!(buf.starts_with(b"HTTP/1.1 200 OK"));
+    }
+
+    #[tokio::test]
+    async fn http1_settings_are_applied() {
+        let addr = spawn(
+            Server::builder()
+                .http1_keep_alive(false)
+                .http1_header_read_timeout(Duration::from_millis(50)),
+        )
+        .await;
+
+        let mut client = TcpStream::connect(addr).await.unwrap();
+        client.write_all(GET).await.unwrap();
+        let mut response = String::new();
+        client.read_to_string(&mut response).await.unwrap();
+        assert!(response.contains("connection: close"), "{response}");
+
+        // clients that never finish their headers are disconnected
+        let mut client = TcpStream::connect(addr).await.unwrap();
+        client.write_all(b"GET / HTTP/1.1\r\n").await.unwrap();
+        let mut response = Vec::new();
+        let read = tokio::time::timeout(Duration::from_secs(5), client.read_to_end(&mut response));
+        read.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn all_addresses_are_served() {
+        // find two free ports
+        let free = || std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let (first, second) = (free(), free());
+        let addrs = [first.local_addr().unwrap(), second.local_addr().unwrap()];
+        drop((first, second));
+
+        tokio::spawn(Server::from_app(hello_app()).bind_all(addrs));
+
+        for addr in addrs {
+            let mut client = loop {
+                match TcpStream::connect(addr).await {
+                    Ok(client) => break client,
+                    Err(_) => tokio::time::sleep(Duration::from_millis(10)).await,
+                }
+            };
+            client.write_all(GET).await.unwrap();
+            let mut buf = [0; 1024];
+            let read = client.read(&mut buf).await.unwrap();
+            assert!(buf[..read].starts_with(b"HTTP/1.1 200 OK"));
+            assert!(buf[..read].ends_with(b"hello"));
         }
     }
+
+    #[tokio::test]
+    async fn handlers_get_the_peer_address() {
+        let mut app = App::new();
+        app.add(
+            Method::GET,
+            "/",
+            (|peer: SocketAddr| peer.to_string()) as fn(_) -> _,
+        )
+        .unwrap();
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(Server::new(addr, app).serve(listener));
+
+        let mut client = TcpStream::connect(addr).await.unwrap();
+        let peer = client.local_addr().unwrap();
+        client
+            .write_all(b"GET / HTTP/1.1\r\nhost: localhost\r\nconnection: close\r\n\r\n")
+            .await
+            .unwrap();
+
+        let mut response = String::new();
+        client.read_to_string(&mut response).await.unwrap();
+        assert!(response.ends_with(&peer.to_string()), "{response}");
+    }
+
+    #[tokio::test]
+    async fn http2_is_served_with_prior_knowledge() {
+        let mut app = App::new();
+        app.add(Method::GET, "/", (|| "hello") as fn() -> _)
+            .unwrap();
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = ServerBuilder::new().http2(true).build(addr, app);
+        tokio::spawn(server.serve(listener));
+
+        let stream = TcpStream::connect(addr).await.unwrap();
+        let (mut sender, conn) =
+            hyper::client::conn::http2::handshake(TokioExecutor::new(), TokioIo::new(stream))
+                .await
+                .unwrap();
+        tokio::spawn(conn);
+
+        let req = http::Request::get(format!("http://{addr}/"))
+            .body(Full::<Bytes>::default())
+            .unwrap();
+        let res = sender.send_request(req).await.unwrap();
+        assert_eq!(res.version(), http::Version::HTTP_2);
+
+        let body = res.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(body, "hello");
+    }
+
+    #[tokio::test]
+    async fn servers_without_address_cannot_bind() {
+        let err = Server::from_app(App::new()).bind().await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn requests_are_served_over_unix_sockets() {
+        let path = std::env::temp_dir().join(format!("genuine-{}.sock", std::process::id()));
+        let mut app = App::new();
+        app.add(Method::GET, "/", (|| "hello") as fn() -> _)
+            .unwrap();
+
+        let (stop, stopped) = oneshot::channel();
+        let server = Server::from_app(app).with_shutdown(async {
+            stopped.await.ok();
+        });
+        let server = tokio::spawn(server.bind_unix(path.clone()));
+
+        let stream = loop {
+            match tokio::net::UnixStream::connect(&path).await {
+                Ok(stream) => break stream,
+                Err(_) => tokio::time::sleep(Duration::from_millis(10)).await,
+            }
+        };
+        let (mut sender, conn) = hyper::client::conn::http1::handshake(TokioIo::new(stream))
+            .await
+            .unwrap();
+        tokio::spawn(conn);
+
+        let req = http::Request::get("/")
+            .header("host", "localhost")
+            .body(Full::<Bytes>::default())
+            .unwrap();
+        let res = sender.send_request(req).await.unwrap();
+        let body = res.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(body, "hello");
+
+        drop(sender);
+        stop.send(()).unwrap();
+        server.await.unwrap().unwrap();
+        assert!(!path.exists());
+    }
 }
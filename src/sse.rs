@@ -0,0 +1,493 @@
+//! Server-sent events.
+//!
+//! Respond with [`Sse`] to stream events to the client as they happen.
+//!
+//! A [`Broadcaster`] sends events to many clients at once, each of which
+//! is responded to with the [`EventStream`] of its subscription.
+
+use std::{
+    fmt,
+    future::Future,
+    io,
+    pin::Pin,
+    task::{ready, Context, Poll},
+    time::Duration,
+};
+
+use futures_core::{future::BoxFuture, stream::BoxStream, Stream};
+use http::{
+    header::{CACHE_CONTROL, CONTENT_TYPE},
+    Response,
+};
+use hyper::body::Bytes;
+use tokio::{
+    sync::broadcast::{self, error::RecvError},
+    time::{Instant, Sleep},
+};
+
+use crate::{body::BodyStream, IntoResponse};
+
+/// Events are dropped for subscribers that lag behind by more than this.
+const CAPACITY: usize = 64;
+
+/// A single server-sent event.
+///
+/// # Examples
+///
+/// ```
+/// use genuine::sse::Event;
+///
+/// let event = Event::new("1:0").event("score").id("7");
+/// assert_eq!(event.to_string(), "event: score\nid: 7\ndata: 1:0\n\n");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Event {
+    event: Option<String>,
+    id: Option<String>,
+    retry: Option<Duration>,
+    data: String,
+}
+
+impl Event {
+    pub fn new(data: impl Into<String>) -> Self {
+        Self {
+            event: None,
+            id: None,
+            retry: None,
+            data: data.into(),
+        }
+    }
+
+    /// Set the event type, which clients can listen for.
+    ///
+    /// Line breaks are removed, since they would end the field early.
+    pub fn event(mut self, event: impl Into<String>) -> Self {
+        self.event = Some(single_line(event.into()));
+        self
+    }
+
+    /// Set the event ID, which clients send back in `Last-Event-ID` when
+    /// they reconnect.
+    ///
+    /// Line breaks are removed, since they would end the field early.
+    pub fn id(mut self, id: impl Into<String>) -> Self {
+        self.id = Some(single_line(id.into()));
+        self
+    }
+
+    /// Set how long clients wait before they reconnect after losing the
+    /// connection.
+    pub fn retry(mut self, retry: Duration) -> Self {
+        self.retry = Some(retry);
+        self
+    }
+
+    pub fn data(&self) -> &str {
+        &self.data
+    }
+}
+
+impl fmt::Display for Event {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(event) = &self.event {
+            writeln!(f, "event: {event}")?;
+        }
+        if let Some(id) = &self.id {
+            writeln!(f, "id: {id}")?;
+        }
+        if let Some(retry) = self.retry {
+            writeln!(f, "retry: {}", retry.as_millis())?;
+        }
+        // every line needs its own field, and empty data still needs one for
+        // the event to be dispatched
+        for line in self.data.split("\r\n").flat_map(|l| l.split(['\r', '\n'])) {
+            if line.is_empty() {
+                writeln!(f, "data:")?;
+            } else {
+                writeln!(f, "data: {line}")?;
+            }
+        }
+        writeln!(f)
+    }
+}
+
+fn single_line(mut field: String) -> String {
+    field.retain(|c| c != '\r' && c != '\n');
+    field
+}
+
+/// A response that streams events to the client as `S` yields them.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use futures::stream::{self, Stream, StreamExt};
+/// use genuine::sse::{Event, Sse};
+///
+/// fn scores() -> Sse<impl Stream<Item = Event>> {
+///     let goals = stream::iter(["1:0", "1:1", "2:1"]).map(Event::new);
+///     Sse::new(goals).keep_alive(Duration::from_secs(15))
+/// }
+/// ```
+pub struct Sse<S> {
+    events: S,
+    keep_alive: Option<Duration>,
+}
+
+impl<S> Sse<S> {
+    pub fn new(events: S) -> Self {
+        Self {
+            events,
+            keep_alive: None,
+        }
+    }
+
+    /// Send a `: ping` comment whenever there was no event for `interval`,
+    /// so that proxies do not close the idle connection.
+    pub fn keep_alive(mut self, interval: Duration) -> Self {
+        self.keep_alive = Some(interval);
+        self
+    }
+}
+
+impl<S> fmt::Debug for Sse<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Sse")
+            .field("keep_alive", &self.keep_alive)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<S> IntoResponse for Sse<S>
+where
+    S: Stream<Item = Event> + Send + 'static,
+{
+    fn into_response(self) -> Response<Bytes> {
+        let body = Encoded {
+            events: Box::pin(self.events),
+            keep_alive: self.keep_alive,
+            ping: None,
+        };
+
+        Response::builder()
+            .header(CONTENT_TYPE, "text/event-stream")
+            .header(CACHE_CONTROL, "no-cache")
+            .body(BodyStream::new(body))
+            .unwrap()
+            .into_response()
+    }
+}
+
+/// The events of an [`Sse`] response, encoded for the body.
+struct Encoded {
+    events: BoxStream<'static, Event>,
+    keep_alive: Option<Duration>,
+    /// Fires when it is time for the next ping, created on the first poll
+    /// because timers need a runtime.
+    ping: Option<Pin<Box<Sleep>>>,
+}
+
+impl Stream for Encoded {
+    type Item = Result<Bytes, io::Error>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+
+        if let Poll::Ready(event) = this.events.as_mut().poll_next(cx) {
+            if let (Some(interval), Some(ping)) = (this.keep_alive, &mut this.ping) {
+                ping.as_mut().reset(Instant::now() + interval);
+            }
+            return Poll::Ready(event.map(|event| Ok(event.to_string().into())));
+        }
+
+        let Some(interval) = this.keep_alive else {
+            return Poll::Pending;
+        };
+        let ping = this
+            .ping
+            .get_or_insert_with(|| Box::pin(tokio::time::sleep(interval)));
+        if ping.as_mut().poll(cx).is_pending() {
+            return Poll::Pending;
+        }
+
+        ping.as_mut().reset(Instant::now() + interval);
+        Poll::Ready(Some(Ok(Bytes::from_static(b": ping\n\n"))))
+    }
+}
+
+/// Sends events to every subscribed client.
+///
+/// Cloning the broadcaster is cheap, all clones send to the same
+/// subscribers.
+///
+/// # Examples
+///
+/// ```
+/// use futures::StreamExt;
+/// use genuine::sse::{Broadcaster, Event};
+///
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// let scores = Broadcaster::new();
+/// let mut stream = scores.subscribe();
+///
+/// scores.send(Event::new("1:0"));
+///
+/// assert_eq!(stream.next().await, Some(Event::new("1:0")));
+/// # });
+/// ```
+#[derive(Debug, Clone)]
+pub struct Broadcaster {
+    sender: broadcast::Sender<Event>,
+}
+
+impl Broadcaster {
+    pub fn new() -> Self {
+        Self {
+            sender: broadcast::Sender::new(CAPACITY),
+        }
+    }
+
+    /// Returns a stream of all events sent from now on.
+    pub fn subscribe(&self) -> EventStream {
+        EventStream {
+            receiver: Some(self.sender.subscribe()),
+            next: None,
+        }
+    }
+
+    /// Send `event` to all subscribers and return how many there are.
+    ///
+    /// Subscribers unsubscribe by dropping their [`EventStream`], e.g. when
+    /// the client closed the connection. Events sent while there are no
+    /// subscribers are discarded.
+    pub fn send(&self, event: Event) -> usize {
+        self.sender.send(event).unwrap_or(0)
+    }
+
+    /// Returns the number of active subscribers.
+    pub fn subscribers(&self) -> usize {
+        self.sender.receiver_count()
+    }
+}
+
+impl Default for Broadcaster {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The events of a [`Broadcaster`] as received by one client.
+///
+/// Responding with it streams the events to the client as they are sent,
+/// like [`Sse`] does, until all broadcasters are gone. If the stream lags
+/// behind, the oldest events are skipped.
+pub struct EventStream {
+    /// Taken by `next` while waiting for an event.
+    receiver: Option<broadcast::Receiver<Event>>,
+    next: Option<BoxFuture<'static, (Option<Event>, broadcast::Receiver<Event>)>>,
+}
+
+impl EventStream {
+    /// Wait for the next event.
+    ///
+    /// Returns [`None`] once all broadcasters are gone.
+    pub async fn recv(&mut self) -> Option<Event> {
+        std::future::poll_fn(|cx| Pin::new(&mut *self).poll_next(cx)).await
+    }
+}
+
+/// Waits for the next event of `receiver` and hands it back with the event.
+async fn next_event(
+    mut receiver: broadcast::Receiver<Event>,
+) -> (Option<Event>, broadcast::Receiver<Event>) {
+    loop {
+        match receiver.recv().await {
+            Ok(event) => return (Some(event), receiver),
+            Err(RecvError::Lagged(_)) => continue,
+            Err(RecvError::Closed) => return (None, receiver),
+        }
+    }
+}
+
+impl Stream for EventStream {
+    type Item = Event;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+        let next = this.next.get_or_insert_with(|| {
+            let receiver = this.receiver.take().expect("receiver is not taken");
+            Box::pin(next_event(receiver))
+        });
+
+        let (event, receiver) = ready!(next.as_mut().poll(cx));
+        this.next = None;
+        this.receiver = Some(receiver);
+        Poll::Ready(event)
+    }
+}
+
+impl fmt::Debug for EventStream {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EventStream").finish_non_exhaustive()
+    }
+}
+
+impl IntoResponse for EventStream {
+    /// Responds with the events as they are sent, see [`Sse`].
+    fn into_response(self) -> Response<Bytes> {
+        Sse::new(self).into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::StreamExt;
+    use http::Method;
+    use http_body_util::{BodyExt, Full};
+
+    use super::*;
+    use crate::router::Router;
+
+    fn race() -> Sse<futures::stream::Iter<std::array::IntoIter<Event, 3>>> {
+        Sse::new(futures::stream::iter([
+            Event::new("start").event("race"),
+            Event::new("lap 1").id("1"),
+            Event::new("finish").retry(Duration::from_secs(3)),
+        ]))
+    }
+
+    #[tokio::test]
+    async fn events_are_streamed() {
+        let mut router = Router::new();
+        router.groups[0]
+            .add(Method::GET, "/race", race as fn() -> _)
+            .unwrap();
+
+        let req = http::Request::get("/race")
+            .body(Full::<Bytes>::default())
+            .unwrap();
+        let res = router.respond(req).await.unwrap();
+        assert_eq!(res.headers()[CONTENT_TYPE], "text/event-stream");
+        assert_eq!(res.headers()[CACHE_CONTROL], "no-cache");
+        assert!(!res.headers().contains_key(http::header::CONNECTION));
+
+        let mut body = res.into_body();
+        let mut events = Vec::new();
+        while let Some(frame) = body.frame().await {
+            events.push(frame.unwrap().into_data().unwrap());
+        }
+        assert_eq!(
+            events,
+            [
+                "event: race\ndata: start\n\n",
+                "id: 1\ndata: lap 1\n\n",
+                "retry: 3000\ndata: finish\n\n",
+            ]
+        );
+    }
+
+    #[test]
+    fn line_breaks_cannot_add_fields() {
+        let event = Event::new("a\r\nb\rc\nd")
+            .event("score\ndata: forged")
+            .id("7\r\nretry: 1");
+        assert_eq!(
+            event.to_string(),
+            "event: scoredata: forged\nid: 7retry: 1\n\
+             data: a\ndata: b\ndata: c\ndata: d\n\n"
+        );
+    }
+
+    #[test]
+    fn empty_data_is_sent() {
+        assert_eq!(Event::new("").to_string(), "data:\n\n");
+        assert_eq!(Event::new("a\n").to_string(), "data: a\ndata:\n\n");
+    }
+
+    #[tokio::test]
+    async fn idle_streams_are_kept_alive() {
+        let mut body = Encoded {
+            events: Box::pin(futures::stream::pending()),
+            keep_alive: Some(Duration::from_millis(10)),
+            ping: None,
+        };
+
+        for _ in 0..2 {
+            let ping = body.next().await.unwrap().unwrap();
+            assert_eq!(ping, ": ping\n\n");
+        }
+    }
+
+    #[tokio::test]
+    async fn every_subscriber_receives_the_event() {
+        let scores = Broadcaster::new();
+        let mut first = scores.subscribe();
+        let mut second = scores.subscribe();
+
+        assert_eq!(scores.send(Event::new("1:0").event("goal")), 2);
+
+        let expected = Some(Event::new("1:0").event("goal"));
+        assert_eq!(first.recv().await, expected);
+        assert_eq!(second.recv().await, expected);
+    }
+
+    #[test]
+    fn dropped_subscribers_are_removed() {
+        let scores = Broadcaster::new();
+        let stream = scores.subscribe();
+        assert_eq!(scores.subscribers(), 1);
+
+        drop(stream);
+
+        assert_eq!(scores.subscribers(), 0);
+        assert_eq!(scores.send(Event::new("1:0")), 0);
+    }
+
+    struct Subscribe(Broadcaster);
+
+    impl crate::Handler for Subscribe {
+        fn handle_request(&self, _: crate::Request) -> Response<Bytes> {
+            self.0.subscribe().into_response()
+        }
+    }
+
+    #[tokio::test]
+    async fn events_sent_after_the_response_are_streamed() {
+        let scores = Broadcaster::new();
+        let mut router = Router::new();
+        router.groups[0]
+            .add(Method::GET, "/scores", Subscribe(scores.clone()))
+            .unwrap();
+
+        let req = http::Request::get("/scores")
+            .body(Full::<Bytes>::default())
+            .unwrap();
+        let res = router.respond(req).await.unwrap();
+        assert_eq!(res.headers()[CONTENT_TYPE], "text/event-stream");
+        let mut body = res.into_body();
+
+        scores.send(Event::new("kick-off"));
+        let frame = body.frame().await.unwrap().unwrap();
+        assert_eq!(frame.into_data().unwrap(), "data: kick-off\n\n");
+
+        scores.send(Event::new("1:0\nby Ferris").id("2"));
+        let frame = body.frame().await.unwrap().unwrap();
+        assert_eq!(
+            frame.into_data().unwrap(),
+            "id: 2\ndata: 1:0\ndata: by Ferris\n\n"
+        );
+
+        drop((router, scores));
+        assert!(body.frame().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn stream_ends_with_the_broadcaster() {
+        let scores = Broadcaster::new();
+        let mut stream = scores.subscribe();
+        drop(scores);
+        assert_eq!(stream.recv().await, None);
+    }
+}
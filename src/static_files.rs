@@ -0,0 +1,406 @@
+//! Serving files from disk.
+
+use std::{
+    fmt, fs, io,
+    path::{Component, Path, PathBuf},
+    sync::Arc,
+    time::UNIX_EPOCH,
+};
+
+use http::{
+    header::{
+        ACCEPT_ENCODING, ACCEPT_RANGES, CONTENT_ENCODING, CONTENT_RANGE, CONTENT_TYPE, ETAG,
+        IF_NONE_MATCH, RANGE,
+    },
+    HeaderMap, HeaderValue, Response, StatusCode,
+};
+use hyper::body::Bytes;
+
+use crate::{response::ResponseExt, Handler, IntoResponse, Request};
+
+type NotFound = Arc<dyn Fn() -> Response<Bytes> + Send + Sync>;
+
+/// Serves files from a directory.
+///
+/// The file is named by the last path parameter of the route, usually a
+/// wildcard. Paths that could escape the directory, e.g. with `..`, are not
+/// found. If the client accepts gzip and a pre-compressed companion file
+/// with a `.gz` suffix exists, e.g. `index.html.gz` next to `index.html`,
+/// that one is served instead, like nginx's `gzip_static`.
+///
+/// Responses carry an `ETag`, so clients can revalidate with
+/// `If-None-Match` and get `304 Not Modified`, and single byte ranges are
+/// served with `206 Partial Content`.
+///
+/// # Examples
+///
+/// ```
+/// use genuine::{static_files::ServeDir, App};
+///
+/// let mut app = App::new();
+///
+/// app.get("/static/{*file}", ServeDir::new("./public")).unwrap();
+/// ```
+#[derive(Clone)]
+pub struct ServeDir {
+    root: PathBuf,
+    not_found: NotFound,
+}
+
+impl ServeDir {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self {
+            root: root.into(),
+            not_found: Arc::new(|| status(StatusCode::NOT_FOUND)),
+        }
+    }
+
+    /// Respond with `not_found` to requests for missing files, instead of a
+    /// plain `404 Not Found`. The status is always 404.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use genuine::static_files::ServeDir;
+    ///
+    /// let serve = ServeDir::new("./public").not_found(|| "no such file");
+    /// ```
+    pub fn not_found<F, R>(mut self, not_found: F) -> Self
+    where
+        F: Fn() -> R + Send + Sync + 'static,
+        R: IntoResponse,
+    {
+        self.not_found = Arc::new(move || (StatusCode::NOT_FOUND, not_found()).into_response());
+        self
+    }
+
+    /// Resolves `path` relative to the root, rejecting any path that could
+    /// escape it.
+    fn resolve(&self, path: &str) -> Option<PathBuf> {
+        let path = Path::new(path.trim_start_matches('/'));
+        path.components()
+            .all(|component| matches!(component, Component::Normal(_)))
+            .then(|| self.root.join(path))
+    }
+
+    /// Responds with the file at `path`, or the part of it that `req`
+    /// asks for.
+    fn serve(&self, req: &Request, path: &Path, content_type: &str) -> io::Result<Response<Bytes>> {
+        let contents = Bytes::from(fs::read(path)?);
+        let etag = etag(&fs::metadata(path)?);
+
+        let mut res = Response::builder()
+            .header(CONTENT_TYPE, content_type)
+            .header(ETAG, &etag)
+            .header(ACCEPT_RANGES, "bytes")
+            .vary("accept-encoding");
+
+        if is_fresh(req.headers(), &etag) {
+            return Ok(res
+                .status(StatusCode::NOT_MODIFIED)
+                .body(Bytes::new())
+                .unwrap());
+        }
+
+        let len = contents.len() as u64;
+        let range = req
+            .headers()
+            .get(RANGE)
+            .and_then(|value| value.to_str().ok());
+        match range.map(|range| parse_range(range, len)) {
+            Some(Some(Ok((start, end)))) => {
+                res = res
+                    .status(StatusCode::PARTIAL_CONTENT)
+                    .header(CONTENT_RANGE, format!("bytes {start}-{end}/{len}"));
+                Ok(res
+                    .body(contents.slice(start as usize..=end as usize))
+                    .unwrap())
+            }
+            Some(Some(Err(Unsatisfiable))) => Ok(res
+                .status(StatusCode::RANGE_NOT_SATISFIABLE)
+                .header(CONTENT_RANGE, format!("bytes */{len}"))
+                .body(Bytes::new())
+                .unwrap()),
+            // ignore ranges we do not understand, e.g. multiple ones
+            Some(None) | None => Ok(res.body(contents).unwrap()),
+        }
+    }
+}
+
+impl fmt::Debug for ServeDir {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ServeDir")
+            .field("root", &self.root)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Handler for ServeDir {
+    fn handle_request(&self, req: Request) -> Response<Bytes> {
+        let Some(path) = req.matches().last().and_then(|m| self.resolve(&m.value)) else {
+            return (self.not_found)();
+        };
+
+        let content_type = content_type(&path);
+
+        if accepts_gzip(req.headers()) {
+            let mut gzipped = path.clone().into_os_string();
+            gzipped.push(".gz");
+
+            if let Ok(mut res) = self.serve(&req, Path::new(&gzipped), &content_type) {
+                res.headers_mut()
+                    .insert(CONTENT_ENCODING, HeaderValue::from_static("gzip"));
+                return res;
+            }
+        }
+
+        match self.serve(&req, &path, &content_type) {
+            Ok(res) => res,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => (self.not_found)(),
+            Err(_) => status(StatusCode::INTERNAL_SERVER_ERROR),
+        }
+    }
+}
+
+fn status(status: StatusCode) -> Response<Bytes> {
+    let reason = status.canonical_reason().unwrap_or_default();
+    Response::builder()
+        .status(status)
+        .body(Bytes::from_static(reason.as_bytes()))
+        .unwrap()
+}
+
+/// A strong validator from the size and modification time of a file.
+fn etag(metadata: &fs::Metadata) -> String {
+    let modified = metadata
+        .modified()
+        .ok()
+        .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+        .unwrap_or_default();
+    format!(r#""{:x}-{:x}""#, metadata.len(), modified.as_nanos())
+}
+
+/// Whether the `If-None-Match` headers list `etag`, so the client's copy is
+/// still fresh.
+fn is_fresh(headers: &HeaderMap, etag: &str) -> bool {
+    headers
+        .get_all(IF_NONE_MATCH)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(|tag| tag.trim())
+        // `If-None-Match` uses the weak comparison
+        .any(|tag| tag == "*" || tag.trim_start_matches("W/") == etag)
+}
+
+/// The `Range` header asks only for bytes past the end of the file.
+#[derive(Debug, PartialEq, Eq)]
+struct Unsatisfiable;
+
+/// Parses a `Range` header with a single byte range into the first and last
+/// byte it covers in a file of `len` bytes.
+///
+/// Returns [`None`] for headers it does not support, which are ignored.
+fn parse_range(range: &str, len: u64) -> Option<Result<(u64, u64), Unsatisfiable>> {
+    let (start, end) = range.strip_prefix("bytes=")?.trim().split_once('-')?;
+
+    let (start, end) = match (start.trim(), end.trim()) {
+        ("", suffix) => {
+            let suffix: u64 = suffix.parse().ok()?;
+            if suffix == 0 || len == 0 {
+                return Some(Err(Unsatisfiable));
+            }
+            (len.saturating_sub(suffix), len - 1)
+        }
+        (start, "") => (start.parse().ok()?, len.saturating_sub(1)),
+        (start, end) => {
+            let (start, end): (u64, u64) = (start.parse().ok()?, end.parse().ok()?);
+            if end < start {
+                return None;
+            }
+            (start, end.min(len.saturating_sub(1)))
+        }
+    };
+
+    if start >= len {
+        return Some(Err(Unsatisfiable));
+    }
+    Some(Ok((start, end)))
+}
+
+/// Whether the `Accept-Encoding` headers allow a gzip encoded response.
+fn accepts_gzip(headers: &HeaderMap) -> bool {
+    headers
+        .get_all(ACCEPT_ENCODING)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|coding| {
+            let mut params = coding.split(';').map(str::trim);
+            let name = params.next().unwrap_or_default();
+            let rejected = params.any(|param| {
+                param
+                    .strip_prefix("q=")
+                    .and_then(|q| q.parse::<f32>().ok())
+                    .is_some_and(|q| q == 0.0)
+            });
+            matches!(name, "gzip" | "*") && !rejected
+        })
+}
+
+fn content_type(path: &Path) -> String {
+    let mime = mime_guess::from_path(path).first_or_octet_stream();
+    match (mime.type_().as_str(), mime.subtype().as_str()) {
+        ("text", _) | ("application", "javascript") => format!("{mime}; charset=utf-8"),
+        _ => mime.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::router::routes::Match;
+
+    use super::*;
+
+    /// A temporary directory that is removed when dropped.
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> Self {
+            let dir = std::env::temp_dir().join(format!("genuine-{}-{}", name, std::process::id()));
+            fs::create_dir_all(&dir).unwrap();
+            Self(dir)
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn request(file: &str, accept_encoding: Option<&'static str>) -> Request {
+        let mut req = http::Request::builder();
+        if let Some(encoding) = accept_encoding {
+            req = req.header(ACCEPT_ENCODING, encoding);
+        }
+        let matches = vec![Match {
+            name: "file".into(),
+            value: file.into(),
+        }];
+        Request::new(req.body(Bytes::new()).unwrap(), matches)
+    }
+
+    fn request_with(file: &str, name: http::HeaderName, value: &str) -> Request {
+        let req = http::Request::builder().header(name, value);
+        let matches = vec![Match {
+            name: "file".into(),
+            value: file.into(),
+        }];
+        Request::new(req.body(Bytes::new()).unwrap(), matches)
+    }
+
+    #[test]
+    fn etags_revalidate() {
+        let dir = TempDir::new("etag");
+        fs::write(dir.0.join("app.js"), "console.log(1)").unwrap();
+        let serve = ServeDir::new(&dir.0);
+
+        let res = serve.handle_request(request("app.js", None));
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(
+            res.headers()[CONTENT_TYPE],
+            "text/javascript; charset=utf-8"
+        );
+        let etag = res.headers()[ETAG].to_str().unwrap().to_owned();
+
+        let res = serve.handle_request(request_with("app.js", IF_NONE_MATCH, &etag));
+        assert_eq!(res.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(res.headers()[ETAG], etag.as_str());
+        assert!(res.body().is_empty());
+
+        fs::write(dir.0.join("app.js"), "console.log(2, 3)").unwrap();
+        let res = serve.handle_request(request_with("app.js", IF_NONE_MATCH, &etag));
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.body(), "console.log(2, 3)");
+    }
+
+    #[test]
+    fn byte_ranges_are_served() {
+        let dir = TempDir::new("range");
+        fs::write(dir.0.join("digits.txt"), "0123456789").unwrap();
+        let serve = ServeDir::new(&dir.0);
+        let range = |range| serve.handle_request(request_with("digits.txt", RANGE, range));
+
+        let res = range("bytes=2-4");
+        assert_eq!(res.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(res.headers()[CONTENT_RANGE], "bytes 2-4/10");
+        assert_eq!(res.body(), "234");
+
+        assert_eq!(range("bytes=7-").body(), "789");
+        assert_eq!(range("bytes=-2").body(), "89");
+        assert_eq!(range("bytes=8-100").body(), "89");
+
+        let res = range("bytes=10-");
+        assert_eq!(res.status(), StatusCode::RANGE_NOT_SATISFIABLE);
+        assert_eq!(res.headers()[CONTENT_RANGE], "bytes */10");
+
+        let res = range("bytes=0-1, 4-5");
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.body(), "0123456789");
+    }
+
+    #[test]
+    fn missing_files_get_the_configured_response() {
+        let dir = TempDir::new("custom-404");
+        let serve = ServeDir::new(&dir.0).not_found(|| "no such file");
+
+        let res = serve.handle_request(request("missing.txt", None));
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+        assert_eq!(res.body(), "no such file");
+    }
+
+    #[test]
+    fn serves_gzip_companion_when_accepted() {
+        let dir = TempDir::new("gzip-static");
+        fs::write(dir.0.join("file.txt"), "plain").unwrap();
+        fs::write(dir.0.join("file.txt.gz"), "gzipped").unwrap();
+        let serve = ServeDir::new(&dir.0);
+
+        let res = serve.handle_request(request("file.txt", Some("deflate, gzip;q=0.8")));
+        assert_eq!(res.body(), "gzipped");
+        assert_eq!(res.headers()[CONTENT_ENCODING], "gzip");
+        assert_eq!(res.headers()[CONTENT_TYPE], "text/plain; charset=utf-8");
+
+        let res = serve.handle_request(request("file.txt", None));
+        assert_eq!(res.body(), "plain");
+        assert!(!res.headers().contains_key(CONTENT_ENCODING));
+
+        let res = serve.handle_request(request("file.txt", Some("gzip;q=0")));
+        assert_eq!(res.body(), "plain");
+    }
+
+    #[test]
+    fn serves_plain_file_without_companion() {
+        let dir = TempDir::new("plain-only");
+        fs::write(dir.0.join("index.html"), "<h1>hi</h1>").unwrap();
+        let serve = ServeDir::new(&dir.0);
+
+        let res = serve.handle_request(request("index.html", Some("gzip")));
+        assert_eq!(res.body(), "<h1>hi</h1>");
+        assert_eq!(res.headers()[CONTENT_TYPE], "text/html; charset=utf-8");
+        assert!(!res.headers().contains_key(CONTENT_ENCODING));
+    }
+
+    #[test]
+    fn missing_and_escaping_files_are_not_found() {
+        let dir = TempDir::new("not-found");
+        let serve = ServeDir::new(&dir.0);
+
+        let res = serve.handle_request(request("missing.txt", None));
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+        let res = serve.handle_request(request("..", None));
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    }
+}
@@ -0,0 +1,340 @@
+//! Helpers for testing apps.
+
+use std::sync::{Arc, Mutex};
+
+use http::{HeaderMap, HeaderValue, Method, Request, Response, StatusCode};
+use http_body_util::{BodyExt, Full};
+use hyper::body::Bytes;
+
+use crate::{
+    middleware::Middleware,
+    router::{ResponseBody, Router},
+    App,
+};
+
+/// Sends requests to an app without a server, so tests need no network.
+///
+/// Requests go through the router like those of a server, including all
+/// middlewares, but have no client address.
+///
+/// # Examples
+///
+/// ```
+/// use genuine::{assert_status, testing::TestClient, App, Param};
+///
+/// fn greet(name: Param<String>) -> String {
+///     format!("Hello, {}!", *name)
+/// }
+///
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// let mut app = App::new();
+/// app.get("/greet/{name}", greet as fn(_) -> _).unwrap();
+/// let client = TestClient::new(app);
+///
+/// let res = client.get("/greet/Ferris").send().await;
+/// assert_status!(res, 200);
+/// assert_eq!(res.text().await, "Hello, Ferris!");
+/// # });
+/// ```
+#[derive(Clone)]
+pub struct TestClient {
+    router: Arc<Router>,
+}
+
+impl TestClient {
+    pub fn new(app: App) -> Self {
+        Self {
+            router: Arc::new(app.into_router()),
+        }
+    }
+
+    /// Sends requests through `router`, for tests that set one up by hand.
+    #[cfg(test)]
+    pub(crate) fn from_router(router: Router) -> Self {
+        Self {
+            router: Arc::new(router),
+        }
+    }
+
+    /// Start a request with `method` to `uri`.
+    pub fn request(&self, method: Method, uri: &str) -> TestRequest {
+        TestRequest {
+            router: Arc::clone(&self.router),
+            req: Request::builder().method(method).uri(uri),
+            body: Bytes::new(),
+        }
+    }
+
+    pub fn get(&self, uri: &str) -> TestRequest {
+        self.request(Method::GET, uri)
+    }
+
+    pub fn post(&self, uri: &str) -> TestRequest {
+        self.request(Method::POST, uri)
+    }
+
+    pub fn put(&self, uri: &str) -> TestRequest {
+        self.request(Method::PUT, uri)
+    }
+
+    pub fn patch(&self, uri: &str) -> TestRequest {
+        self.request(Method::PATCH, uri)
+    }
+
+    pub fn delete(&self, uri: &str) -> TestRequest {
+        self.request(Method::DELETE, uri)
+    }
+}
+
+/// A request of a [`TestClient`], sent with [`TestRequest::send`].
+pub struct TestRequest {
+    router: Arc<Router>,
+    req: http::request::Builder,
+    body: Bytes,
+}
+
+impl TestRequest {
+    /// Add a header.
+    ///
+    /// # Panics
+    ///
+    /// Sending the request panics if the name or value is invalid.
+    pub fn header<V>(mut self, name: &str, value: V) -> Self
+    where
+        HeaderValue: TryFrom<V>,
+        <HeaderValue as TryFrom<V>>::Error: Into<http::Error>,
+    {
+        self.req = self.req.header(name, value);
+        self
+    }
+
+    pub fn body(mut self, body: impl Into<Bytes>) -> Self {
+        self.body = body.into();
+        self
+    }
+
+    /// Send `value` as a JSON body.
+    #[cfg(feature = "serde_json")]
+    pub fn json<T: serde::Serialize>(self, value: &T) -> Self {
+        let body = serde_json::to_vec(value).expect("value is not serializable as JSON");
+        self.header("content-type", "application/json").body(body)
+    }
+
+    /// Sends the request through the router of the app.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the URI or a header is invalid.
+    pub async fn send(self) -> TestResponse {
+        let req = self
+            .req
+            .body(Full::new(self.body))
+            .expect("invalid test request");
+        let res = self
+            .router
+            .respond(req)
+            .await
+            .expect("buffered bodies are always valid");
+        TestResponse { res }
+    }
+}
+
+/// The response to a [`TestRequest`].
+#[derive(Debug)]
+pub struct TestResponse {
+    res: Response<ResponseBody>,
+}
+
+impl TestResponse {
+    pub fn status(&self) -> StatusCode {
+        self.res.status()
+    }
+
+    pub fn headers(&self) -> &HeaderMap {
+        self.res.headers()
+    }
+
+    /// Returns the first value of the header called `name`, if it is text.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.res.headers().get(name)?.to_str().ok()
+    }
+
+    /// Reads the whole body, including all chunks of streamed ones.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a stream fails.
+    pub async fn bytes(self) -> Bytes {
+        self.res
+            .into_body()
+            .collect()
+            .await
+            .expect("response stream failed")
+            .to_bytes()
+    }
+
+    /// Reads the whole body as text, replacing invalid UTF-8.
+    pub async fn text(self) -> String {
+        String::from_utf8_lossy(&self.bytes().await).into_owned()
+    }
+
+    /// Reads the whole body as JSON.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the body is not a JSON `T`.
+    #[cfg(feature = "serde_json")]
+    pub async fn json<T: serde::de::DeserializeOwned>(self) -> T {
+        let body = self.bytes().await;
+        match serde_json::from_slice(&body) {
+            Ok(value) => value,
+            Err(err) => panic!(
+                "response body is not the expected JSON: {err}\n{}",
+                String::from_utf8_lossy(&body)
+            ),
+        }
+    }
+}
+
+/// A middleware that records its name whenever it is called.
+///
+/// All recorders created from one another with
+/// [`MiddlewareOrderRecorder::named`] write to the same log, which shows the
+/// order they ran in.
+///
+/// # Examples
+///
+/// ```
+/// use genuine::{middleware::Middleware, testing::MiddlewareOrderRecorder};
+/// use http::Request;
+/// use hyper::body::Bytes;
+///
+/// let auth = MiddlewareOrderRecorder::new("auth");
+/// let log = auth.named("log");
+///
+/// let mut req = Request::new(Bytes::new());
+/// log.before(&mut req);
+/// auth.before(&mut req);
+///
+/// assert_eq!(auth.calls(), ["log", "auth"]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct MiddlewareOrderRecorder {
+    name: String,
+    calls: Arc<Mutex<Vec<String>>>,
+}
+
+impl MiddlewareOrderRecorder {
+    /// Create a recorder with a log of its own.
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            calls: Arc::default(),
+        }
+    }
+
+    /// Create a recorder called `name` that writes to the same log.
+    pub fn named(&self, name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            calls: Arc::clone(&self.calls),
+        }
+    }
+
+    /// Returns the log, e.g. to record handler calls in it as well.
+    pub fn shared(&self) -> Arc<Mutex<Vec<String>>> {
+        Arc::clone(&self.calls)
+    }
+
+    /// Returns the names recorded so far, in call order.
+    pub fn calls(&self) -> Vec<String> {
+        self.calls.lock().unwrap().clone()
+    }
+
+    fn record(&self) {
+        self.calls.lock().unwrap().push(self.name.clone());
+    }
+}
+
+impl Middleware for MiddlewareOrderRecorder {
+    fn before(&self, _req: &mut Request<Bytes>) -> Option<Response<Bytes>> {
+        self.record();
+        None
+    }
+
+    fn after(&self, _res: &mut Response<Bytes>) {
+        self.record();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Named, Param, ParamName};
+
+    struct Id;
+
+    impl ParamName for Id {
+        const NAME: &'static str = "id";
+    }
+
+    fn show(user: Param<String>, id: Named<Id, u32>) -> String {
+        format!("post #{} by {}", *id, *user)
+    }
+
+    fn client() -> TestClient {
+        let mut app = App::new();
+        app.get("/users/{user}/posts/{id}", show as fn(_, _) -> _)
+            .unwrap();
+        app.default_response_header("x-app", HeaderValue::from_static("genuine"));
+        TestClient::new(app)
+    }
+
+    #[tokio::test]
+    async fn requests_reach_the_handlers() {
+        let res = client().get("/users/ferris/posts/42").send().await;
+
+        crate::assert_status!(res, 200);
+        assert_eq!(res.header("x-app"), Some("genuine"));
+        assert_eq!(res.text().await, "post #42 by ferris");
+    }
+
+    #[tokio::test]
+    async fn requests_without_route_are_not_found() {
+        let res = client().get("/users/ferris").send().await;
+        crate::assert_status!(res, StatusCode::NOT_FOUND);
+
+        let res = client().delete("/users/ferris/posts/42").send().await;
+        crate::assert_status!(res, 405);
+        assert_eq!(res.header("allow"), Some("GET, HEAD, OPTIONS"));
+    }
+
+    #[cfg(feature = "serde_json")]
+    #[tokio::test]
+    async fn json_bodies_round_trip() {
+        use crate::extract::Json;
+
+        #[derive(Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+        struct User {
+            name: String,
+        }
+
+        fn create(Json(user): Json<User>) -> (StatusCode, Json<User>) {
+            (StatusCode::CREATED, Json(user))
+        }
+
+        let mut app = App::new();
+        app.post("/users", create as fn(_) -> _).unwrap();
+        let client = TestClient::new(app);
+
+        let user = User {
+            name: "ferris".into(),
+        };
+        let res = client.post("/users").json(&user).send().await;
+        crate::assert_status!(res, 201);
+        assert_eq!(res.json::<User>().await, user);
+
+        let res = client.post("/users").body("{}").send().await;
+        crate::assert_status!(res, 415);
+    }
+}
@@ -0,0 +1,160 @@
+//! Serving HTTPS with [`rustls`].
+//!
+//! A server needs a certificate chain and the matching private key, e.g.
+//! loaded from PEM files:
+//!
+//! ```no_run
+//! use std::sync::Arc;
+//!
+//! use genuine::{
+//!     tls::rustls::{
+//!         pki_types::{pem::PemObject, CertificateDer, PrivateKeyDer},
+//!         ServerConfig,
+//!     },
+//!     App,
+//! };
+//!
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let certs = CertificateDer::pem_file_iter("cert.pem")?.collect::<Result<Vec<_>, _>>()?;
+//! let key = PrivateKeyDer::from_pem_file("key.pem")?;
+//!
+//! let config = ServerConfig::builder()
+//!     .with_no_client_auth()
+//!     .with_single_cert(certs, key)?;
+//!
+//! let app = App::new();
+//! app.run_tls(([0, 0, 0, 0], 443), Arc::new(config))?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! With a [`ServerBuilder`](crate::ServerBuilder) that [serves
+//! HTTP/2](crate::ServerBuilder::http2), clients can pick it with ALPN:
+//!
+//! ```no_run
+//! # use std::sync::Arc;
+//! # use genuine::{tls::rustls::ServerConfig, App, ServerBuilder};
+//! # fn run(config: ServerConfig) -> std::io::Result<()> {
+//! let app = App::new();
+//! let builder = ServerBuilder::new().http2(true);
+//! app.run_tls_with(([0, 0, 0, 0], 443), builder, Arc::new(config))?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::{future::Future, io, sync::Arc};
+
+pub use tokio_rustls::rustls;
+
+use crate::Server;
+
+/// A [`Server`] that serves HTTPS, see [`Server::tls`].
+pub struct TlsServer(Server);
+
+impl Server {
+    /// Serve HTTPS instead of plain HTTP, i.e. do a TLS handshake with
+    /// `config` on every accepted connection.
+    ///
+    /// If the server [serves HTTP/2](crate::ServerBuilder::http2) and
+    /// `config` has no ALPN protocols, it offers `h2` and `http/1.1`.
+    pub fn tls(mut self, config: Arc<rustls::ServerConfig>) -> TlsServer {
+        self.tls = Some(config);
+        TlsServer(self)
+    }
+}
+
+impl TlsServer {
+    /// See [`Server::with_shutdown`].
+    pub fn with_shutdown<F>(self, signal: F) -> Self
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        Self(self.0.with_shutdown(signal))
+    }
+
+    pub async fn bind(self) -> io::Result<()> {
+        self.0.bind().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use http::Method;
+    use rustls::{
+        pki_types::{PrivateKeyDer, ServerName},
+        ClientConfig, RootCertStore, ServerConfig,
+    };
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::{TcpListener, TcpStream},
+    };
+    use tokio_rustls::TlsConnector;
+
+    use super::*;
+    use crate::{App, ServerBuilder};
+
+    /// Serves `GET /` over TLS with a self-signed certificate for
+    /// `localhost` and returns a client config that trusts it.
+    async fn serve(http2: bool) -> (std::net::SocketAddr, ClientConfig) {
+        let rcgen::CertifiedKey { cert, signing_key } =
+            rcgen::generate_simple_self_signed(["localhost".to_owned()]).unwrap();
+        let key = PrivateKeyDer::Pkcs8(signing_key.serialize_der().into());
+        let config = ServerConfig::builder()
+            .with_no_client_auth()
+            .with_single_cert(vec![cert.der().clone()], key)
+            .unwrap();
+
+        let mut app = App::new();
+        app.add(Method::GET, "/", (|| "secret") as fn() -> _)
+            .unwrap();
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = ServerBuilder::new().http2(http2).build(addr, app);
+        let TlsServer(server) = server.tls(Arc::new(config));
+        tokio::spawn(server.serve(listener));
+
+        let mut roots = RootCertStore::empty();
+        roots.add(cert.der().clone()).unwrap();
+        let client = ClientConfig::builder()
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+        (addr, client)
+    }
+
+    async fn connect(
+        addr: std::net::SocketAddr,
+        client: ClientConfig,
+    ) -> tokio_rustls::client::TlsStream<TcpStream> {
+        let stream = TcpStream::connect(addr).await.unwrap();
+        TlsConnector::from(Arc::new(client))
+            .connect(ServerName::try_from("localhost").unwrap(), stream)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn requests_are_served_over_tls() {
+        let (addr, client) = serve(false).await;
+        let mut stream = connect(addr, client).await;
+
+        stream
+            .write_all(b"GET / HTTP/1.1\r\nhost: localhost\r\nconnection: close\r\n\r\n")
+            .await
+            .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+
+        assert!(response.starts_with("HTTP/1.1 200 OK"), "{response}");
+        assert!(response.ends_with("secret"), "{response}");
+    }
+
+    #[tokio::test]
+    async fn http2_is_negotiated_with_alpn() {
+        let (addr, mut client) = serve(true).await;
+        client.alpn_protocols = vec![b"h2".to_vec()];
+
+        let stream = connect(addr, client).await;
+        assert_eq!(stream.get_ref().1.alpn_protocol(), Some(&b"h2"[..]));
+    }
+}
@@ -0,0 +1,280 @@
+//! Building URLs.
+
+use std::{
+    collections::HashMap,
+    convert::Infallible,
+    fmt::{self, Display, Write},
+    sync::Arc,
+};
+
+use crate::{FromRequest, Part, Path, Request, Route};
+
+/// Builds a URL with a query string, percent-encoding names and values.
+///
+/// # Examples
+///
+/// ```
+/// use genuine::url::QueryBuilder;
+///
+/// let url = QueryBuilder::new("/users")
+///     .param("page", 2)
+///     .param("name", "Jane Doe")
+///     .build();
+///
+/// assert_eq!(url, "/users?page=2&name=Jane%20Doe");
+/// ```
+#[derive(Debug, Clone)]
+pub struct QueryBuilder {
+    url: String,
+    has_query: bool,
+}
+
+impl QueryBuilder {
+    /// Start building from `base`, which may already contain a query string.
+    pub fn new(base: impl Into<String>) -> Self {
+        let url = base.into();
+        let has_query = url.contains('?');
+        Self { url, has_query }
+    }
+
+    /// Append `name=value` to the query string.
+    pub fn param(mut self, name: &str, value: impl Display) -> Self {
+        self.url.push(if self.has_query { '&' } else { '?' });
+        self.has_query = true;
+
+        // writing to a `String` cannot fail
+        write!(
+            self.url,
+            "{}={}",
+            Encoded(name),
+            Encoded(&value.to_string())
+        )
+        .unwrap();
+        self
+    }
+
+    pub fn build(self) -> String {
+        self.url
+    }
+}
+
+/// A URL could not be built, see [`Urls::url_for`].
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum UrlGenerationError {
+    #[error("no route is named `{0}`")]
+    UnknownRoute(String),
+    #[error("route `{route}` needs a value for parameter `{param}`")]
+    MissingParam { route: String, param: String },
+}
+
+/// Builds the URLs of named routes, see [`Route::name`].
+///
+/// Handlers get it as an extractor, e.g. to redirect to another route after
+/// a `POST` request. Outside of handlers, use
+/// [`App::url_for`](crate::App::url_for).
+///
+/// # Examples
+///
+/// ```
+/// use genuine::url::Urls;
+/// use http::{header::LOCATION, Response, StatusCode};
+/// use hyper::body::Bytes;
+///
+/// fn create_user(urls: Urls) -> Response<Bytes> {
+///     let url = urls.url_for("user", &[("id", "42")]).unwrap();
+///
+///     Response::builder()
+///         .status(StatusCode::SEE_OTHER)
+///         .header(LOCATION, url)
+///         .body(Bytes::new())
+///         .unwrap()
+/// }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Urls(Arc<HashMap<String, Path>>);
+
+impl Urls {
+    /// Collects the paths of the named `routes`. If several routes have the
+    /// same name, the first one wins.
+    pub(crate) fn new<'r>(routes: impl IntoIterator<Item = &'r Route>) -> Self {
+        let mut paths = HashMap::new();
+        for route in routes {
+            if let Some(name) = &route.name {
+                paths
+                    .entry(name.clone())
+                    .or_insert_with(|| route.path.clone());
+            }
+        }
+        Self(Arc::new(paths))
+    }
+
+    /// Returns the URL of the route called `name`, with its parameters
+    /// replaced by the values in `params`.
+    ///
+    /// Values are percent-encoded. Those of wildcards keep their slashes.
+    /// Params that the route path does not have are appended as query
+    /// string, like with [`QueryBuilder`].
+    pub fn url_for(
+        &self,
+        name: &str,
+        params: &[(&str, &str)],
+    ) -> Result<String, UrlGenerationError> {
+        let path = self
+            .0
+            .get(name)
+            .ok_or_else(|| UrlGenerationError::UnknownRoute(name.to_owned()))?;
+
+        let mut url = String::new();
+        let mut used = Vec::new();
+        for part in path.parts() {
+            let (param, wildcard) = match part {
+                Part::Literal(literal) => {
+                    url.push_str(&String::from_utf8_lossy(literal));
+                    continue;
+                }
+                Part::Param { name } => (name, false),
+                Part::Wildcard { name } => (name, true),
+            };
+
+            let value = params
+                .iter()
+                .find(|(name, _)| name == param)
+                .map(|(_, value)| value)
+                .ok_or_else(|| UrlGenerationError::MissingParam {
+                    route: name.to_owned(),
+                    param: param.clone(),
+                })?;
+            used.push(param.as_str());
+
+            // writing to a `String` cannot fail
+            if wildcard {
+                for (i, segment) in value.split('/').enumerate() {
+                    if i > 0 {
+                        url.push('/');
+                    }
+                    write!(url, "{}", Encoded(segment)).unwrap();
+                }
+            } else {
+                write!(url, "{}", Encoded(value)).unwrap();
+            }
+        }
+
+        let query = params.iter().filter(|(name, _)| !used.contains(name));
+        Ok(query
+            .fold(QueryBuilder::new(url), |url, (name, value)| {
+                url.param(name, value)
+            })
+            .build())
+    }
+}
+
+impl FromRequest for Urls {
+    type Error = Infallible;
+
+    fn from_request(req: &Request) -> Result<Self, Self::Error> {
+        Ok(req.extensions().get::<Urls>().cloned().unwrap_or_default())
+    }
+}
+
+/// Percent-encodes everything but the unreserved characters of RFC 3986.
+struct Encoded<'a>(&'a str);
+
+impl Display for Encoded<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for &byte in self.0.as_bytes() {
+            match byte {
+                b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                    f.write_char(byte as char)?
+                }
+                _ => write!(f, "%{byte:02X}")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn params_are_joined() {
+        let url = QueryBuilder::new("/users")
+            .param("page", 2)
+            .param("limit", 10)
+            .build();
+        assert_eq!(url, "/users?page=2&limit=10");
+    }
+
+    #[test]
+    fn special_characters_are_encoded() {
+        let url = QueryBuilder::new("/search")
+            .param("q", "a&b=c d/ü")
+            .param("sort by", "name")
+            .build();
+        assert_eq!(url, "/search?q=a%26b%3Dc%20d%2F%C3%BC&sort%20by=name");
+    }
+
+    #[test]
+    fn empty_values_are_kept() {
+        let url = QueryBuilder::new("/items").param("filter", "").build();
+        assert_eq!(url, "/items?filter=");
+    }
+
+    #[test]
+    fn existing_query_is_extended() {
+        let url = QueryBuilder::new("/items?page=1").param("limit", 5).build();
+        assert_eq!(url, "/items?page=1&limit=5");
+    }
+
+    #[test]
+    fn no_params_leaves_base_untouched() {
+        assert_eq!(QueryBuilder::new("/items").build(), "/items");
+    }
+
+    fn urls() -> Urls {
+        let route = |path: &str, name: &str| {
+            let path = Path::new(path.to_owned()).unwrap();
+            let mut route = Route::new(http::Method::GET, path, Box::new((|| "") as fn() -> _));
+            route.name(name);
+            route
+        };
+        Urls::new(&[
+            route("/users/{id}/posts/{post}", "post"),
+            route("/files/{*path}", "file"),
+        ])
+    }
+
+    #[test]
+    fn params_are_substituted() {
+        let url = urls().url_for("post", &[("post", "7"), ("id", "42")]);
+        assert_eq!(url.unwrap(), "/users/42/posts/7");
+
+        let url = urls().url_for("post", &[("id", "a b"), ("post", "x/y")]);
+        assert_eq!(url.unwrap(), "/users/a%20b/posts/x%2Fy");
+
+        let url = urls().url_for("file", &[("path", "docs/read me.md")]);
+        assert_eq!(url.unwrap(), "/files/docs/read%20me.md");
+    }
+
+    #[test]
+    fn extra_params_are_appended_as_query() {
+        let url = urls().url_for("post", &[("id", "42"), ("post", "7"), ("page", "2")]);
+        assert_eq!(url.unwrap(), "/users/42/posts/7?page=2");
+    }
+
+    #[test]
+    fn unknown_routes_and_missing_params_are_errors() {
+        assert_eq!(
+            urls().url_for("user", &[]),
+            Err(UrlGenerationError::UnknownRoute("user".into()))
+        );
+        assert_eq!(
+            urls().url_for("post", &[("id", "42")]),
+            Err(UrlGenerationError::MissingParam {
+                route: "post".into(),
+                param: "post".into(),
+            })
+        );
+    }
+}
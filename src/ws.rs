@@ -0,0 +1,122 @@
+//! WebSocket support.
+//!
+//! With the `ws` feature, handlers accept WebSocket connections with the
+//! [`WebSocketUpgrade`] extractor.
+
+#[cfg(feature = "ws")]
+mod upgrade;
+
+use http::{header::SEC_WEBSOCKET_PROTOCOL, HeaderMap, Response, StatusCode};
+use hyper::body::Bytes;
+#[cfg(feature = "ws")]
+pub use tokio_tungstenite::tungstenite::Message;
+#[cfg(feature = "ws")]
+pub use upgrade::{UpgradeRejection, WebSocket, WebSocketUpgrade};
+
+use crate::IntoResponse;
+
+/// The client offered subprotocols of which the server supports none.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("none of the requested WebSocket subprotocols is supported")]
+pub struct ProtocolMismatch;
+
+impl IntoResponse for ProtocolMismatch {
+    fn into_response(self) -> Response<Bytes> {
+        Response::builder()
+            .status(StatusCode::BAD_REQUEST)
+            .body(self.to_string().into())
+            .unwrap()
+    }
+}
+
+/// Selects the subprotocol for a WebSocket upgrade.
+///
+/// `supported` lists the subprotocols of the server in order of preference.
+/// The first of them that the client offers in its `Sec-WebSocket-Protocol`
+/// headers is chosen, and should be echoed in the `101 Switching Protocols`
+/// response.
+///
+/// Returns `Ok(None)` if the client did not ask for a subprotocol and
+/// [`ProtocolMismatch`] if none of the offered ones is supported, in which
+/// case the upgrade must fail.
+///
+/// [`WebSocketUpgrade::accept_protocols`] does this for a handshake.
+///
+/// # Examples
+///
+/// ```
+/// use genuine::ws::select_protocol;
+/// use http::{header::SEC_WEBSOCKET_PROTOCOL, HeaderMap, HeaderValue};
+///
+/// let mut headers = HeaderMap::new();
+/// headers.insert(SEC_WEBSOCKET_PROTOCOL, HeaderValue::from_static("graphql-ws, chat"));
+///
+/// let protocol = select_protocol(&headers, &["chat", "graphql-ws"]);
+/// assert_eq!(protocol, Ok(Some("chat")));
+/// ```
+pub fn select_protocol<'a>(
+    headers: &HeaderMap,
+    supported: &[&'a str],
+) -> Result<Option<&'a str>, ProtocolMismatch> {
+    let mut offered = headers
+        .get_all(SEC_WEBSOCKET_PROTOCOL)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(str::trim)
+        .filter(|protocol| !protocol.is_empty())
+        .peekable();
+
+    if offered.peek().is_none() {
+        return Ok(None);
+    }
+
+    let offered: Vec<_> = offered.collect();
+    supported
+        .iter()
+        .find(|protocol| offered.contains(protocol))
+        .map(|protocol| Some(*protocol))
+        .ok_or(ProtocolMismatch)
+}
+
+#[cfg(test)]
+mod tests {
+    use http::HeaderValue;
+
+    use super::*;
+
+    fn offering(protocols: &[&'static str]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for protocol in protocols {
+            headers.append(SEC_WEBSOCKET_PROTOCOL, HeaderValue::from_static(protocol));
+        }
+        headers
+    }
+
+    #[test]
+    fn negotiates_supported_protocol() {
+        let headers = offering(&["graphql-ws"]);
+        let protocol = select_protocol(&headers, &["chat", "graphql-ws"]);
+        assert_eq!(protocol, Ok(Some("graphql-ws")));
+    }
+
+    #[test]
+    fn prefers_server_order_across_header_values() {
+        let headers = offering(&["graphql-ws, superchat", "chat"]);
+        let protocol = select_protocol(&headers, &["chat", "graphql-ws"]);
+        assert_eq!(protocol, Ok(Some("chat")));
+    }
+
+    #[test]
+    fn no_offered_protocol_means_none() {
+        let protocol = select_protocol(&HeaderMap::new(), &["chat"]);
+        assert_eq!(protocol, Ok(None));
+    }
+
+    #[test]
+    fn mismatch_is_a_bad_request() {
+        let headers = offering(&["mqtt"]);
+        let err = select_protocol(&headers, &["chat", "graphql-ws"]).unwrap_err();
+        assert_eq!(err.into_response().status(), StatusCode::BAD_REQUEST);
+    }
+}
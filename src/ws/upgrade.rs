@@ -0,0 +1,415 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use futures_core::Stream;
+use futures_sink::Sink;
+use http::{
+    header::{
+        CONNECTION, SEC_WEBSOCKET_ACCEPT, SEC_WEBSOCKET_KEY, SEC_WEBSOCKET_PROTOCOL,
+        SEC_WEBSOCKET_VERSION, UPGRADE,
+    },
+    HeaderMap, HeaderName, HeaderValue, Method, Response, StatusCode,
+};
+use hyper::{
+    body::Bytes,
+    upgrade::{OnUpgrade, Upgraded},
+};
+use hyper_util::rt::TokioIo;
+use tokio_tungstenite::{
+    tungstenite::{self, handshake::derive_accept_key, protocol::Role, Message},
+    WebSocketStream,
+};
+
+use super::{select_protocol, ProtocolMismatch};
+use crate::{FromRequest, IntoResponse, Rejection, Request};
+
+/// An established WebSocket connection.
+///
+/// Receive [`Message`]s through its `Stream` and send them through its
+/// `Sink` implementation.
+#[derive(Debug)]
+pub struct WebSocket {
+    inner: WebSocketStream<TokioIo<Upgraded>>,
+    protocol: Option<String>,
+}
+
+impl WebSocket {
+    /// Returns the subprotocol that was agreed on, see
+    /// [`WebSocketUpgrade::accept_protocols`].
+    pub fn protocol(&self) -> Option<&str> {
+        self.protocol.as_deref()
+    }
+}
+
+impl Stream for WebSocket {
+    type Item = Result<Message, tungstenite::Error>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        Pin::new(&mut self.inner).poll_next(cx)
+    }
+}
+
+impl Sink<Message> for WebSocket {
+    type Error = tungstenite::Error;
+
+    fn poll_ready(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Pin::new(&mut self.inner).poll_ready(cx)
+    }
+
+    fn start_send(mut self: Pin<&mut Self>, item: Message) -> Result<(), Self::Error> {
+        Pin::new(&mut self.inner).start_send(item)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Pin::new(&mut self.inner).poll_close(cx)
+    }
+}
+
+/// The request is not a valid WebSocket handshake, see
+/// [`WebSocketUpgrade`].
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum UpgradeRejection {
+    #[error("WebSocket handshakes must use GET")]
+    MethodNotGet,
+    #[error("missing `Connection: upgrade` header")]
+    MissingConnectionUpgrade,
+    #[error("missing `Upgrade: websocket` header")]
+    MissingUpgradeWebSocket,
+    #[error("unsupported WebSocket version, only 13 is supported")]
+    UnsupportedVersion,
+    #[error("missing `Sec-WebSocket-Key` header")]
+    MissingKey,
+    #[error("the connection cannot be upgraded")]
+    NotUpgradable,
+}
+
+impl IntoResponse for UpgradeRejection {
+    fn into_response(self) -> Response<Bytes> {
+        let mut res = Response::new(self.to_string().into());
+        *res.status_mut() = StatusCode::BAD_REQUEST;
+        if self == UpgradeRejection::UnsupportedVersion {
+            *res.status_mut() = StatusCode::UPGRADE_REQUIRED;
+            res.headers_mut()
+                .insert(SEC_WEBSOCKET_VERSION, HeaderValue::from_static("13"));
+        }
+        res
+    }
+}
+
+/// A valid WebSocket handshake, ready to be accepted with
+/// [`WebSocketUpgrade::on_upgrade`].
+///
+/// # Examples
+///
+/// ```
+/// use futures::{SinkExt, StreamExt};
+/// use genuine::{ws::WebSocketUpgrade, App, IntoResponse};
+/// use http::Method;
+///
+/// async fn echo(upgrade: WebSocketUpgrade) -> impl IntoResponse {
+///     upgrade.on_upgrade(|mut ws| async move {
+///         while let Some(Ok(message)) = ws.next().await {
+///             if message.is_text() && ws.send(message).await.is_err() {
+///                 break;
+///             }
+///         }
+///     })
+/// }
+///
+/// let mut app = App::new();
+/// app.add_async(Method::GET, "/echo", echo as fn(_) -> _)
+///     .unwrap();
+/// ```
+///
+/// Invalid handshakes are answered with their [`UpgradeRejection`].
+#[derive(Debug)]
+pub struct WebSocketUpgrade {
+    accept: HeaderValue,
+    on_upgrade: OnUpgrade,
+    /// The `Sec-WebSocket-Protocol` headers of the handshake.
+    offered: HeaderMap,
+    protocol: Option<String>,
+}
+
+impl WebSocketUpgrade {
+    /// Agree on the first of the `supported` subprotocols that the client
+    /// offers, see [`select_protocol`]. It is sent back to the client and
+    /// available through [`WebSocket::protocol`].
+    ///
+    /// Returns [`ProtocolMismatch`], which answers `400 Bad Request`, if the
+    /// client offers subprotocols but none of the supported ones.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use genuine::{
+    ///     ws::{ProtocolMismatch, WebSocketUpgrade},
+    ///     App,
+    /// };
+    /// use http::{Method, Response};
+    /// use hyper::body::Bytes;
+    ///
+    /// async fn graphql(upgrade: WebSocketUpgrade) -> Result<Response<Bytes>, ProtocolMismatch> {
+    ///     let upgrade = upgrade.accept_protocols(&["graphql-transport-ws", "graphql-ws"])?;
+    ///     Ok(upgrade.on_upgrade(|ws| async move {
+    ///         tracing::info!(protocol = ?ws.protocol(), "connected");
+    ///     }))
+    /// }
+    ///
+    /// let mut app = App::new();
+    /// app.add_async(Method::GET, "/graphql", graphql as fn(_) -> _)
+    ///     .unwrap();
+    /// ```
+    pub fn accept_protocols(mut self, supported: &[&str]) -> Result<Self, ProtocolMismatch> {
+        self.protocol = select_protocol(&self.offered, supported)?.map(str::to_owned);
+        Ok(self)
+    }
+
+    /// Accept the handshake: returns the `101 Switching Protocols` response
+    /// and calls `callback` on a new task once the connection is upgraded.
+    pub fn on_upgrade<F, Fut>(self, callback: F) -> Response<Bytes>
+    where
+        F: FnOnce(WebSocket) -> Fut + Send + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let mut res = Response::builder()
+            .status(StatusCode::SWITCHING_PROTOCOLS)
+            .header(CONNECTION, "upgrade")
+            .header(UPGRADE, "websocket")
+            .header(SEC_WEBSOCKET_ACCEPT, self.accept);
+        if let Some(protocol) = &self.protocol {
+            // offered by the client, so a valid header value
+            res = res.header(SEC_WEBSOCKET_PROTOCOL, protocol);
+        }
+
+        let protocol = self.protocol;
+        tokio::spawn(async move {
+            match self.on_upgrade.await {
+                Ok(upgraded) => {
+                    let io = TokioIo::new(upgraded);
+                    let inner = WebSocketStream::from_raw_socket(io, Role::Server, None).await;
+                    callback(WebSocket { inner, protocol }).await;
+                }
+                Err(err) => tracing::warn!(error = %err, "failed to upgrade to a WebSocket"),
+            }
+        });
+
+        res.body(Bytes::new()).unwrap()
+    }
+}
+
+/// Whether the comma-separated `name` headers list `token`, ignoring case.
+fn lists(headers: &HeaderMap, name: HeaderName, token: &str) -> bool {
+    headers
+        .get_all(name)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|listed| listed.trim().eq_ignore_ascii_case(token))
+}
+
+impl Rejection for UpgradeRejection {
+    fn respond(self) -> Response<Bytes> {
+        self.into_response()
+    }
+}
+
+impl FromRequest for WebSocketUpgrade {
+    type Error = UpgradeRejection;
+
+    fn from_request(req: &Request) -> Result<Self, Self::Error> {
+        let headers = req.headers();
+        if req.method() != Method::GET {
+            return Err(UpgradeRejection::MethodNotGet);
+        }
+        if !lists(headers, CONNECTION, "upgrade") {
+            return Err(UpgradeRejection::MissingConnectionUpgrade);
+        }
+        if !lists(headers, UPGRADE, "websocket") {
+            return Err(UpgradeRejection::MissingUpgradeWebSocket);
+        }
+        if headers
+            .get(SEC_WEBSOCKET_VERSION)
+            .map(HeaderValue::as_bytes)
+            != Some(b"13")
+        {
+            return Err(UpgradeRejection::UnsupportedVersion);
+        }
+        let key = headers
+            .get(SEC_WEBSOCKET_KEY)
+            .ok_or(UpgradeRejection::MissingKey)?;
+
+        // hyper leaves this for upgradable HTTP/1 connections
+        let on_upgrade = req
+            .extensions()
+            .get::<OnUpgrade>()
+            .cloned()
+            .ok_or(UpgradeRejection::NotUpgradable)?;
+
+        let mut offered = HeaderMap::new();
+        for protocols in headers.get_all(SEC_WEBSOCKET_PROTOCOL) {
+            offered.append(SEC_WEBSOCKET_PROTOCOL, protocols.clone());
+        }
+
+        // the accept key is base64, a valid header value
+        let accept = derive_accept_key(key.as_bytes()).parse().unwrap();
+        Ok(Self {
+            accept,
+            on_upgrade,
+            offered,
+            protocol: None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::{SinkExt, StreamExt};
+    use tokio::net::{TcpListener, TcpStream};
+    use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+
+    use super::*;
+    use crate::{testing::TestClient, App, ServerBuilder};
+
+    async fn echo(upgrade: WebSocketUpgrade) -> impl IntoResponse {
+        upgrade.on_upgrade(|mut ws| async move {
+            while let Some(Ok(message)) = ws.next().await {
+                if message.is_text() && ws.send(message).await.is_err() {
+                    break;
+                }
+            }
+        })
+    }
+
+    fn echo_app() -> App {
+        let mut app = App::new();
+        app.add_async(Method::GET, "/echo", echo as fn(_) -> _)
+            .unwrap();
+        app
+    }
+
+    async fn serve() -> std::net::SocketAddr {
+        let app = echo_app();
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = ServerBuilder::new().build(addr, app);
+        tokio::spawn(server.serve(listener));
+        addr
+    }
+
+    #[tokio::test]
+    async fn messages_are_echoed() {
+        let addr = serve().await;
+        let stream = TcpStream::connect(addr).await.unwrap();
+        let (mut ws, res) = tokio_tungstenite::client_async(format!("ws://{addr}/echo"), stream)
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::SWITCHING_PROTOCOLS);
+
+        ws.send(Message::text("ping")).await.unwrap();
+        assert_eq!(ws.next().await.unwrap().unwrap(), Message::text("ping"));
+    }
+
+    #[tokio::test]
+    async fn invalid_handshakes_reach_the_client() {
+        let client = TestClient::new(echo_app());
+
+        let res = client.get("/echo").send().await;
+        crate::assert_status!(res, 400);
+        assert_eq!(res.text().await, "missing `Connection: upgrade` header");
+
+        let res = client
+            .get("/echo")
+            .header("connection", "upgrade")
+            .header("upgrade", "websocket")
+            .header("sec-websocket-version", "8")
+            .send()
+            .await;
+        crate::assert_status!(res, 426);
+        assert_eq!(res.header("sec-websocket-version"), Some("13"));
+    }
+
+    /// Greets clients with the subprotocol that was agreed on.
+    async fn graphql(upgrade: WebSocketUpgrade) -> Result<Response<Bytes>, ProtocolMismatch> {
+        let upgrade = upgrade.accept_protocols(&["graphql-transport-ws", "graphql-ws"])?;
+        Ok(upgrade.on_upgrade(|mut ws| async move {
+            let protocol = ws.protocol().unwrap_or("none").to_owned();
+            ws.send(Message::text(protocol)).await.ok();
+        }))
+    }
+
+    async fn connect_offering(protocol: &'static str) -> Result<(), tungstenite::Error> {
+        let mut app = App::new();
+        app.add_async(Method::GET, "/graphql", graphql as fn(_) -> _)
+            .unwrap();
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(ServerBuilder::new().build(addr, app).serve(listener));
+
+        let mut req = format!("ws://{addr}/graphql").into_client_request()?;
+        req.headers_mut()
+            .insert(SEC_WEBSOCKET_PROTOCOL, HeaderValue::from_static(protocol));
+        let stream = TcpStream::connect(addr).await.unwrap();
+        let (mut ws, res) = tokio_tungstenite::client_async(req, stream).await?;
+
+        assert_eq!(res.headers()[SEC_WEBSOCKET_PROTOCOL], "graphql-ws");
+        assert_eq!(ws.next().await.unwrap()?, Message::text("graphql-ws"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn offered_protocols_are_accepted() {
+        connect_offering("mqtt, graphql-ws").await.unwrap();
+
+        let err = connect_offering("mqtt").await.unwrap_err();
+        assert!(
+            matches!(&err, tungstenite::Error::Http(res) if res.status() == 400),
+            "{err}"
+        );
+    }
+
+    fn request(headers: &[(&str, &str)]) -> Request {
+        let mut req = http::Request::get("/echo");
+        for (name, value) in headers {
+            req = req.header(*name, *value);
+        }
+        Request::new(req.body(Bytes::new()).unwrap(), Vec::new())
+    }
+
+    #[test]
+    fn invalid_handshakes_are_rejected() {
+        let handshake = [
+            ("connection", "keep-alive, Upgrade"),
+            ("upgrade", "websocket"),
+            ("sec-websocket-version", "13"),
+            ("sec-websocket-key", "dGhlIHNhbXBsZSBub25jZQ=="),
+        ];
+        let reject = |headers: &[(&str, &str)]| {
+            WebSocketUpgrade::from_request(&request(headers)).unwrap_err()
+        };
+
+        assert_eq!(
+            reject(&handshake[1..]),
+            UpgradeRejection::MissingConnectionUpgrade
+        );
+        assert_eq!(
+            reject(&handshake[..1]),
+            UpgradeRejection::MissingUpgradeWebSocket
+        );
+        assert_eq!(
+            reject(&handshake[..2]),
+            UpgradeRejection::UnsupportedVersion
+        );
+        assert_eq!(reject(&handshake[..3]), UpgradeRejection::MissingKey);
+        // not served by hyper
+        assert_eq!(reject(&handshake), UpgradeRejection::NotUpgradable);
+    }
+}